@@ -0,0 +1,67 @@
+//! A REST map agent, serving any `openlr::map::Map` over the JSON contract spoken
+//! by `openlr_server::http_map_proxy::HttpMapProxy`.
+
+use actix_web::{web, HttpResponse};
+use geo::Coord;
+use openlr::edge::Edge;
+use openlr::map::Map;
+use openlr_server::http_map_proxy::{
+    NextEdgeRequest, NextEdgeResponse, RadiusSearchRequest, RadiusSearchResponse,
+};
+
+/// Registers the map agent endpoints.  The map they serve must be registered as
+/// application data of type `web::Data<dyn Map>`.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.route("/nearby_edges", web::post().to(nearby_edges))
+        .route("/next_edges", web::post().to(next_edges))
+        .route("/previous_edges", web::post().to(previous_edges));
+}
+
+async fn nearby_edges(
+    map: web::Data<dyn Map>,
+    req: web::Json<RadiusSearchRequest>,
+) -> HttpResponse {
+    let points = req
+        .points
+        .iter()
+        .map(|p| Coord { x: p.lon, y: p.lat })
+        .collect::<Vec<Coord>>();
+
+    // Each point carries its own radius, whereas the map searches around all points
+    // with the same radius: only fall back to one search per point if the radii differ
+    let edge_sets = match req.points.first() {
+        Some(first) if req.points.iter().any(|p| p.radius != first.radius) => {
+            let mut edge_sets = Vec::with_capacity(points.len());
+            for (point, p) in points.iter().zip(req.points.iter()) {
+                match map.get_nearby_edges(vec![*point], p.radius).await {
+                    Ok(mut edges) => edge_sets.push(edges.pop().unwrap_or_default()),
+                    Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+                }
+            }
+            edge_sets
+        }
+        Some(first) => match map.get_nearby_edges(points, first.radius).await {
+            Ok(edge_sets) => edge_sets,
+            Err(e) => return HttpResponse::InternalServerError().body(e.to_string()),
+        },
+        None => Vec::<Vec<Edge>>::new(),
+    };
+
+    HttpResponse::Ok().json(RadiusSearchResponse(edge_sets))
+}
+
+async fn next_edges(map: web::Data<dyn Map>, req: web::Json<NextEdgeRequest>) -> HttpResponse {
+    let req = req.into_inner();
+    match map.get_next_edges(req.id, req.meta).await {
+        Ok(edges) => HttpResponse::Ok().json(NextEdgeResponse(edges)),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
+
+async fn previous_edges(map: web::Data<dyn Map>, req: web::Json<NextEdgeRequest>) -> HttpResponse {
+    let req = req.into_inner();
+    match map.get_previous_edges(req.id, req.meta).await {
+        Ok(edges) => HttpResponse::Ok().json(NextEdgeResponse(edges)),
+        Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+    }
+}
@@ -1,3 +1,40 @@
-fn main() {
-    println!("Hello, world!");
+use std::sync::Arc;
+
+use actix_web::{web, App, HttpServer};
+use clap::Parser;
+use mock_map_agent::mock_map::MockMap;
+use openlr::map::Map;
+
+#[derive(Parser, Default, Debug)]
+#[clap(author = "TomTom International", version, about)]
+/// Mock Map REST MapServer
+pub struct Arguments {
+    /// CSV file from which the map will be derived
+    file_path: String,
+    #[clap(default_value_t=String::from("127.0.0.1:8081"),short, long)]
+    /// Address to listen on (i.e. "127.0.0.1:8081")
+    address: String,
+}
+
+#[actix_web::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    simple_logger::init_with_env().unwrap();
+    let args = Arguments::parse();
+    let map: Arc<dyn Map> = Arc::new(MockMap::new_from_csv(&args.file_path)?);
+
+    log::info!("REST map server with source: {} initializing...", args.file_path);
+    log::info!("REST map server listening on: {}...", args.address);
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::from(map.clone()))
+            .configure(http_map_server::configure)
+    })
+    .bind(args.address)?
+    .run()
+    .await?;
+
+    log::info!("REST map server terminating...");
+
+    Ok(())
 }
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use actix_web::{web, App, HttpServer};
+use mock_map_agent::mock_map::MockMap;
+use openlr::decoding_parameters::DecodingParameters;
+use openlr::errors::OpenLrErr;
+use openlr::location::Location;
+use openlr::log::LogLevel;
+use openlr::map::Map;
+use openlr_server::http_map_proxy::HttpMapProxy;
+use url::Url;
+
+// Serve the map on an ephemeral port, and return a proxy for it
+fn start_agent(csv: &str) -> HttpMapProxy {
+    let map: Arc<dyn Map> = Arc::new(MockMap::new_from_csv(csv).unwrap());
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(web::Data::from(map.clone()))
+            .configure(http_map_server::configure)
+    })
+    .workers(1)
+    .bind("127.0.0.1:0")
+    .unwrap();
+    let address = server.addrs()[0];
+    actix_web::rt::spawn(server.run());
+
+    HttpMapProxy::new(Url::parse(&format!("http://{}/", address)).unwrap()).unwrap()
+}
+
+#[actix_web::test]
+async fn test_decode_over_rest() {
+    let proxy = start_agent("../openlr/test_data/test4.csv");
+
+    let res = openlr::decode_binary(
+        "C/7VOCaEbSu/BP+5AMUrbJEQ",
+        1,
+        &proxy,
+        &DecodingParameters::default(),
+        LogLevel::Debug,
+    )
+    .await;
+    match res.result {
+        Ok(Location::Line(l)) => {
+            assert_eq!(
+                l.edges.iter().map(|e| e.id).collect::<Vec<i64>>(),
+                vec![11384678, 3551999, 12007589, 13042191]
+            );
+            assert_eq!(l.p_off, Some((31, 64)));
+            assert_eq!(l.n_off, Some((15, 19)));
+        }
+        r => panic!("Unexpected decoding result: {:?}", r),
+    }
+}
+
+#[actix_web::test]
+async fn test_previous_edges_over_rest() {
+    let proxy = start_agent("../openlr/test_data/test1.csv");
+    let mock = MockMap::new_from_csv("../openlr/test_data/test1.csv").unwrap();
+
+    let previous = proxy
+        .get_previous_edges(3193167, String::new())
+        .await
+        .unwrap();
+    let expected = mock
+        .get_previous_edges(3193167, String::new())
+        .await
+        .unwrap();
+    assert_eq!(
+        previous.iter().map(|e| e.id).collect::<Vec<i64>>(),
+        expected.iter().map(|e| e.id).collect::<Vec<i64>>()
+    );
+    assert_eq!(previous.len(), 1);
+
+    assert!(matches!(
+        proxy.get_previous_edges(1, String::new()).await,
+        Err(OpenLrErr::PreviousEdgeError(_))
+    ));
+}
+
+#[actix_web::test]
+async fn test_agent_errors() {
+    let proxy = start_agent("../openlr/test_data/test4.csv");
+
+    match proxy.get_next_edges(1, String::new()).await {
+        Err(OpenLrErr::NextEdgeError(reason)) => assert!(reason.contains("Unknown edge 1")),
+        r => panic!("Unexpected result: {:?}", r),
+    }
+
+    // nothing is listening on the port of a dropped listener
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+    let unreachable =
+        HttpMapProxy::new(Url::parse(&format!("http://{}/", address)).unwrap()).unwrap();
+    assert!(matches!(
+        unreachable
+            .get_nearby_edges(vec![geo::Coord { x: 0.0, y: 0.0 }], 10)
+            .await,
+        Err(OpenLrErr::NearbyEdgesError(_))
+    ));
+}
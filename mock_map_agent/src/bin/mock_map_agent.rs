@@ -1,8 +1,9 @@
 use clap::Parser;
-use geo::Coord;
+use geo::{Coord, LineString, Polygon};
 use mock_map_agent::mock_map::MockMap;
 use openlr::map::Map;
-use openlr::{edge::Edge, fow::FOW, frc::FRC};
+use openlr::map_info::AttributeSemantics;
+use openlr::{edge::Edge, errors::OpenLrErr, fow::FOW, frc::FRC};
 use openlr_services::map_agent_server::MapAgent;
 use tonic::Code;
 use tonic::{transport::Server, Request, Response, Status};
@@ -24,7 +25,9 @@ pub mod openlr_services {
 
 use openlr_services::{
     map_agent_server::MapAgentServer,
-    Coordinate, EdgeSet, NextEdgesRequest, NearbyEdgesRequest, NearbyEdgesResponse,
+    AreaEdgesRequest, BoundingBox, Capabilities, Coordinate, EdgeSet, MapInfo, MapInfoRequest,
+    NextEdgesBatchRequest, NextEdgesBatchResponse, NextEdgesRequest, NearbyEdgesRequest,
+    NearbyEdgesResponse, PreviousEdgesRequest, Subgraph, SubgraphEdge, TurnRestriction,
 };
 
 use openlr_services::Edge as ProtoEdge;
@@ -79,7 +82,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     simple_logger::init_with_env().unwrap();
     let args = Arguments::parse();
     let address = args.address.parse().unwrap();
-    let map_service = MockMapService::new(&args.file_path);
+    let map_service = MockMapService::new(&args.file_path)?;
 
     log::info!("Mock map server with source: {} initializing...", args.file_path);
     log::info!("Mock map server listening on port: {}...", args.address);
@@ -100,10 +103,10 @@ pub struct MockMapService {
 }
 
 impl MockMapService {
-    pub fn new(file: &str) -> Self {
-        MockMapService {
-            mock_map: MockMap::new_from_csv(file),
-        }
+    pub fn new(file: &str) -> Result<Self, OpenLrErr> {
+        Ok(MockMapService {
+            mock_map: MockMap::new_from_csv(file)?,
+        })
     }
 }
 
@@ -126,6 +129,48 @@ impl MapAgent for MockMapService {
         }))
     }
 
+    async fn get_previous_edges(
+        &self,
+        request: Request<PreviousEdgesRequest>,
+    ) -> Result<Response<EdgeSet>, Status> {
+        let rsp = self
+            .mock_map
+            .get_previous_edges(request.get_ref().id, request.get_ref().meta.clone())
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+        Ok(Response::new(EdgeSet {
+            edges: rsp
+                .iter()
+                .map(proto_edge_from_edge)
+                .collect::<Vec<ProtoEdge>>(),
+        }))
+    }
+
+    async fn get_next_edges_batch(
+        &self,
+        request: Request<NextEdgesBatchRequest>,
+    ) -> Result<Response<NextEdgesBatchResponse>, Status> {
+        let edges = request
+            .into_inner()
+            .edges
+            .into_iter()
+            .map(|ner| (ner.id, ner.meta))
+            .collect::<Vec<(i64, String)>>();
+        let rsp = self
+            .mock_map
+            .get_next_edges_many(&edges)
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+        Ok(Response::new(NextEdgesBatchResponse {
+            edge_sets: rsp
+                .iter()
+                .map(|v| EdgeSet {
+                    edges: v.iter().map(proto_edge_from_edge).collect::<Vec<ProtoEdge>>(),
+                })
+                .collect::<Vec<EdgeSet>>(),
+        }))
+    }
+
     async fn get_nearby_edges(
         &self,
         request: Request<NearbyEdgesRequest>,
@@ -156,4 +201,105 @@ impl MapAgent for MockMapService {
 
             Ok ( Response::new(NearbyEdgesResponse { edge_sets } ) )
     }
+
+    async fn get_edges_in_area(
+        &self,
+        request: Request<AreaEdgesRequest>,
+    ) -> Result<Response<EdgeSet>, Status> {
+        let area = polygon_from_area_request(request.get_ref());
+        let rsp = self
+            .mock_map
+            .get_edges_in_area(area)
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+        Ok(Response::new(EdgeSet {
+            edges: rsp.iter().map(proto_edge_from_edge).collect::<Vec<ProtoEdge>>(),
+        }))
+    }
+
+    async fn get_subgraph(
+        &self,
+        request: Request<AreaEdgesRequest>,
+    ) -> Result<Response<Subgraph>, Status> {
+        let area = polygon_from_area_request(request.get_ref());
+        let subgraph = self
+            .mock_map
+            .get_subgraph(area)
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?
+            .ok_or_else(|| Status::new(Code::Unimplemented, "Subgraphs are not supported"))?;
+        Ok(Response::new(Subgraph {
+            edges: subgraph
+                .edges
+                .iter()
+                .map(|me| SubgraphEdge {
+                    edge: Some(proto_edge_from_edge(&me.edge)),
+                    start_node: me.start_node,
+                    end_node: me.end_node,
+                })
+                .collect::<Vec<SubgraphEdge>>(),
+            turn_restrictions: subgraph
+                .turn_restrictions
+                .into_iter()
+                .map(|r| TurnRestriction { edges: r.edges })
+                .collect::<Vec<TurnRestriction>>(),
+        }))
+    }
+
+    async fn get_map_info(
+        &self,
+        _request: Request<MapInfoRequest>,
+    ) -> Result<Response<MapInfo>, Status> {
+        let info = self
+            .mock_map
+            .get_info()
+            .await
+            .map_err(|e| Status::new(Code::Internal, e.to_string()))?;
+        Ok(Response::new(MapInfo {
+            bounding_box: info.bounding_box.map(|r| BoundingBox {
+                lower_left: Some(Coordinate {
+                    longitude: r.min().x,
+                    latitude: r.min().y,
+                }),
+                upper_right: Some(Coordinate {
+                    longitude: r.max().x,
+                    latitude: r.max().y,
+                }),
+            }),
+            vendor: info.vendor,
+            version: info.version,
+            capabilities: Some(Capabilities {
+                // GetNextEdgesBatch is served by this agent whatever the map
+                next_edges_batch: true,
+                subgraph_prefetch: info.capabilities.subgraph_prefetch,
+                turn_restrictions: info.capabilities.turn_restrictions,
+                area_search: info.capabilities.area_search,
+            }),
+            fow_semantics: semantics_to_proto(info.fow_semantics),
+            frc_semantics: semantics_to_proto(info.frc_semantics),
+        }))
+    }
+}
+
+fn semantics_to_proto(semantics: AttributeSemantics) -> String {
+    match semantics {
+        AttributeSemantics::OpenLr => String::new(),
+        AttributeSemantics::Derived(s) => s,
+    }
+}
+
+fn polygon_from_area_request(request: &AreaEdgesRequest) -> Polygon {
+    Polygon::new(
+        LineString::new(
+            request
+                .boundary
+                .iter()
+                .map(|c| Coord {
+                    x: c.longitude,
+                    y: c.latitude,
+                })
+                .collect::<Vec<Coord>>(),
+        ),
+        vec![],
+    )
 }
@@ -1 +1 @@
-pub mod mock_map;
\ No newline at end of file
+pub mod mock_map;
@@ -1,14 +1,13 @@
 use async_trait::async_trait;
-use geo::Coord;
+use geo::{BoundingRect, Coord, Intersects, Polygon, Rect};
 use openlr::edge::Edge;
 use openlr::errors::OpenLrErr;
-use openlr::fow::FOW;
-use openlr::frc::FRC;
+use openlr::in_memory_map::MapEdge;
 use openlr::map::Map;
+use openlr::map_info::{Capabilities, MapInfo};
+use openlr::map_loader::csv;
+use openlr::subgraph::Subgraph;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::prelude::*;
-use std::io::BufReader;
 
 #[derive(Debug)]
 struct MapEntry {
@@ -36,38 +35,50 @@ impl MockMap {
             edge_map: HashMap::<i64, MapEntry>::new(),
         }
     }
-    pub fn new_from_csv(filename: &str) -> Self {
-        let mut edge_map = HashMap::<i64, MapEntry>::new();
-
-        match File::open(filename) {
-            Ok(file) => {
-                let buf_reader = BufReader::new(file);
-
-                for line in buf_reader.lines() {
-                    let l = line.unwrap();
-                    let v = l.split(":").collect::<Vec<&str>>();
-                    let start_node = v.get(5).unwrap().parse::<i64>().unwrap();
-                    let end_node = v.get(6).unwrap().parse::<i64>().unwrap();
-                    let e = Edge::new_from_wkt(
-                        v.get(0).unwrap().parse::<i64>().unwrap(),
-                        v.get(1).unwrap().trim_matches('\"').to_owned(),
-                        FOW::from_u8(v.get(2).unwrap().parse::<u8>().unwrap()),
-                        FRC::from_u8(v.get(3).unwrap().parse::<u8>().unwrap()),
-                        v.get(7).unwrap().parse::<u32>().unwrap(),
-                        v.get(8).unwrap().trim_matches('\"'),
-                    )
-                    .unwrap();
-                    edge_map.insert(e.get_id(), MapEntry::new(e, start_node, end_node));
-                }
-            }
-            err => println!("Error: {:?}", err),
-        }
-        return Self { edge_map };
+    pub fn new_from_csv(filename: &str) -> Result<Self, OpenLrErr> {
+        let edge_map = csv::load(filename)?
+            .into_iter()
+            .map(|me| {
+                (
+                    me.edge.get_id(),
+                    MapEntry::new(me.edge, me.start_node, me.end_node),
+                )
+            })
+            .collect::<HashMap<i64, MapEntry>>();
+        Ok(Self { edge_map })
     }
 }
 
 #[async_trait]
 impl Map for MockMap {
+    async fn get_info(&self) -> Result<MapInfo, OpenLrErr> {
+        Ok(MapInfo {
+            bounding_box: self
+                .edge_map
+                .values()
+                .filter_map(|me| me.edge.geom.bounding_rect())
+                .reduce(|a, b| {
+                    Rect::new(
+                        Coord {
+                            x: a.min().x.min(b.min().x),
+                            y: a.min().y.min(b.min().y),
+                        },
+                        Coord {
+                            x: a.max().x.max(b.max().x),
+                            y: a.max().y.max(b.max().y),
+                        },
+                    )
+                }),
+            vendor: "Mock map".to_owned(),
+            capabilities: Capabilities {
+                subgraph_prefetch: true,
+                area_search: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
     async fn get_nearby_edges(
         &self,
         points: Vec<Coord>,
@@ -90,7 +101,10 @@ impl Map for MockMap {
         src_edge_id: i64,
         src_meta: String,
     ) -> Result<Vec<Edge>, OpenLrErr> {
-        let src = self.edge_map.get(&src_edge_id).unwrap();
+        let src = self
+            .edge_map
+            .get(&src_edge_id)
+            .ok_or_else(|| OpenLrErr::NextEdgeError(format!("Unknown edge {}", src_edge_id)))?;
         Ok(self
             .edge_map
             .values()
@@ -99,6 +113,44 @@ impl Map for MockMap {
             .cloned()
             .collect::<Vec<Edge>>())
     }
+
+    async fn get_previous_edges(
+        &self,
+        dst_edge_id: i64,
+        _dst_meta: String,
+    ) -> Result<Vec<Edge>, OpenLrErr> {
+        let dst = self
+            .edge_map
+            .get(&dst_edge_id)
+            .ok_or_else(|| OpenLrErr::PreviousEdgeError(format!("Unknown edge {}", dst_edge_id)))?;
+        Ok(self
+            .edge_map
+            .values()
+            .filter(|me| me.end_node == dst.start_node && me.start_node != dst.end_node)
+            .map(|me| &me.edge)
+            .cloned()
+            .collect::<Vec<Edge>>())
+    }
+
+    async fn get_edges_in_area(&self, area: Polygon) -> Result<Vec<Edge>, OpenLrErr> {
+        Ok(self
+            .edge_map
+            .values()
+            .filter(|me| area.intersects(&me.edge.geom))
+            .map(|me| me.edge.to_owned())
+            .collect::<Vec<Edge>>())
+    }
+
+    async fn get_subgraph(&self, area: Polygon) -> Result<Option<Subgraph>, OpenLrErr> {
+        Ok(Some(Subgraph::new(
+            self.edge_map
+                .values()
+                .filter(|me| area.intersects(&me.edge.geom))
+                .map(|me| MapEdge::new(me.edge.to_owned(), me.start_node, me.end_node))
+                .collect::<Vec<MapEdge>>(),
+            vec![],
+        )))
+    }
 }
 
 #[test]
@@ -2,18 +2,24 @@ use crate::candidate_edge::CandidateEdge;
 use crate::decoding_parameters::DecodingParameters;
 use crate::edge::Edge;
 use crate::request_context::RequestContext;
+use crate::turn_restriction::TurnRestriction;
 use crate::OpenLrErr;
 use crate::OpenLrErr::NoSubPathFound;
 use indexmap::map::Entry::{Occupied, Vacant};
 use indexmap::IndexMap;
 use num_traits::Zero;
-use rustc_hash::FxHasher;
+use rustc_hash::{FxHashMap, FxHasher};
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
+use std::collections::{hash_map, BinaryHeap};
 use std::hash::{BuildHasherDefault, Hash, Hasher};
 
 type FxIndexMap<K, V> = IndexMap<K, V, BuildHasherDefault<FxHasher>>;
 
+// The number of candidates whose successors are requested from the map at once.  Maps served
+// over a network answer a batch in a single round trip, at the cost of sometimes expanding
+// candidates which a strictly sequential search would never have reached.
+const EXPANSION_BATCH_SIZE: usize = 8;
+
 fn reverse_path<N, V, F>(parents: &FxIndexMap<N, V>, mut parent: F, start: usize) -> Vec<N>
 where
     N: Eq + Hash + Clone,
@@ -32,23 +38,54 @@ where
 }
 
 // EdgeWrapper is a newtype pattern around a map Edge to make it amenable to various trait
-// implementations (like hashing, cloning, Ord, etc) necessary for the astar algorithm
-struct EdgeWrapper(Edge);
+// implementations (like hashing, cloning, Ord, etc) necessary for the astar algorithm.
+// Along with the edge, it holds the ids of the last edges of the path to it (ending with
+// its own) which begin a turn restriction, or none if no restriction is under way, since
+// the same edge may be reached along paths which differ in the turns they may then take.
+struct EdgeWrapper(Edge, Vec<i64>);
 impl PartialEq<Self> for EdgeWrapper {
     fn eq(&self, other: &Self) -> bool {
-        self.0.get_id() == other.0.get_id()
+        self.0.get_id() == other.0.get_id() && self.1 == other.1
     }
 }
 impl Eq for EdgeWrapper {}
 impl Hash for EdgeWrapper {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.get_id().hash(state)
+        self.0.get_id().hash(state);
+        self.1.hash(state)
     }
 }
 impl Clone for EdgeWrapper {
     fn clone(&self) -> Self {
-        EdgeWrapper(self.0.clone())
+        EdgeWrapper(self.0.clone(), self.1.clone())
+    }
+}
+
+// Extends the tail of a path (its last edges which begin a turn restriction, or else its
+// last edge) by a successor.  Returns None if the extended tail ends with a complete
+// restriction, or else the longest suffix of the extended tail, of at least two edges,
+// which begins a restriction and may yet be completed.  The restrictions of every edge
+// in the tail must already have been fetched.
+fn extend_tail(
+    restrictions: &FxHashMap<i64, Vec<TurnRestriction>>,
+    tail: &[i64],
+    successor: i64,
+) -> Option<Vec<i64>> {
+    let mut extended = tail.to_vec();
+    extended.push(successor);
+    let mut longest = None;
+    for start in 0..extended.len() - 1 {
+        let suffix = &extended[start..];
+        for r in restrictions.get(&suffix[0]).into_iter().flatten() {
+            if r.edges == suffix {
+                return None;
+            }
+            if longest.is_none() && r.edges.len() > suffix.len() && r.edges.starts_with(suffix) {
+                longest = Some(start);
+            }
+        }
     }
+    Some(longest.map(|start| extended.split_off(start)).unwrap_or_default())
 }
 
 // SmallestCostHolder is copied unmodified from the pathfinder crate:
@@ -82,21 +119,48 @@ impl Ord for SmallestCostHolder {
     }
 }
 
+// The maximum length of a path between LRPs, given the DNP of the first: the greater of the
+// maximum absolute and relative deviations
+pub(crate) fn max_path_length(dnp: u32, params: &DecodingParameters) -> u32 {
+    u32::max(
+        dnp + params.max_absolute_dnp_variance,
+        (dnp as f64 * (1.0 + params.max_relative_dnp_variance)) as u32,
+    )
+}
+
 pub(crate) async fn find_acceptable_shortest_path<'a>(
     src: &'a CandidateEdge<'a>,
     dst: &'a CandidateEdge<'a>,
     context: &RequestContext<'_, DecodingParameters>,
 ) -> Result<Vec<Edge>, OpenLrErr> {
-    let dst_id = dst.candidate.get_id();
-    let p = dst.candidate.get_start_point();
-    let (dst_lon, dst_lat) = (p.x(), p.y());
     let max_acceptable_frc = context.params.allowed_frc_delta_table[src.lrp.frc.to_usize()];
-    let src_dnp = src.lrp.dnp.unwrap().1;
-    // set the maximum path length as the greater of the maximum absolute and relative deviations
-    let max_distance = u32::max(
-        src_dnp + context.params.max_absolute_dnp_variance,
-        (src_dnp as f64 * (1.0 + context.params.max_relative_dnp_variance)) as u32,
-    );
+    let max_distance = max_path_length(src.lrp.dnp.unwrap().1, context.params);
+
+    find_shortest_path(
+        &src.candidate,
+        &dst.candidate,
+        max_acceptable_frc,
+        max_distance,
+        context,
+    )
+    .await?
+    .ok_or(NoSubPathFound(src.lrp.index, dst.lrp.index))
+}
+
+// Find the shortest path from the src edge to the dst edge, considering only lines whose FRC
+// is no lower than max_acceptable_frc. The length of a path is the sum of the lengths of the
+// edges strictly between src and dst, and paths whose length reaches max_distance are abandoned.
+// Paths never follow all the edges of one of the map's turn restrictions in succession.
+pub(crate) async fn find_shortest_path<P: Sync>(
+    src: &Edge,
+    dst: &Edge,
+    max_acceptable_frc: usize,
+    max_distance: u32,
+    context: &RequestContext<'_, P>,
+) -> Result<Option<Vec<Edge>>, OpenLrErr> {
+    let dst_id = dst.get_id();
+    let p = dst.get_start_point();
+    let (dst_lon, dst_lat) = (p.x(), p.y());
 
     let mut to_see = BinaryHeap::new();
     to_see.push(SmallestCostHolder {
@@ -106,52 +170,117 @@ pub(crate) async fn find_acceptable_shortest_path<'a>(
     });
     let mut parents: FxIndexMap<EdgeWrapper, (usize, u32)> = FxIndexMap::default();
     parents.insert(
-        EdgeWrapper(src.candidate.clone()),
+        EdgeWrapper(src.clone(), Vec::new()),
         (usize::MAX, Zero::zero()),
     );
-    while let Some(SmallestCostHolder { cost, index, .. }) = to_see.pop() {
-        let successors = {
-            let (node, &(_, c)) = parents.get_index(index).unwrap();
+    // the turn restrictions beginning on each edge expanded so far
+    let mut restrictions: FxHashMap<i64, Vec<TurnRestriction>> = FxHashMap::default();
+    while let Some(top) = to_see.pop() {
+        // Pop the most promising candidates, to expand them with a single request to the map.
+        // The destination ends the batch, and is only reached once it is the most promising.
+        let mut batch: Vec<SmallestCostHolder> = Vec::new();
+        let mut next = Some(top);
+        while let Some(holder) = next.take() {
+            let (node, &(_, c)) = parents.get_index(holder.index).unwrap();
 
             context.trace(|| {
                 format!(
                     "Top candidate {} popped from heap with cost: {}",
                     node.0.get_id(),
-                    cost
+                    holder.cost
                 )
             });
 
-            // We've arrived at the destination edge.  Walk the chain of parents back to the src
             if node.0.get_id() == dst_id {
-                let path = reverse_path(&parents, |&(p, _)| p, index);
-                return Ok(path.into_iter().map(|ew| ew.0).collect());
+                // We've arrived at the destination edge.  Walk the chain of parents back to
+                // the src
+                if batch.is_empty() {
+                    let path = reverse_path(&parents, |&(p, _)| p, holder.index);
+                    return Ok(Some(path.into_iter().map(|ew| ew.0).collect()));
+                }
+                to_see.push(holder);
+                break;
             }
             // We may have inserted a node several time into the binary heap if we found
             // a better way to access it. Ensure that we are currently dealing with the
             // best path and discard the others.
-            if cost > c {
-                continue;
+            if holder.cost <= c {
+                batch.push(holder);
+            }
+            if batch.len() < EXPANSION_BATCH_SIZE {
+                next = to_see.pop();
+            }
+        }
+
+        let mut tails = Vec::with_capacity(batch.len());
+        let mut requests = Vec::with_capacity(batch.len());
+        for holder in batch.iter() {
+            let node = parents.get_index(holder.index).unwrap().0;
+            let node_id = node.0.get_id();
+            if let hash_map::Entry::Vacant(e) = restrictions.entry(node_id) {
+                e.insert(
+                    context
+                        .map_server
+                        .get_turn_restrictions(node_id, node.0.get_metadata())
+                        .await?
+                        .into_iter()
+                        .filter(|r| r.edges.len() >= 2 && r.get_from() == Some(node_id))
+                        .collect::<Vec<TurnRestriction>>(),
+                );
             }
+            tails.push(match node.1.is_empty() {
+                true => vec![node_id],
+                false => node.1.clone(),
+            });
+            requests.push((node_id, node.0.get_metadata()));
+        }
+        if requests.is_empty() {
+            continue;
+        }
+
+        // Find all outgoing lines from the current lines that satisfy LFRC constraints and
+        // leave the cumulative path length in the DNP Goldilocks zone (not too long), and
+        // which the turn restrictions allow.
+        let successors = context
+            .map_server
+            .get_next_edges_many(&requests)
+            .await?
+            .into_iter()
+            .zip(batch.iter().zip(tails.iter()))
+            .flat_map(|(next_edges, (holder, tail))| {
+                let restrictions = &restrictions;
+                next_edges
+                    .into_iter()
+                    .filter(|e| e.get_frc().to_usize() <= max_acceptable_frc)
+                    .filter_map(move |n| match extend_tail(restrictions, tail, n.get_id()) {
+                        Some(successor_tail) => {
+                            let move_cost = if n.get_id() == dst_id {
+                                0u32
+                            } else {
+                                n.get_line_length()
+                            };
+                            Some((
+                                holder.index,
+                                holder.cost,
+                                EdgeWrapper(n, successor_tail),
+                                move_cost,
+                            ))
+                        }
+                        None => {
+                            context.debug(|| {
+                                format!(
+                                    "Successor {} rejected due to a turn restriction from {:?}",
+                                    n.get_id(),
+                                    tail
+                                )
+                            });
+                            None
+                        }
+                    })
+            })
+            .collect::<Vec<(usize, u32, EdgeWrapper, u32)>>();
 
-            // Find all outgoing lines from the current line that satisfies LFRC constraints and
-            // leaves the cumulative path length in the DNP Goldilocks zone (not too long).
-            context
-                .map_server
-                .get_next_edges(node.0.get_id(), node.0.get_metadata())
-                .await?
-                .into_iter()
-                .filter(|e| e.get_frc().to_usize() <= max_acceptable_frc)
-                .map(|n| {
-                    let move_cost = if n.get_id() == dst_id {
-                        0u32
-                    } else {
-                        n.get_line_length()
-                    };
-                    (EdgeWrapper(n), move_cost)
-                })
-        };
-
-        for (successor, move_cost) in successors {
+        for (index, cost, successor, move_cost) in successors {
             // the new cost is the distance we've traveled to arrive at the start of this
             // successor, plus the distance from the successor's start to end
             let new_cost = cost + move_cost;
@@ -207,5 +336,68 @@ pub(crate) async fn find_acceptable_shortest_path<'a>(
             }
         }
     }
-    Err(NoSubPathFound(src.lrp.index, dst.lrp.index))
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fow::FOW;
+    use crate::frc::FRC;
+    use crate::in_memory_map::{InMemoryMap, MapEdge};
+    use crate::log::LogLevel;
+    use futures::executor::block_on;
+    use geo::Coord;
+
+    fn edge(id: i64, coords: Vec<(f64, f64)>) -> Edge {
+        Edge::new_from_coords(
+            id,
+            "".to_owned(),
+            FOW::SingleCarriageway,
+            FRC::FRC3,
+            100,
+            coords.into_iter().map(Coord::from).collect(),
+        )
+    }
+
+    fn shortest_path(map: &InMemoryMap, src: i64, dst: i64) -> Option<Vec<i64>> {
+        let context = RequestContext::new(map, &(), LogLevel::Error);
+        let src = map.get_edge(src).unwrap().edge.clone();
+        let dst = map.get_edge(dst).unwrap().edge.clone();
+        block_on(find_shortest_path(&src, &dst, 7, 10000, &context))
+            .unwrap()
+            .map(|path| path.iter().map(|e| e.get_id()).collect())
+    }
+
+    #[test]
+    fn test_turn_restrictions() {
+        // a straight road 1, 2, 3, with a detour 4, 5 around edge 2
+        let mut map = InMemoryMap::new(vec![
+            MapEdge::new(edge(1, vec![(0.0, 0.0), (0.001, 0.0)]), 1, 2),
+            MapEdge::new(edge(2, vec![(0.001, 0.0), (0.002, 0.0)]), 2, 3),
+            MapEdge::new(edge(3, vec![(0.002, 0.0), (0.003, 0.0)]), 3, 4),
+            MapEdge::new(edge(4, vec![(0.001, 0.0), (0.0015, 0.001)]), 2, 5),
+            MapEdge::new(edge(5, vec![(0.0015, 0.001), (0.002, 0.0)]), 5, 3),
+        ])
+        .unwrap();
+        assert_eq!(shortest_path(&map, 1, 3), Some(vec![1, 2, 3]));
+
+        // no straight on through edge 2 onto edge 3, although edge 2 can be reached
+        map.add_turn_restrictions(vec![TurnRestriction::new(vec![1, 2, 3])])
+            .unwrap();
+        assert_eq!(shortest_path(&map, 1, 3), Some(vec![1, 4, 5, 3]));
+        assert_eq!(shortest_path(&map, 1, 2), Some(vec![1, 2]));
+
+        // nor from edge 5 onto edge 3
+        map.add_turn_restrictions(vec![TurnRestriction::new(vec![5, 3])])
+            .unwrap();
+        assert_eq!(shortest_path(&map, 1, 3), None);
+
+        assert!(map
+            .add_turn_restrictions(vec![TurnRestriction::new(vec![1, 3])])
+            .is_err());
+        assert!(map
+            .add_turn_restrictions(vec![TurnRestriction::new(vec![1])])
+            .is_err());
+    }
 }
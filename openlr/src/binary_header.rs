@@ -34,6 +34,10 @@ impl BinaryHeader {
         BinaryHeader { header_byte: b }
     }
 
+    pub fn to_u8(&self) -> u8 {
+        self.header_byte
+    }
+
     pub fn has_attrs(&self) -> bool {
         self.header_byte & 0b00001000 > 0
     }
@@ -0,0 +1,558 @@
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use geo::{BoundingRect, Coord, Polygon, Rect};
+use lru::LruCache;
+use rustc_hash::FxHashSet;
+
+use crate::common::widen_rect;
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::map::Map;
+use crate::map_info::MapInfo;
+use crate::subgraph::Subgraph;
+use crate::turn_restriction::TurnRestriction;
+
+// Beyond this many tiles, a radius search is passed on to the map rather than assembled
+// from tiles (i.e. for very large radii, or close to the poles)
+const MAX_TILES_PER_SEARCH: i64 = 64;
+
+/// The bounds of the caches of a [`CachingMap`]
+#[derive(Debug, Clone, Copy)]
+pub struct CacheConfig {
+    /// Maximum number of lines whose next lines are cached
+    pub next_edges_capacity: usize,
+    /// Maximum number of tiles (or, for maps without area searches, radius searches)
+    /// whose lines are cached
+    pub tile_capacity: usize,
+    /// Maximum number of subgraphs cached
+    pub subgraph_capacity: usize,
+    /// Time after which a cached result is fetched from the map again
+    pub ttl: Duration,
+    /// Width and height of a tile in degrees
+    pub tile_size: f64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            next_edges_capacity: 100_000,
+            tile_capacity: 10_000,
+            subgraph_capacity: 1_000,
+            ttl: Duration::from_secs(300),
+            tile_size: 0.01,
+        }
+    }
+}
+
+// An LRU cache whose entries also expire after a fixed time
+struct TtlCache<K: Hash + Eq, V: Clone> {
+    entries: Mutex<LruCache<K, (Instant, V)>>,
+    ttl: Duration,
+}
+
+impl<K: Hash + Eq, V: Clone> TtlCache<K, V> {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        TtlCache {
+            entries: Mutex::new(LruCache::new(
+                NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN),
+            )),
+            ttl,
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<V> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some((inserted, value)) if inserted.elapsed() < self.ttl => Some(value.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&self, key: K, value: V) {
+        self.entries
+            .lock()
+            .unwrap()
+            .put(key, (Instant::now(), value));
+    }
+}
+
+/// A map which caches the next lines of each line, the lines near each point, and the
+/// subgraphs of another map, so that repeatedly decoding the same location references
+/// does not query the map again.  Radius searches are answered from square tiles of the
+/// map, each fetched with a single area search, if the map supports them, and otherwise
+/// cached for each point and radius.  Subgraphs are fetched for the area grown to whole
+/// tiles, so that they are shared by all areas within the same tiles.  Errors are never
+/// cached, and all other requests are passed on to the map.
+pub struct CachingMap<M: Map> {
+    map: M,
+    next_edges: TtlCache<(i64, String), Vec<Edge>>,
+    tiles: TtlCache<(i64, i64), Vec<Edge>>,
+    nearby_edges: TtlCache<(u64, u64, u32), Vec<Edge>>,
+    subgraphs: TtlCache<(i64, i64, i64, i64), Option<Subgraph>>,
+    tile_size: f64,
+}
+
+impl<M: Map> CachingMap<M> {
+    pub fn new(map: M, config: CacheConfig) -> Self {
+        CachingMap {
+            map,
+            next_edges: TtlCache::new(config.next_edges_capacity, config.ttl),
+            tiles: TtlCache::new(config.tile_capacity, config.ttl),
+            nearby_edges: TtlCache::new(config.tile_capacity, config.ttl),
+            subgraphs: TtlCache::new(config.subgraph_capacity, config.ttl),
+            tile_size: config.tile_size,
+        }
+    }
+
+    /// Returns the map whose results are cached
+    pub fn inner(&self) -> &M {
+        &self.map
+    }
+
+    // The tiles covering every point within radius meters of a point, or None if there
+    // are too many of them
+    fn covering_tiles(&self, c: &Coord, radius: u32) -> Option<Vec<(i64, i64)>> {
+        let area = widen_rect(&Rect::new(*c, *c), radius as f64);
+        let tile = |v: f64| (v / self.tile_size).floor() as i64;
+        let (min_x, min_y) = (tile(area.min().x), tile(area.min().y));
+        let (max_x, max_y) = (tile(area.max().x), tile(area.max().y));
+        if (max_x - min_x + 1) * (max_y - min_y + 1) > MAX_TILES_PER_SEARCH {
+            return None;
+        }
+        Some(
+            (min_x..=max_x)
+                .flat_map(|x| (min_y..=max_y).map(move |y| (x, y)))
+                .collect::<Vec<(i64, i64)>>(),
+        )
+    }
+
+    // The rectangle covering the tiles from (min_x, min_y) to (max_x, max_y) inclusive
+    fn tiles_rect(&self, min_x: i64, min_y: i64, max_x: i64, max_y: i64) -> Rect {
+        Rect::new(
+            Coord {
+                x: min_x as f64 * self.tile_size,
+                y: min_y as f64 * self.tile_size,
+            },
+            Coord {
+                x: (max_x + 1) as f64 * self.tile_size,
+                y: (max_y + 1) as f64 * self.tile_size,
+            },
+        )
+    }
+
+    async fn get_tile(&self, tile: (i64, i64)) -> Result<Vec<Edge>, OpenLrErr> {
+        if let Some(edges) = self.tiles.get(&tile) {
+            return Ok(edges);
+        }
+        let area = self.tiles_rect(tile.0, tile.1, tile.0, tile.1);
+        let edges = self.map.get_edges_in_area(area.to_polygon()).await?;
+        self.tiles.put(tile, edges.clone());
+        Ok(edges)
+    }
+
+    // The lines within radius meters of a point, from the tiles around it
+    async fn get_nearby_edges_from_tiles(
+        &self,
+        c: &Coord,
+        radius: u32,
+        tiles: Vec<(i64, i64)>,
+    ) -> Result<Vec<Edge>, OpenLrErr> {
+        let mut seen = FxHashSet::default();
+        let mut nearby = Vec::new();
+        for tile in tiles {
+            for e in self.get_tile(tile).await? {
+                if !seen.contains(&e.id) && e.distance_to_point(c.x, c.y) <= radius {
+                    seen.insert(e.id);
+                    nearby.push(e);
+                }
+            }
+        }
+        Ok(nearby)
+    }
+
+    // The lines within radius meters of a point, searched for by the map itself
+    async fn get_nearby_edges_of_point(
+        &self,
+        c: &Coord,
+        radius: u32,
+    ) -> Result<Vec<Edge>, OpenLrErr> {
+        let key = (c.x.to_bits(), c.y.to_bits(), radius);
+        if let Some(edges) = self.nearby_edges.get(&key) {
+            return Ok(edges);
+        }
+        let edges = self
+            .map
+            .get_nearby_edges(vec![*c], radius)
+            .await?
+            .pop()
+            .unwrap_or_default();
+        self.nearby_edges.put(key, edges.clone());
+        Ok(edges)
+    }
+}
+
+#[async_trait]
+impl<M: Map> Map for CachingMap<M> {
+    async fn get_info(&self) -> Result<MapInfo, OpenLrErr> {
+        self.map.get_info().await
+    }
+
+    async fn get_nearby_edges(
+        &self,
+        points: Vec<Coord>,
+        radius: u32,
+    ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+        let area_search = self.map.get_info().await?.capabilities.area_search;
+        let mut edge_sets = Vec::with_capacity(points.len());
+        for c in &points {
+            edge_sets.push(match self.covering_tiles(c, radius) {
+                Some(tiles) if area_search => {
+                    self.get_nearby_edges_from_tiles(c, radius, tiles).await?
+                }
+                _ => self.get_nearby_edges_of_point(c, radius).await?,
+            });
+        }
+        Ok(edge_sets)
+    }
+
+    async fn get_next_edges(&self, id: i64, meta: String) -> Result<Vec<Edge>, OpenLrErr> {
+        let key = (id, meta);
+        if let Some(edges) = self.next_edges.get(&key) {
+            return Ok(edges);
+        }
+        let edges = self.map.get_next_edges(id, key.1.clone()).await?;
+        self.next_edges.put(key, edges.clone());
+        Ok(edges)
+    }
+
+    /// Returns the cached next lines of the given lines, and fetches those of the others
+    /// from the map at once
+    async fn get_next_edges_many(
+        &self,
+        edges: &[(i64, String)],
+    ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+        let mut next_edges = edges
+            .iter()
+            .map(|key| self.next_edges.get(key))
+            .collect::<Vec<Option<Vec<Edge>>>>();
+        let missing = edges
+            .iter()
+            .zip(next_edges.iter())
+            .filter(|(_, cached)| cached.is_none())
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<(i64, String)>>();
+        if !missing.is_empty() {
+            let fetched = self.map.get_next_edges_many(&missing).await?;
+            if fetched.len() != missing.len() {
+                return Err(OpenLrErr::NextEdgeError(format!(
+                    "The map returned {} sets of next lines for {} lines",
+                    fetched.len(),
+                    missing.len()
+                )));
+            }
+            let mut fetched = fetched.into_iter();
+            for (key, slot) in edges.iter().zip(next_edges.iter_mut()) {
+                if slot.is_none() {
+                    let found = fetched.next().unwrap_or_default();
+                    self.next_edges.put(key.clone(), found.clone());
+                    *slot = Some(found);
+                }
+            }
+        }
+        Ok(next_edges.into_iter().flatten().collect::<Vec<Vec<Edge>>>())
+    }
+
+    async fn get_previous_edges(&self, id: i64, meta: String) -> Result<Vec<Edge>, OpenLrErr> {
+        self.map.get_previous_edges(id, meta).await
+    }
+
+    async fn get_turn_restrictions(
+        &self,
+        id: i64,
+        meta: String,
+    ) -> Result<Vec<TurnRestriction>, OpenLrErr> {
+        self.map.get_turn_restrictions(id, meta).await
+    }
+
+    async fn get_edges_in_area(&self, area: Polygon) -> Result<Vec<Edge>, OpenLrErr> {
+        self.map.get_edges_in_area(area).await
+    }
+
+    /// Returns the cached subgraph of the tiles covering the area, or fetches it from the
+    /// map
+    async fn get_subgraph(&self, area: Polygon) -> Result<Option<Subgraph>, OpenLrErr> {
+        let bounds = match area.bounding_rect() {
+            Some(bounds) => bounds,
+            None => return self.map.get_subgraph(area).await,
+        };
+        let tile = |v: f64| (v / self.tile_size).floor() as i64;
+        let key = (
+            tile(bounds.min().x),
+            tile(bounds.min().y),
+            tile(bounds.max().x),
+            tile(bounds.max().y),
+        );
+        if let Some(subgraph) = self.subgraphs.get(&key) {
+            return Ok(subgraph);
+        }
+        let tiles = self.tiles_rect(key.0, key.1, key.2, key.3);
+        let subgraph = self.map.get_subgraph(tiles.to_polygon()).await?;
+        self.subgraphs.put(key, subgraph.clone());
+        Ok(subgraph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fow::FOW;
+    use crate::frc::FRC;
+    use crate::in_memory_map::{InMemoryMap, MapEdge};
+    use futures::executor::block_on;
+    use geo::LineString;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // Counts the requests which reach the map
+    struct CountingMap {
+        map: InMemoryMap,
+        next_edges: AtomicUsize,
+        nearby_edges: AtomicUsize,
+        area_searches: AtomicUsize,
+        subgraphs: AtomicUsize,
+        area_search: bool,
+        // the number of sets of next lines missing from each batch
+        short_batches: usize,
+    }
+
+    #[async_trait]
+    impl Map for CountingMap {
+        async fn get_info(&self) -> Result<MapInfo, OpenLrErr> {
+            let mut info = self.map.get_info().await?;
+            info.capabilities.area_search = self.area_search;
+            Ok(info)
+        }
+
+        async fn get_nearby_edges(
+            &self,
+            points: Vec<Coord>,
+            radius: u32,
+        ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+            self.nearby_edges.fetch_add(1, Ordering::Relaxed);
+            self.map.get_nearby_edges(points, radius).await
+        }
+
+        async fn get_next_edges(&self, id: i64, meta: String) -> Result<Vec<Edge>, OpenLrErr> {
+            self.next_edges.fetch_add(1, Ordering::Relaxed);
+            self.map.get_next_edges(id, meta).await
+        }
+
+        async fn get_next_edges_many(
+            &self,
+            edges: &[(i64, String)],
+        ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+            let mut next_edges = Vec::with_capacity(edges.len());
+            for (id, meta) in edges {
+                next_edges.push(self.get_next_edges(*id, meta.clone()).await?);
+            }
+            next_edges.truncate(edges.len().saturating_sub(self.short_batches));
+            Ok(next_edges)
+        }
+
+        async fn get_edges_in_area(&self, area: Polygon) -> Result<Vec<Edge>, OpenLrErr> {
+            self.area_searches.fetch_add(1, Ordering::Relaxed);
+            self.map.get_edges_in_area(area).await
+        }
+
+        async fn get_subgraph(&self, area: Polygon) -> Result<Option<Subgraph>, OpenLrErr> {
+            self.subgraphs.fetch_add(1, Ordering::Relaxed);
+            let edges = self.map.get_edges_in_area(area).await?;
+            Ok(Some(Subgraph::new(
+                edges
+                    .iter()
+                    .filter_map(|e| self.map.get_edge(e.id).cloned())
+                    .collect::<Vec<MapEdge>>(),
+                vec![],
+            )))
+        }
+    }
+
+    fn map_edge(id: i64, start_node: i64, end_node: i64, coords: Vec<(f64, f64)>) -> MapEdge {
+        MapEdge::new(
+            Edge::new(
+                id,
+                String::new(),
+                FOW::SingleCarriageway,
+                FRC::FRC3,
+                100,
+                LineString::from(coords),
+            ),
+            start_node,
+            end_node,
+        )
+    }
+
+    fn counting_map(area_search: bool) -> CountingMap {
+        CountingMap {
+            map: InMemoryMap::new(vec![
+                map_edge(1, 1, 2, vec![(0.0, 0.0), (0.001, 0.0)]),
+                map_edge(2, 2, 3, vec![(0.001, 0.0), (0.002, 0.0)]),
+                map_edge(3, 2, 4, vec![(0.001, 0.0), (0.001, 0.001)]),
+                map_edge(4, 5, 6, vec![(0.015, 0.0), (0.016, 0.0)]),
+            ])
+            .unwrap(),
+            next_edges: AtomicUsize::new(0),
+            nearby_edges: AtomicUsize::new(0),
+            area_searches: AtomicUsize::new(0),
+            subgraphs: AtomicUsize::new(0),
+            area_search,
+            short_batches: 0,
+        }
+    }
+
+    fn sorted_ids(edges: &[Edge]) -> Vec<i64> {
+        let mut ids = edges.iter().map(|e| e.id).collect::<Vec<i64>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_next_edges() {
+        let map = CachingMap::new(counting_map(true), CacheConfig::default());
+        let first = block_on(map.get_next_edges(1, String::new())).unwrap();
+        let again = block_on(map.get_next_edges(1, String::new())).unwrap();
+        assert_eq!(sorted_ids(&first), vec![2, 3]);
+        assert_eq!(sorted_ids(&first), sorted_ids(&again));
+        assert_eq!(map.inner().next_edges.load(Ordering::Relaxed), 1);
+
+        // only the next edges of line 2 are fetched
+        let many =
+            block_on(map.get_next_edges_many(&[(1, String::new()), (2, String::new())])).unwrap();
+        assert_eq!(many.len(), 2);
+        assert_eq!(sorted_ids(&many[0]), vec![2, 3]);
+        assert!(many[1].is_empty());
+        assert_eq!(map.inner().next_edges.load(Ordering::Relaxed), 2);
+
+        // errors are not cached
+        assert!(block_on(map.get_next_edges(42, String::new())).is_err());
+        assert!(block_on(map.get_next_edges(42, String::new())).is_err());
+        assert_eq!(map.inner().next_edges.load(Ordering::Relaxed), 4);
+
+        // nor are the next edges of a batch which the map answered only in part
+        let short = CachingMap::new(
+            CountingMap {
+                short_batches: 1,
+                ..counting_map(true)
+            },
+            CacheConfig::default(),
+        );
+        let batch = [(1, String::new()), (2, String::new())];
+        assert!(matches!(
+            block_on(short.get_next_edges_many(&batch)),
+            Err(OpenLrErr::NextEdgeError(_))
+        ));
+        block_on(short.get_next_edges(1, String::new())).unwrap();
+        assert_eq!(short.inner().next_edges.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_subgraphs() {
+        let map = CachingMap::new(counting_map(true), CacheConfig::default());
+        let area = |x: f64| {
+            Rect::new(
+                Coord { x, y: 0.0001 },
+                Coord {
+                    x: x + 0.001,
+                    y: 0.001,
+                },
+            )
+            .to_polygon()
+        };
+
+        // both areas lie within the tile east of the origin, whose lines they share
+        let first = block_on(map.get_subgraph(area(0.0))).unwrap().unwrap();
+        let second = block_on(map.get_subgraph(area(0.002))).unwrap().unwrap();
+        let ids = |s: &Subgraph| {
+            let mut ids = s.edges.iter().map(|me| me.edge.id).collect::<Vec<i64>>();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(&first), vec![1, 2, 3]);
+        assert_eq!(ids(&first), ids(&second));
+        assert_eq!(map.inner().subgraphs.load(Ordering::Relaxed), 1);
+
+        // an area across two tiles is fetched again
+        let across = block_on(map.get_subgraph(area(0.0095))).unwrap().unwrap();
+        assert_eq!(ids(&across), vec![1, 2, 3, 4]);
+        assert_eq!(map.inner().subgraphs.load(Ordering::Relaxed), 2);
+    }
+
+    #[test]
+    fn test_expiry_and_eviction() {
+        let expiring = CachingMap::new(
+            counting_map(true),
+            CacheConfig {
+                ttl: Duration::ZERO,
+                ..Default::default()
+            },
+        );
+        block_on(expiring.get_next_edges(1, String::new())).unwrap();
+        block_on(expiring.get_next_edges(1, String::new())).unwrap();
+        assert_eq!(expiring.inner().next_edges.load(Ordering::Relaxed), 2);
+
+        let small = CachingMap::new(
+            counting_map(true),
+            CacheConfig {
+                next_edges_capacity: 1,
+                ..Default::default()
+            },
+        );
+        for id in [1, 2, 1] {
+            block_on(small.get_next_edges(id, String::new())).unwrap();
+        }
+        assert_eq!(small.inner().next_edges.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_nearby_edges() {
+        let points = vec![
+            Coord {
+                x: 0.0011,
+                y: 0.0001,
+            },
+            Coord { x: 0.0155, y: 0.0 },
+        ];
+        for area_search in [true, false] {
+            let map = CachingMap::new(counting_map(area_search), CacheConfig::default());
+            let expected = block_on(map.inner().map.get_nearby_edges(points.clone(), 50)).unwrap();
+            for _ in 0..2 {
+                let nearby = block_on(map.get_nearby_edges(points.clone(), 50)).unwrap();
+                assert_eq!(nearby.len(), 2);
+                for (found, expected) in nearby.iter().zip(expected.iter()) {
+                    assert_eq!(sorted_ids(found), sorted_ids(expected));
+                }
+            }
+            assert_eq!(sorted_ids(&expected[0]), vec![1, 2, 3]);
+            assert_eq!(sorted_ids(&expected[1]), vec![4]);
+            match area_search {
+                // the two points lie in the first and second tile east of the origin, and
+                // within 50m of the tiles south of them
+                true => {
+                    assert_eq!(map.inner().area_searches.load(Ordering::Relaxed), 4);
+                    assert_eq!(map.inner().nearby_edges.load(Ordering::Relaxed), 0);
+                }
+                false => {
+                    assert_eq!(map.inner().area_searches.load(Ordering::Relaxed), 0);
+                    assert_eq!(map.inner().nearby_edges.load(Ordering::Relaxed), 2);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use crate::binary_header::BinaryHeader;
+use crate::common::{find_edges_in_area, int2deg};
+use crate::decodable_reference::DecodableReference;
+use crate::decoding_parameters::DecodingParameters;
+use crate::deserializable_reference::DeserializableReference;
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::location::Location;
+use crate::request_context::RequestContext;
+use async_trait::async_trait;
+use geo::{Coord, HaversineDestination, LineString, Point, Polygon};
+use serde::Serialize;
+
+/// Number of vertices used to approximate the circumference of a circle
+const CIRCLE_VERTICES: usize = 64;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// OpenLR Circle                                                      //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+#[derive(Debug, Serialize)]
+pub struct CircleLocationReference {
+    pub(crate) header: BinaryHeader,
+    pub longitude: f64,
+    pub latitude: f64,
+    pub radius: u32,
+}
+
+#[async_trait]
+impl DecodableReference for CircleLocationReference {
+    type Peer = CircleLocation;
+    async fn decode(
+        &self,
+        context: &RequestContext<DecodingParameters>,
+    ) -> Result<Location, OpenLrErr> {
+        let mut circle = CircleLocation {
+            longitude: self.longitude,
+            latitude: self.latitude,
+            radius: self.radius,
+            edges: None,
+        };
+        circle.edges = find_edges_in_area(circle.get_polygon(), context).await?;
+        Ok(Location::Circle(circle))
+    }
+}
+
+impl DeserializableReference for CircleLocationReference {
+    type T = CircleLocationReference;
+    fn from_binary(bytes: &Vec<u8>) -> Result<Self::T, OpenLrErr> {
+        // header (1) + center (6) + radius (1..4)
+        if bytes.len() < 8 || bytes.len() > 11 {
+            return Err(OpenLrErr::InvalidBinaryStringLength(bytes.len()));
+        }
+
+        // The radius is a big-endian unsigned integer occupying the remaining bytes
+        let radius = bytes[7..]
+            .iter()
+            .fold(0u32, |acc, b| (acc << 8) | *b as u32);
+
+        Ok(CircleLocationReference {
+            header: BinaryHeader::new(bytes[0]),
+            longitude: int2deg(bytes[1], bytes[2], bytes[3]),
+            latitude: int2deg(bytes[4], bytes[5], bytes[6]),
+            radius,
+        })
+    }
+
+    fn from_xml(_xml: &str) -> Result<Self::T, OpenLrErr> {
+        Err(OpenLrErr::UnsupportedLocationTypeError(
+            "Circle in XML".to_owned(),
+        ))
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct CircleLocation {
+    pub longitude: f64,
+    pub latitude: f64,
+    /// Radius of the circle in meters
+    pub radius: u32,
+    pub edges: Option<Vec<Edge>>,
+}
+
+impl CircleLocation {
+    /// Returns a polygon approximating the circle
+    pub fn get_polygon(&self) -> Polygon {
+        let center = Point::new(self.longitude, self.latitude);
+        let ring = (0..CIRCLE_VERTICES)
+            .map(|i| {
+                let bearing = i as f64 * 360.0 / CIRCLE_VERTICES as f64;
+                center.haversine_destination(bearing, self.radius as f64).0
+            })
+            .collect::<Vec<Coord>>();
+        Polygon::new(LineString::new(ring), vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use geo::{Contains, CoordsIter, HaversineDistance};
+
+    #[test]
+    fn test_from_binary() {
+        let bytes: Vec<u8> = vec![0x03, 0x04, 0x5b, 0x5b, 0x23, 0x46, 0xf5, 0x01, 0xf4];
+        let circle = CircleLocationReference::from_binary(&bytes).unwrap();
+        assert_relative_eq!(circle.longitude, 6.12683, epsilon = 1e-4);
+        assert_relative_eq!(circle.latitude, 49.60851, epsilon = 1e-4);
+        assert_eq!(circle.radius, 500);
+
+        assert!(CircleLocationReference::from_binary(&bytes[..7].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_polygon() {
+        let circle = CircleLocation {
+            longitude: 6.12683,
+            latitude: 49.60851,
+            radius: 500,
+            edges: None,
+        };
+        let center = Point::new(circle.longitude, circle.latitude);
+        let polygon = circle.get_polygon();
+        assert!(polygon.contains(&center));
+        for c in polygon.exterior().coords_iter() {
+            assert_relative_eq!(center.haversine_distance(&Point(c)), 500.0, epsilon = 1.0);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crate::binary_header::BinaryHeader;
+use crate::common::find_route_across_lrps;
+use crate::decodable_reference::DecodableReference;
+use crate::decoding_parameters::DecodingParameters;
+use crate::deserializable_reference::DeserializableReference;
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::line::LineLocationReference;
+use crate::location::Location;
+use crate::location_reference_point::LocationReferencePoint;
+use crate::request_context::RequestContext;
+use async_trait::async_trait;
+use serde::Serialize;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// OpenLR ClosedLine                                                  //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+#[derive(Debug, Serialize)]
+pub struct ClosedLineLocationReference {
+    pub(crate) header: BinaryHeader,
+    pub(crate) lrps: Vec<LocationReferencePoint>,
+}
+
+impl ClosedLineLocationReference {
+    // Given a sequence of Edges that connect the LRPs in a LocRef, generate
+    // a ClosedLineLocation.  Since the last LRP coincides with the first, the
+    // route may begin and end on the same edge, in which case the duplicate
+    // is dropped to close the loop.
+    fn build_location(&self, mut path: Vec<Edge>) -> ClosedLineLocation {
+        if path.len() > 1 && path.first().unwrap().get_id() == path.last().unwrap().get_id() {
+            path.pop();
+        }
+        ClosedLineLocation { edges: path }
+    }
+}
+
+#[async_trait]
+impl DecodableReference for ClosedLineLocationReference {
+    type Peer = ClosedLineLocation;
+    async fn decode(
+        &self,
+        context: &RequestContext<DecodingParameters>,
+    ) -> Result<Location, OpenLrErr> {
+        let (lp, _, _) = find_route_across_lrps(&self.lrps, context).await?;
+        Ok(Location::ClosedLine(self.build_location(lp)))
+    }
+}
+
+impl DeserializableReference for ClosedLineLocationReference {
+    type T = ClosedLineLocationReference;
+    fn from_binary(bytes: &Vec<u8>) -> Result<Self::T, OpenLrErr> {
+        // header (1) + first LRP (9) + intermediate LRPs (7 each) + last LRP attributes (2)
+        let len = bytes.len();
+        if len < 12 || !(len - 12).is_multiple_of(7) {
+            return Err(OpenLrErr::InvalidBinaryStringLength(len));
+        }
+
+        let num_lrps = (len - 12) / 7 + 2;
+        let mut lrps = Vec::new();
+        let mut offset = 10;
+
+        // Parse the first LocationReferencePoint
+        lrps.push(LineLocationReference::build_lrp(
+            &bytes[1..offset],
+            None,
+            0,
+            false,
+        ));
+
+        // Parse the intermediate LRPs
+        for i in 1..num_lrps - 1 {
+            lrps.push(LineLocationReference::build_lrp(
+                &bytes[offset..offset + 7],
+                Some(&lrps[i - 1]),
+                i,
+                false,
+            ));
+            offset += 7;
+        }
+
+        // The last LRP only carries its attributes: its coordinates are those of the first LRP
+        lrps.push(LocationReferencePoint::new_from_byte_array(
+            lrps[0].longitude,
+            lrps[0].latitude,
+            &bytes[offset..offset + 2],
+            num_lrps - 1,
+            true,
+        ));
+
+        Ok(ClosedLineLocationReference {
+            header: BinaryHeader::new(bytes[0]),
+            lrps,
+        })
+    }
+
+    fn from_xml(_xml: &str) -> Result<Self::T, OpenLrErr> {
+        Err(OpenLrErr::UnsupportedLocationTypeError(
+            "ClosedLine in XML".to_owned(),
+        ))
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct ClosedLineLocation {
+    /// The edges forming the loop, in order of travel. The last edge connects
+    /// back to the first.
+    pub edges: Vec<Edge>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fow::FOW;
+    use crate::frc::FRC;
+
+    #[test]
+    fn test_from_binary() {
+        let bytes: Vec<u8> = vec![
+            0x5b, 0xff, 0xb3, 0x53, 0x26, 0x49, 0x24, 0x3f, 0xf6, 0x01, 0x3f, 0x05,
+        ];
+        let cl = ClosedLineLocationReference::from_binary(&bytes).unwrap();
+        assert_eq!(cl.lrps.len(), 2);
+        assert_eq!(cl.lrps[0].longitude, cl.lrps[1].longitude);
+        assert_eq!(cl.lrps[0].latitude, cl.lrps[1].latitude);
+        assert_eq!(cl.lrps[0].dnp, Some((58, 117)));
+        assert!(cl.lrps[1].is_last);
+        assert!(matches!(cl.lrps[1].frc, FRC::FRC7));
+        assert!(matches!(cl.lrps[1].fow, FOW::Other));
+        assert_eq!(cl.lrps[1].bearing_sector, 5);
+
+        assert!(ClosedLineLocationReference::from_binary(&bytes[..11].to_vec()).is_err());
+    }
+}
@@ -1,23 +1,162 @@
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use geo::Coord;
+use geo::{Coord, Intersects, Polygon, Rect};
 use itertools::Itertools;
 
-use crate::astar::find_acceptable_shortest_path;
+use crate::astar::{find_acceptable_shortest_path, max_path_length};
 use crate::candidate_edge::CandidateEdge;
 use crate::decoding_parameters::DecodingParameters;
 use crate::edge::Edge;
 use crate::errors::OpenLrErr;
+use crate::in_memory_map::InMemoryMap;
+use crate::location_reference::LocationReference;
 use crate::location_reference_point::LocationReferencePoint;
 use crate::request_context::RequestContext;
 use crate::route_generator::RouteGenerator;
+use crate::subgraph::Subgraph;
 
 pub const DISTANCE_PER_SECTION: f64 = 15_000.0 / 256.0;
 pub const DEGREES_PER_SECTION: f64 = 360.0 / 32.0;
 
+// A conservative (i.e. short) length of one degree of latitude, in meters, so that the
+// corridor around the LRPs is never narrower than intended
+const METERS_PER_DEGREE: f64 = 110_000.0;
+
 pub(crate) async fn find_route_across_lrps<'a>(
     lrps: &'a Vec<LocationReferencePoint>,
     context: &'a RequestContext<'a, DecodingParameters>,
+) -> Result<(Vec<Edge>, u32, u32), OpenLrErr> {
+    // if the map supports it, fetch the subgraph around the LRPs at once and search it
+    // locally, falling back to the map itself should that fail in any way
+    if context.params.prefetch_subgraphs {
+        match prefetch_subgraph(lrps, context).await {
+            Ok(Some(map)) => match find_route(lrps, &context.with_map(&map)).await {
+                Ok(route) => return Ok(route),
+                Err(e) => context.debug(|| {
+                    format!(
+                        "No route found within the prefetched subgraph ({}), so searching the map",
+                        e
+                    )
+                }),
+            },
+            Ok(None) => (),
+            Err(e) => context.debug(|| {
+                format!(
+                    "Unable to prefetch the subgraph around the LRPs ({}), so searching the map",
+                    e
+                )
+            }),
+        }
+    }
+    find_route(lrps, context).await
+}
+
+// Fetch the subgraph of the corridor between each pair of consecutive LRPs, and merge them
+// into a map to be searched locally, or None if the map does not serve subgraphs
+async fn prefetch_subgraph(
+    lrps: &[LocationReferencePoint],
+    context: &RequestContext<'_, DecodingParameters>,
+) -> Result<Option<InMemoryMap>, OpenLrErr> {
+    let mut ids = HashSet::new();
+    let mut restrictions = HashSet::new();
+    let mut merged = Subgraph::default();
+    for pair in lrps.windows(2) {
+        let subgraph = match context
+            .map_server
+            .get_subgraph(corridor(pair, context.params))
+            .await?
+        {
+            Some(subgraph) => subgraph,
+            None => return Ok(None),
+        };
+        merged.edges.extend(
+            subgraph
+                .edges
+                .into_iter()
+                .filter(|me| ids.insert(me.edge.get_id())),
+        );
+        merged.turn_restrictions.extend(
+            subgraph
+                .turn_restrictions
+                .into_iter()
+                .filter(|r| restrictions.insert(r.edges.clone())),
+        );
+    }
+    context.debug(|| {
+        format!(
+            "Prefetched a subgraph of {} edges around the LRPs",
+            merged.edges.len()
+        )
+    });
+    InMemoryMap::new_from_subgraph(merged).map(Some)
+}
+
+// The area within which candidates and paths between consecutive LRPs are searched for:
+// the bounding box of the LRPs, widened by the search radius and by half the longest
+// acceptable path from any of them (but the last) to the next
+pub(crate) fn corridor(lrps: &[LocationReferencePoint], params: &DecodingParameters) -> Polygon {
+    let buffer = params.search_radius as f64
+        + lrps[..lrps.len() - 1]
+            .iter()
+            .filter_map(|lrp| lrp.dnp)
+            .map(|(_, dnp)| max_path_length(dnp, params) as f64 / 2.0)
+            .fold(0.0, f64::max);
+    let bounds = lrps.iter().fold(
+        Rect::new(
+            Coord {
+                x: lrps[0].longitude,
+                y: lrps[0].latitude,
+            },
+            Coord {
+                x: lrps[0].longitude,
+                y: lrps[0].latitude,
+            },
+        ),
+        |rect, lrp| {
+            Rect::new(
+                Coord {
+                    x: rect.min().x.min(lrp.longitude),
+                    y: rect.min().y.min(lrp.latitude),
+                },
+                Coord {
+                    x: rect.max().x.max(lrp.longitude),
+                    y: rect.max().y.max(lrp.latitude),
+                },
+            )
+        },
+    );
+    widen_rect(&bounds, buffer).to_polygon()
+}
+
+// Widens a rectangle in WGS84 by at least a distance in meters on every side
+pub(crate) fn widen_rect(rect: &Rect, meters: f64) -> Rect {
+    let dlat = meters / METERS_PER_DEGREE;
+    let (min_lat, max_lat) = (
+        (rect.min().y - dlat).max(-90.0),
+        (rect.max().y + dlat).min(90.0),
+    );
+    // a degree of longitude is shortest at the latitude furthest from the equator
+    let cos_lat = min_lat.abs().max(max_lat.abs()).to_radians().cos();
+    let dlon = meters / (METERS_PER_DEGREE * cos_lat);
+    let (min_lon, max_lon) = match dlon.is_finite() && dlon < 180.0 {
+        true => (rect.min().x - dlon, rect.max().x + dlon),
+        false => (-180.0, 180.0),
+    };
+    Rect::new(
+        Coord {
+            x: min_lon,
+            y: min_lat,
+        },
+        Coord {
+            x: max_lon,
+            y: max_lat,
+        },
+    )
+}
+
+async fn find_route<'a>(
+    lrps: &'a Vec<LocationReferencePoint>,
+    context: &'a RequestContext<'a, DecodingParameters>,
 ) -> Result<(Vec<Edge>, u32, u32), OpenLrErr> {
     let lrp_candidates = find_candidates(lrps, context).await?;
 
@@ -118,6 +257,55 @@ pub(crate) async fn find_candidates<'a>(
     return Ok(candidates);
 }
 
+// Rejects a location reference any of whose LRPs lies outside the bounding box of the map
+// (widened by the search radius), before any candidates or paths are searched for
+pub(crate) async fn check_map_coverage(
+    locref: &LocationReference,
+    context: &RequestContext<'_, DecodingParameters>,
+) -> Result<(), OpenLrErr> {
+    let lrps = locref.lrps();
+    if lrps.is_empty() {
+        return Ok(());
+    }
+    let bounds = match context.map_server.get_info().await?.bounding_box {
+        Some(rect) => widen_rect(&rect, context.params.search_radius as f64),
+        None => return Ok(()),
+    };
+    match lrps.iter().find(|lrp| {
+        !bounds.intersects(&Coord {
+            x: lrp.longitude,
+            y: lrp.latitude,
+        })
+    }) {
+        Some(lrp) => Err(OpenLrErr::LrpOutsideMap(
+            lrp.index,
+            lrp.longitude,
+            lrp.latitude,
+        )),
+        None => Ok(()),
+    }
+}
+
+// If the caller asked for them, query the map for all edges intersecting the area
+// covered by an area location
+pub(crate) async fn find_edges_in_area(
+    area: Polygon,
+    context: &RequestContext<'_, DecodingParameters>,
+) -> Result<Option<Vec<Edge>>, OpenLrErr> {
+    if !context.params.return_area_edges {
+        return Ok(None);
+    }
+
+    let edges = context.map_server.get_edges_in_area(area).await?;
+    context.debug(|| {
+        format!(
+            "Edges intersecting area: {:?}",
+            edges.iter().map(|e| e.get_id()).collect::<Vec<i64>>()
+        )
+    });
+    Ok(Some(edges))
+}
+
 pub(crate) async fn find_location_route(
     context: &RequestContext<'_, DecodingParameters>,
     candidates: &Vec<&CandidateEdge<'_>>,
@@ -295,9 +483,100 @@ pub fn calculate_offset(offset: u8, dnp: (u32, u32)) -> (u32, u32) {
     )
 }
 
+// The functions below are the inverses of the parsing helpers above, and are
+// used when serializing a location reference back into its binary form.
+
+pub fn deg2int(deg: f64) -> [u8; 3] {
+    let i = ((deg * 16777216.0) / 360.0 + signum(deg)).round() as i32;
+    let [_, f, m, l] = (i & 0x00ffffff).to_be_bytes();
+    [f, m, l]
+}
+
+pub fn get_relative_coordinate(curr: f64, prev: f64) -> Result<[u8; 2], OpenLrErr> {
+    let relative = ((curr - prev) * 100_000.0).round();
+    if relative < i16::MIN as f64 || relative > i16::MAX as f64 {
+        return Err(OpenLrErr::RelativeCoordinateOutOfRange(curr, prev));
+    }
+    Ok((relative as i16).to_be_bytes())
+}
+
+pub fn bearing2int(bearing: (f64, f64)) -> u8 {
+    // a bearing range maps back onto the sector it was parsed from, while a
+    // scalar bearing maps onto the sector containing it
+    let mid = (bearing.0 + bearing.1) / 2.0;
+    ((mid / DEGREES_PER_SECTION) as u8) & 0b00011111
+}
+
+pub fn dnp2int(dnp: (u32, u32)) -> u8 {
+    let mid = (dnp.0 + dnp.1) as f64 / 2.0;
+    f64::min(255.0, mid / DISTANCE_PER_SECTION) as u8
+}
+
+pub fn offset2int(offset: (u32, u32), dnp: (u32, u32)) -> u8 {
+    // Offset buckets overlap for short DNPs, so pick the bucket that reproduces
+    // the offset range most closely
+    (0..=255u8)
+        .min_by_key(|b| {
+            let (lb, ub) = calculate_offset(*b, dnp);
+            lb.abs_diff(offset.0) + ub.abs_diff(offset.1)
+        })
+        .unwrap()
+}
+
 #[cfg(test)]
 mod tests {
-    use super::calculate_circular_delta;
+    use super::*;
+
+    #[test]
+    fn test_deg2int() {
+        for v in [[0x04, 0x5b, 0x5b], [0x23, 0x46, 0xf5], [0xff, 0xb3, 0x18], [0x00, 0x00, 0x01]] {
+            assert_eq!(deg2int(int2deg(v[0], v[1], v[2])), v);
+        }
+    }
+
+    #[test]
+    fn test_relative_coordinate() {
+        let prev = int2deg(0x04, 0x5b, 0x5b);
+        let curr = get_next_coordinate(0xff, 0xb9, prev);
+        assert_eq!(get_relative_coordinate(curr, prev).unwrap(), [0xff, 0xb9]);
+        assert!(get_relative_coordinate(prev + 1.0, prev).is_err());
+    }
+
+    #[test]
+    fn test_attribute_round_trip() {
+        for b in 0..32u8 {
+            assert_eq!(bearing2int(int2bearing(b)), b);
+        }
+        for d in 0..=255u8 {
+            assert_eq!(dnp2int(distance_to_next_lrp(d)), d);
+        }
+        let dnp = distance_to_next_lrp(10);
+        for o in 0..=255u8 {
+            assert_eq!(calculate_offset(offset2int(calculate_offset(o, dnp), dnp), dnp), calculate_offset(o, dnp));
+        }
+    }
+
+    #[test]
+    fn test_corridor() {
+        use geo::{Contains, HaversineDestination, Point};
+
+        let params = DecodingParameters::default();
+        let lrps = vec![
+            LocationReferencePoint::new_from_byte_array(4.9, 52.3, &[0x1b, 0x04, 0x10], 0, false),
+            LocationReferencePoint::new_from_byte_array(4.91, 52.31, &[0x1b, 0x04], 1, true),
+        ];
+        let buffer = params.search_radius as f64
+            + max_path_length(lrps[0].dnp.unwrap().1, &params) as f64 / 2.0;
+        let area = corridor(&lrps, &params);
+        for (lrp, bearings) in [(&lrps[0], [180.0, 270.0]), (&lrps[1], [0.0, 90.0])] {
+            let p = Point::new(lrp.longitude, lrp.latitude);
+            for bearing in bearings {
+                assert!(area.contains(&p.haversine_destination(bearing, buffer)));
+                assert!(!area.contains(&p.haversine_destination(bearing, 2.0 * buffer)));
+            }
+        }
+    }
+
     #[test]
     fn test_calc_circular_delta() {
         assert_eq!(calculate_circular_delta(360, 359, 32), 1);
@@ -92,6 +92,21 @@ pub struct DecodingParameters {
     ///  from which the line's bearing is to be determined.  HINT: this defaults to 20, and don't
     ///  change it.
     pub bearing_distance: u32,
+
+    /// Whether a decoded GeoCoordinate should be snapped onto the closest edge within
+    ///  the search radius.  If false, the coordinate is returned as-is without consulting
+    ///  the map.
+    pub map_match_geo_coordinates: bool,
+
+    /// Whether the edges intersecting a decoded area location (circle, rectangle, grid
+    ///  or polygon) should be retrieved from the map and returned with the location.
+    ///  The map must support area searches.
+    pub return_area_edges: bool,
+
+    /// Whether, if the map supports it, the subgraph around a line location's LRPs should be
+    ///  fetched at once, and candidates and paths searched in it locally rather than with
+    ///  a request to the map for each line.
+    pub prefetch_subgraphs: bool,
 }
 
 impl DecodingParameters {
@@ -237,6 +252,24 @@ impl DecodingParameters {
     fn set_bearing_distance(&mut self, value: u32) {
         self.bearing_distance = value;
     }
+
+    /// Set whether a decoded GeoCoordinate should be snapped onto the closest edge within
+    ///  the search radius.
+    fn set_map_match_geo_coordinates(&mut self, value: bool) {
+        self.map_match_geo_coordinates = value;
+    }
+
+    /// Set whether the edges intersecting a decoded area location should be retrieved
+    ///  from the map and returned with the location.
+    fn set_return_area_edges(&mut self, value: bool) {
+        self.return_area_edges = value;
+    }
+
+    /// Set whether the subgraph around the LRPs should be fetched from the map, if it
+    ///  supports it, and searched locally.
+    fn set_prefetch_subgraphs(&mut self, value: bool) {
+        self.prefetch_subgraphs = value;
+    }
 }
 
 impl Default for DecodingParameters {
@@ -282,6 +315,9 @@ impl Default for DecodingParameters {
             absolute_snapping_threshold: 10,
             relative_snapping_threshold: 0.05,
             bearing_distance: 20,
+            map_match_geo_coordinates: false,
+            return_area_edges: false,
+            prefetch_subgraphs: true,
         }
     }
 }
@@ -3,5 +3,7 @@ use crate::errors::OpenLrErr;
 pub trait DeserializableReference {
     type T;
     fn from_binary(binary: &Vec<u8>) -> Result<Self::T, OpenLrErr>;
+    /// OpenLR XML is only read for line and point along line location references, so the
+    /// other types report it as an unsupported location type
     fn from_xml(xml: &str) -> Result<Self::T, OpenLrErr>;
 }
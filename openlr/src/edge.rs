@@ -2,12 +2,12 @@ use crate::errors::OpenLrErr;
 use crate::fow::FOW;
 use crate::frc::FRC;
 use geo::{
-    Bearing, Closest, ClosestPoint, Coord, CoordsIter, HaversineDistance, LineInterpolatePoint,
-    LineLocatePoint, LineString, Point,
+    Bearing, Closest, ClosestPoint, Coord, CoordsIter, EuclideanLength, HaversineDistance,
+    LineInterpolatePoint, LineLocatePoint, LineString, Point,
 };
 use log::warn;
 use serde::ser::SerializeStruct;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::hash::{Hash, Hasher};
 use wkt::TryFromWkt;
 
@@ -68,6 +68,32 @@ impl Edge {
         self.geom.line_interpolate_point(fraction).unwrap()
     }
 
+    /// Returns the part of the line geometry between the points which are {@code from}
+    /// and {@code to} meters away from the start of the line, as found by
+    /// get_point_along_line.  The vertices of the line lying between those points are
+    /// kept.
+    pub fn get_sub_line(&self, from: u32, to: u32) -> LineString {
+        let total = self.geom.euclidean_length();
+        if self.len == 0 || total == 0.0 {
+            return self.geom.clone();
+        }
+        let to = u32::min(to, self.len);
+        let from = u32::min(from, to);
+        let (start, end) = (from as f64 / self.len as f64, to as f64 / self.len as f64);
+
+        let mut coords = vec![self.get_point_along_line(from).0];
+        let mut travelled = 0.0;
+        for line in self.geom.lines() {
+            travelled += line.euclidean_length();
+            let fraction = travelled / total;
+            if fraction > start && fraction < end {
+                coords.push(line.end);
+            }
+        }
+        coords.push(self.get_point_along_line(to).0);
+        LineString::new(coords)
+    }
+
     /// Gets the length of the line indicating its real dimension along the geometry of the line. The resolution used
     /// for the length value should be meter [m].
     pub fn get_line_length(&self) -> u32 {
@@ -243,13 +269,54 @@ impl Serialize for Edge {
         state.serialize_field("id", &self.id)?;
         state.serialize_field("meta", &self.meta)?;
         state.serialize_field("len", &self.len)?;
-        state.serialize_field("fow", &self.frc.to_usize())?;
+        state.serialize_field("fow", &self.fow.to_usize())?;
         state.serialize_field("frc", &self.frc.to_usize())?;
         state.serialize_field("geom", &coords)?;
         state.end()
     }
 }
 
+// The serialized form of an Edge, as produced by its Serialize implementation
+#[derive(Deserialize)]
+struct SerializedEdge {
+    id: i64,
+    #[serde(default)]
+    meta: String,
+    len: u32,
+    fow: u8,
+    frc: u8,
+    geom: Vec<(f64, f64)>,
+}
+
+impl<'de> Deserialize<'de> for Edge {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let e = SerializedEdge::deserialize(deserializer)?;
+        if e.fow > 7 || e.frc > 7 {
+            return Err(serde::de::Error::custom(format!(
+                "Invalid FOW ({}) or FRC ({}) for edge {}",
+                e.fow, e.frc, e.id
+            )));
+        }
+        if e.geom.len() < 2 {
+            return Err(serde::de::Error::custom(format!(
+                "The geometry of edge {} needs at least two points",
+                e.id
+            )));
+        }
+        Ok(Edge::new_from_coords(
+            e.id,
+            e.meta,
+            FOW::from_u8(e.fow),
+            FRC::from_u8(e.frc),
+            e.len,
+            e.geom.into_iter().map(Coord::from).collect::<Vec<Coord>>(),
+        ))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use itertools::Itertools;
@@ -274,4 +341,55 @@ mod tests {
         assert_eq!(e1_points.first().unwrap().0.x, -0.42244);
         assert_eq!(e1_points.first().unwrap().0.y, 53.84129);
     }
+
+    #[test]
+    fn test_serialize_deserialize() {
+        let e1 = Edge::new_from_wkt(
+            -8548148,
+            "1152964797973692416".to_owned(),
+            FOW::SingleCarriageway,
+            FRC::FRC5,
+            199,
+            "LINESTRING(-0.42244 53.84129,-0.42241 53.84117)",
+        )
+        .unwrap();
+        let json = serde_json::to_string(&e1).unwrap();
+        let e2: Edge = serde_json::from_str(&json).unwrap();
+        assert_eq!(e2.id, e1.id);
+        assert_eq!(e2.meta, e1.meta);
+        assert_eq!(e2.fow, e1.fow);
+        assert_eq!(e2.frc, e1.frc);
+        assert_eq!(e2.len, e1.len);
+        assert_eq!(e2.geom, e1.geom);
+
+        assert!(serde_json::from_str::<Edge>(
+            r#"{"id": 1, "len": 10, "fow": 9, "frc": 0, "geom": [[0.0, 0.0], [1.0, 1.0]]}"#
+        )
+        .is_err());
+        assert!(serde_json::from_str::<Edge>(
+            r#"{"id": 1, "len": 10, "fow": 3, "frc": 0, "geom": [[0.0, 0.0]]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_get_sub_line() {
+        let e1 = Edge::new_from_wkt(
+            1,
+            "".to_owned(),
+            FOW::SingleCarriageway,
+            FRC::FRC3,
+            100,
+            "LINESTRING(0 0,0.5 0,1 0)",
+        )
+        .unwrap();
+
+        assert_eq!(e1.get_sub_line(0, 100), e1.geom);
+        let sub_line = e1.get_sub_line(25, 75).into_points();
+        assert_eq!(sub_line.len(), 3);
+        assert_eq!(sub_line[0].x(), 0.25);
+        assert_eq!(sub_line[1].x(), 0.5);
+        assert_eq!(sub_line[2].x(), 0.75);
+        assert_eq!(e1.get_sub_line(60, 200).into_points().len(), 2);
+    }
 }
@@ -0,0 +1,398 @@
+use crate::astar::find_shortest_path;
+use crate::common::trim;
+use crate::edge::Edge;
+use crate::encoding_parameters::EncodingParameters;
+use crate::errors::OpenLrErr;
+use crate::frc::FRC;
+use crate::location_reference_point::LocationReferencePoint;
+use crate::request_context::RequestContext;
+
+// Verify that each edge of the location is followed by the next one, and return for
+// each edge (but the last) the number of lines leaving its end node.
+async fn check_connectivity(
+    edges: &[Edge],
+    context: &RequestContext<'_, EncodingParameters>,
+) -> Result<Vec<usize>, OpenLrErr> {
+    let mut out_degrees = Vec::with_capacity(edges.len());
+    for pair in edges.windows(2) {
+        let next_edges = context
+            .map_server
+            .get_next_edges(pair[0].get_id(), pair[0].get_metadata())
+            .await?;
+        if !next_edges.iter().any(|e| e.get_id() == pair[1].get_id()) {
+            return Err(OpenLrErr::LocationNotConnected(
+                pair[0].get_id(),
+                pair[1].get_id(),
+            ));
+        }
+        out_degrees.push(next_edges.len());
+    }
+    Ok(out_degrees)
+}
+
+// Drop the edges at either end of the location that are completely covered by the
+// offsets. Returns the indices of the first and last remaining edges, along with the
+// offsets into those edges.
+fn trim_offsets(
+    edges: &[Edge],
+    pos_offset: u32,
+    neg_offset: u32,
+) -> Result<(usize, usize, u32, u32), OpenLrErr> {
+    let (start, pos_offset) =
+        trim(&mut edges.iter(), pos_offset).ok_or(OpenLrErr::PostiveOffsetTooLong)?;
+    let (end, neg_offset) =
+        trim(&mut edges.iter().rev(), neg_offset).ok_or(OpenLrErr::NegativeOffsetTooLong)?;
+    let end = edges.len() - 1 - end;
+
+    if start > end
+        || (start == end && pos_offset + neg_offset >= edges[start].get_line_length())
+    {
+        return Err(OpenLrErr::NegativeOffsetTooLong);
+    }
+    Ok((start, end, pos_offset, neg_offset))
+}
+
+// Return the number of leading edges of edges[src..=dst] which the shortest path from
+// edges[src] to edges[dst] follows.  If it equals the number of edges, the location
+// between the two edges is the shortest path and needs no further LRPs.
+async fn count_shared_edges(
+    edges: &[Edge],
+    src: usize,
+    dst: usize,
+    context: &RequestContext<'_, EncodingParameters>,
+) -> Result<usize, OpenLrErr> {
+    if src == dst {
+        return Ok(1);
+    }
+
+    // No path which is longer than the location itself is of interest
+    let location_length: u32 = edges[src + 1..dst].iter().map(|e| e.get_line_length()).sum();
+    let path = find_shortest_path(
+        &edges[src],
+        &edges[dst],
+        FRC::FRC7.to_usize(),
+        location_length + 1,
+        context,
+    )
+    .await?
+    .unwrap_or_default();
+
+    Ok(path
+        .iter()
+        .zip(edges[src..=dst].iter())
+        .take_while(|(p, e)| p.get_id() == e.get_id())
+        .count())
+}
+
+// Find the index of the edge at whose start the LRP following the one at edges[src] must be
+// placed, or edges.len() if the last LRP (at the end of the last edge) can follow directly.
+async fn find_next_lrp_position(
+    edges: &[Edge],
+    out_degrees: &[usize],
+    src: usize,
+    context: &RequestContext<'_, EncodingParameters>,
+) -> Result<usize, OpenLrErr> {
+    let n = edges.len();
+    let distance = |dst: usize| -> u32 { edges[src..dst].iter().map(|e| e.get_line_length()).sum() };
+
+    // Ideally, the remainder of the location is the shortest path and is short enough
+    let shared = count_shared_edges(edges, src, n - 1, context).await?;
+    if distance(n) <= context.params.max_dnp && shared == n - src {
+        return Ok(n);
+    }
+
+    // Otherwise, the next LRP is placed no further than the point at which the shortest
+    // path deviates from the location, and no further than the maximum DNP allows
+    let mut dst = usize::min(src + shared, n - 1);
+    while dst > src && distance(dst) > context.params.max_dnp {
+        dst -= 1;
+    }
+    if dst == src {
+        return Err(OpenLrErr::MaximumDnpExceeded(
+            edges[src].get_id(),
+            context.params.max_dnp,
+        ));
+    }
+    while dst > src + 1 && count_shared_edges(edges, src, dst, context).await? != dst - src + 1 {
+        dst -= 1;
+    }
+
+    // Prefer a node at which the route could leave the location: moving the LRP back
+    // along a chain of edges keeps the subpath a shortest path
+    if context.params.place_lrps_on_valid_nodes {
+        if let Some(valid) = (src + 1..=dst).rev().find(|i| out_degrees[i - 1] > 1) {
+            if valid != dst {
+                context.debug(|| {
+                    format!(
+                        "Moving LRP from start of edge {} back to valid node at start of edge {}",
+                        edges[dst].get_id(),
+                        edges[valid].get_id()
+                    )
+                });
+            }
+            dst = valid;
+        }
+    }
+
+    Ok(dst)
+}
+
+// Build the LRP at the start of edges[start], describing the path up to edges[end]
+// (exclusive)
+fn build_lrp(
+    edges: &[Edge],
+    start: usize,
+    end: usize,
+    index: usize,
+    context: &RequestContext<'_, EncodingParameters>,
+) -> LocationReferencePoint {
+    let edge = &edges[start];
+    let p = edge.get_start_point();
+    let bearing = edge.bearing_to_point(context.params.bearing_distance, true);
+    let dnp: u32 = edges[start..end].iter().map(|e| e.get_line_length()).sum();
+    let lowest_frc = edges[start..end]
+        .iter()
+        .map(|e| e.get_frc())
+        .max_by_key(|frc| frc.to_usize())
+        .unwrap();
+
+    LocationReferencePoint {
+        longitude: p.x(),
+        latitude: p.y(),
+        fow: edge.get_fow(),
+        frc: edge.get_frc(),
+        lowest_frc_to_next_point: Some(lowest_frc),
+        bearing: (bearing, bearing),
+        bearing_sector: 0,
+        dnp: Some((dnp, dnp)),
+        index,
+        is_last: false,
+    }
+}
+
+// Build the last LRP, which is located at the end of the last edge
+fn build_last_lrp(
+    edges: &[Edge],
+    index: usize,
+    context: &RequestContext<'_, EncodingParameters>,
+) -> LocationReferencePoint {
+    let edge = edges.last().unwrap();
+    let p = edge.get_end_point();
+    let bearing = edge.bearing_to_point(context.params.bearing_distance, false);
+
+    LocationReferencePoint {
+        longitude: p.x(),
+        latitude: p.y(),
+        fow: edge.get_fow(),
+        frc: edge.get_frc(),
+        lowest_frc_to_next_point: None,
+        bearing: (bearing, bearing),
+        bearing_sector: 0,
+        dnp: None,
+        index,
+        is_last: true,
+    }
+}
+
+// Place the LRPs along a connected path, so that each pair of consecutive LRPs is
+// joined by the shortest path between them
+async fn place_lrps(
+    edges: &[Edge],
+    out_degrees: &[usize],
+    context: &RequestContext<'_, EncodingParameters>,
+) -> Result<Vec<LocationReferencePoint>, OpenLrErr> {
+    // Determine where along the path the LRPs must be placed
+    let mut positions = vec![0];
+    loop {
+        let next = find_next_lrp_position(edges, out_degrees, *positions.last().unwrap(), context)
+            .await?;
+        if next == edges.len() {
+            break;
+        }
+        positions.push(next);
+    }
+    context.debug(|| {
+        format!(
+            "LRPs placed at start of edges: {:?}",
+            positions.iter().map(|i| edges[*i].get_id()).collect::<Vec<i64>>()
+        )
+    });
+
+    let mut lrps = positions
+        .iter()
+        .enumerate()
+        .map(|(index, start)| {
+            let end = positions.get(index + 1).copied().unwrap_or(edges.len());
+            build_lrp(edges, *start, end, index, context)
+        })
+        .collect::<Vec<LocationReferencePoint>>();
+    lrps.push(build_last_lrp(edges, lrps.len(), context));
+
+    Ok(lrps)
+}
+
+// Encode a path through the map as a sequence of LRPs. The offsets are measured from the
+// start of the first edge and the end of the last edge respectively.  Returns the LRPs along
+// with the offsets from the first and last LRPs to the start and end of the location.
+pub(crate) async fn encode_path(
+    edges: &[Edge],
+    pos_offset: u32,
+    neg_offset: u32,
+    context: &RequestContext<'_, EncodingParameters>,
+) -> Result<(Vec<LocationReferencePoint>, u32, u32), OpenLrErr> {
+    if edges.is_empty() {
+        return Err(OpenLrErr::EmptyEdgeVec);
+    }
+
+    let out_degrees = check_connectivity(edges, context).await?;
+    let (start, end, pos_offset, neg_offset) = trim_offsets(edges, pos_offset, neg_offset)?;
+    let edges = &edges[start..=end];
+    let out_degrees = &out_degrees[start..end];
+    context.debug(|| {
+        format!(
+            "Encoding path: {:?} (offsets: {}m, {}m)",
+            edges.iter().map(|e| e.get_id()).collect::<Vec<i64>>(),
+            pos_offset,
+            neg_offset
+        )
+    });
+
+    // Move the first and last LRPs out to valid nodes where possible, falling back to the
+    // path as given should the offsets then no longer fit before the second LRP or after
+    // the second-to-last LRP
+    let length: u32 = edges.iter().map(|e| e.get_line_length()).sum();
+    let (expanded, prefix) =
+        expand_to_valid_nodes(edges, length + context.params.max_dnp, context).await?;
+    if expanded.len() > edges.len() {
+        let suffix = expanded.iter().map(|e| e.get_line_length()).sum::<u32>() - length - prefix;
+        let (pos_offset, neg_offset) = (pos_offset + prefix, neg_offset + suffix);
+        let out_degrees = check_connectivity(&expanded, context).await?;
+        let lrps = place_lrps(&expanded, &out_degrees, context).await?;
+        let dnp = |lrp: &LocationReferencePoint| lrp.dnp.map_or(0, |(lb, _)| lb);
+        if pos_offset < dnp(&lrps[0]) && neg_offset < dnp(&lrps[lrps.len() - 2]) {
+            return Ok((lrps, pos_offset, neg_offset));
+        }
+    }
+
+    let lrps = place_lrps(edges, out_degrees, context).await?;
+    Ok((lrps, pos_offset, neg_offset))
+}
+
+// Extend the path backwards and forwards across invalid nodes, i.e. nodes with exactly one
+// incoming and one outgoing line, at which no route could leave the path, as long as the
+// path stays within the given length.  Returns the extended path along with the length of
+// the edges added before its start.  The path is left as given unless LRPs are to be placed
+// on valid nodes, or should the map not support previous edge searches.
+async fn expand_to_valid_nodes(
+    edges: &[Edge],
+    max_length: u32,
+    context: &RequestContext<'_, EncodingParameters>,
+) -> Result<(Vec<Edge>, u32), OpenLrErr> {
+    if !context.params.place_lrps_on_valid_nodes {
+        return Ok((edges.to_vec(), 0));
+    }
+    match expand_across_invalid_nodes(edges, max_length, context).await {
+        Err(OpenLrErr::PreviousEdgeError(reason)) => {
+            context.debug(|| format!("Not expanding path to valid nodes: {}", reason));
+            Ok((edges.to_vec(), 0))
+        }
+        result => result,
+    }
+}
+
+async fn expand_across_invalid_nodes(
+    edges: &[Edge],
+    max_length: u32,
+    context: &RequestContext<'_, EncodingParameters>,
+) -> Result<(Vec<Edge>, u32), OpenLrErr> {
+    let map = context.map_server;
+    let mut path = edges.to_vec();
+    let mut length: u32 = path.iter().map(|e| e.get_line_length()).sum();
+    let mut prefix: u32 = 0;
+    let fits = |path: &[Edge], length: u32, edge: &Edge| {
+        length + edge.get_line_length() <= max_length
+            && !path.iter().any(|e| e.get_id() == edge.get_id())
+    };
+
+    loop {
+        let first = &path[0];
+        let previous = map
+            .get_previous_edges(first.get_id(), first.get_metadata())
+            .await?;
+        if previous.len() != 1 || !fits(&path, length, &previous[0]) {
+            break;
+        }
+        let edge = &previous[0];
+        if map
+            .get_next_edges(edge.get_id(), edge.get_metadata())
+            .await?
+            .len()
+            != 1
+        {
+            break;
+        }
+        length += edge.get_line_length();
+        prefix += edge.get_line_length();
+        path.insert(0, edge.clone());
+    }
+
+    loop {
+        let last = &path[path.len() - 1];
+        let next = map
+            .get_next_edges(last.get_id(), last.get_metadata())
+            .await?;
+        if next.len() != 1 || !fits(&path, length, &next[0]) {
+            break;
+        }
+        let edge = &next[0];
+        if map
+            .get_previous_edges(edge.get_id(), edge.get_metadata())
+            .await?
+            .len()
+            != 1
+        {
+            break;
+        }
+        length += edge.get_line_length();
+        path.push(edge.clone());
+    }
+
+    if prefix > 0 || path.len() > edges.len() {
+        context.debug(|| {
+            format!(
+                "Expanded path to valid nodes: {:?}",
+                path.iter().map(|e| e.get_id()).collect::<Vec<i64>>()
+            )
+        });
+    }
+    Ok((path, prefix))
+}
+
+// Encode a path, which must not be trimmed, as exactly two LRPs, e.g. the line on which a
+// point location lies.  The path is expanded to valid nodes where possible.  The offset is
+// measured from the start of the first edge.  Returns the LRPs along with the offset from
+// the first LRP, or None if the path cannot be described by two LRPs.
+pub(crate) async fn encode_two_lrp_path(
+    edges: &[Edge],
+    offset: u32,
+    context: &RequestContext<'_, EncodingParameters>,
+) -> Result<Option<(Vec<LocationReferencePoint>, u32)>, OpenLrErr> {
+    if edges.is_empty() {
+        return Err(OpenLrErr::EmptyEdgeVec);
+    }
+    check_connectivity(edges, context).await?;
+
+    // Fall back to the path as given if the expanded one needs intermediate LRPs
+    let mut paths = vec![expand_to_valid_nodes(edges, context.params.max_dnp, context).await?];
+    if paths[0].0.len() > edges.len() {
+        paths.push((edges.to_vec(), 0));
+    }
+    for (path, prefix) in paths {
+        let out_degrees = check_connectivity(&path, context).await?;
+        let lrps = place_lrps(&path, &out_degrees, context).await?;
+        if lrps.len() == 2 {
+            return Ok(Some((lrps, prefix + offset)));
+        }
+    }
+    Ok(None)
+}
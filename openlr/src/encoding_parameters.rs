@@ -1 +1,27 @@
-pub struct EncodingParameters {}
+#[derive(Clone, Debug)]
+pub struct EncodingParameters {
+    /// The distance (in meters) along a line from its start (or end, for the last
+    ///  LRP) to the point used to calculate the LRP's bearing.  This should match
+    ///  the bearing distance used by the decoder.
+    pub bearing_distance: u32,
+
+    /// The maximum distance (in meters) between two consecutive LRPs.  If the
+    ///  distance along the location would exceed this value, an intermediate LRP
+    ///  is inserted.  The binary format cannot represent DNPs above 15000m.
+    pub max_dnp: u32,
+
+    /// Whether an intermediate LRP may be moved back along the location so that it
+    ///  is placed on a valid node (i.e. a node at which the route could deviate
+    ///  from the location) rather than in the middle of a chain of edges.
+    pub place_lrps_on_valid_nodes: bool,
+}
+
+impl Default for EncodingParameters {
+    fn default() -> Self {
+        EncodingParameters {
+            bearing_distance: 20,
+            max_dnp: 15000,
+            place_lrps_on_valid_nodes: true,
+        }
+    }
+}
@@ -17,6 +17,10 @@ pub enum OpenLrErr {
     NextLinesError(i64, String),
     #[error("Error performing nearby edges search: {0}")]
     NearbyEdgesError(String),
+    #[error("Error performing area search: {0}")]
+    AreaSearchError(String),
+    #[error("Error retrieving subgraph: {0}")]
+    SubgraphError(String),
     #[error("Cannot build Location from empty Edge vector")]
     EmptyEdgeVec,
     #[error("Negative offset spans entire found path")]
@@ -51,14 +55,38 @@ pub enum OpenLrErr {
     NoEdgesNearLRP(usize),
     #[error("No candidates found for LRP {0}")]
     NoCandidatesFoundForLRP(usize),
+    #[error("LRP {0} ({1}, {2}) lies outside the bounding box of the map")]
+    LrpOutsideMap(usize, f64, f64),
     #[error("Unable to parse base64 string: {0}. Reason: {1}")]
     Base64ParseError(String, String),
     #[error("Cannot deserialize. Invalid base64 string length: {0}")]
     InvalidBinaryStringLength(usize),
+    #[error("Cannot encode. Edges {0} and {1} of the location are not connected")]
+    LocationNotConnected(i64, i64),
+    #[error("Cannot encode. Edge {0} is longer than the maximum DNP ({1}m)")]
+    MaximumDnpExceeded(i64, u32),
+    #[error("Unable to parse XML: {0}")]
+    XmlParseError(String),
+    #[error("Cannot deserialize. Invalid grid dimensions: {0} x {1}")]
+    InvalidGridDimensions(u16, u16),
+    #[error("Cannot serialize. Coordinate {0} too far from previous coordinate {1}")]
+    RelativeCoordinateOutOfRange(f64, f64),
+    #[error("Cannot serialize. Serialization of {0} location references is not supported")]
+    UnsupportedSerializationError(String),
     #[error("Error from radius search: {0}")]
     NextSearchError(String),
     #[error("Error from next edge search: {0}")]
     NextEdgeError(String),
+    #[error("Error from previous edge search: {0}")]
+    PreviousEdgeError(String),
+    #[error("Error from turn restriction search: {0}")]
+    TurnRestrictionError(String),
+    #[error("Map agent {0} is down: calls fail fast until it recovers")]
+    AgentCircuitOpen(String),
+    #[error("Unable to load map: {0}")]
+    MapLoadError(String),
+    #[error("Invalid feature {1} in {0}: {2}")]
+    InvalidMapFeature(String, usize, String),
     #[error("Unknown error encountered during OpenLR processing")]
     Unknown,
 }
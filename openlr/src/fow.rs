@@ -41,4 +41,32 @@ impl FOW {
             _ => unreachable!(),
         }
     }
+
+    /// Name of the FOW as used by the OpenLR XML format
+    pub fn to_xml(&self) -> &'static str {
+        match self {
+            FOW::Undefined => "UNDEFINED",
+            FOW::Motorway => "MOTORWAY",
+            FOW::MultipleCarriageway => "MULTIPLE_CARRIAGEWAY",
+            FOW::SingleCarriageway => "SINGLE_CARRIAGEWAY",
+            FOW::Roundabout => "ROUNDABOUT",
+            FOW::TrafficSquare => "TRAFFICSQUARE",
+            FOW::SlipRoad => "SLIPROAD",
+            FOW::Other => "OTHER",
+        }
+    }
+
+    pub fn from_xml(s: &str) -> Option<Self> {
+        match s {
+            "UNDEFINED" => Some(FOW::Undefined),
+            "MOTORWAY" => Some(FOW::Motorway),
+            "MULTIPLE_CARRIAGEWAY" => Some(FOW::MultipleCarriageway),
+            "SINGLE_CARRIAGEWAY" => Some(FOW::SingleCarriageway),
+            "ROUNDABOUT" => Some(FOW::Roundabout),
+            "TRAFFICSQUARE" => Some(FOW::TrafficSquare),
+            "SLIPROAD" => Some(FOW::SlipRoad),
+            "OTHER" => Some(FOW::Other),
+            _ => None,
+        }
+    }
 }
@@ -42,4 +42,32 @@ impl FRC {
             _ => unreachable!(),
         }
     }
+
+    /// Name of the FRC as used by the OpenLR XML format
+    pub fn to_xml(&self) -> &'static str {
+        match self {
+            FRC::FRC0 => "FRC0",
+            FRC::FRC1 => "FRC1",
+            FRC::FRC2 => "FRC2",
+            FRC::FRC3 => "FRC3",
+            FRC::FRC4 => "FRC4",
+            FRC::FRC5 => "FRC5",
+            FRC::FRC6 => "FRC6",
+            FRC::FRC7 => "FRC7",
+        }
+    }
+
+    pub fn from_xml(s: &str) -> Option<Self> {
+        match s {
+            "FRC0" => Some(FRC::FRC0),
+            "FRC1" => Some(FRC::FRC1),
+            "FRC2" => Some(FRC::FRC2),
+            "FRC3" => Some(FRC::FRC3),
+            "FRC4" => Some(FRC::FRC4),
+            "FRC5" => Some(FRC::FRC5),
+            "FRC6" => Some(FRC::FRC6),
+            "FRC7" => Some(FRC::FRC7),
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,154 @@
+use crate::binary_header::BinaryHeader;
+use crate::common::int2deg;
+use crate::decodable_reference::DecodableReference;
+use crate::decoding_parameters::DecodingParameters;
+use crate::deserializable_reference::DeserializableReference;
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::location::Location;
+use crate::request_context::RequestContext;
+use async_trait::async_trait;
+use geo::Coord;
+use serde::Serialize;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// OpenLR GeoCoordinate                                               //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+#[derive(Debug, Serialize)]
+pub struct GeoCoordinateLocationReference {
+    pub(crate) header: BinaryHeader,
+    pub longitude: f64,
+    pub latitude: f64,
+}
+
+impl GeoCoordinateLocationReference {
+    // Find the edge closest to the coordinate, along with the distance from the
+    // start of that edge to the projection of the coordinate onto it
+    async fn find_nearest_edge(
+        &self,
+        context: &RequestContext<'_, DecodingParameters>,
+    ) -> Result<Option<(Edge, u32)>, OpenLrErr> {
+        let nearby_edges = context
+            .map_server
+            .get_nearby_edges(
+                vec![Coord {
+                    x: self.longitude,
+                    y: self.latitude,
+                }],
+                context.params.search_radius,
+            )
+            .await?
+            .pop()
+            .unwrap_or_default();
+
+        context.debug(|| {
+            format!(
+                "Edges near coordinate (lon: {}, lat: {}): {:?}",
+                self.longitude,
+                self.latitude,
+                nearby_edges.iter().map(|e| e.get_id()).collect::<Vec<i64>>()
+            )
+        });
+
+        Ok(nearby_edges
+            .into_iter()
+            .min_by_key(|e| e.distance_to_point(self.longitude, self.latitude))
+            .map(|e| {
+                let offset = e.measure_along_line(self.longitude, self.latitude);
+                (e, offset)
+            }))
+    }
+}
+
+#[async_trait]
+impl DecodableReference for GeoCoordinateLocationReference {
+    type Peer = GeoCoordinateLocation;
+    async fn decode(
+        &self,
+        context: &RequestContext<DecodingParameters>,
+    ) -> Result<Location, OpenLrErr> {
+        // A geocoordinate is map independent, so only consult the map if the
+        // caller explicitly asked for the coordinate to be snapped onto an edge
+        let (edge, offset) = if context.params.map_match_geo_coordinates {
+            match self.find_nearest_edge(context).await? {
+                Some((e, offset)) => {
+                    context.debug(|| {
+                        format!(
+                            "Coordinate snapped to edge {} at offset {}m",
+                            e.get_id(),
+                            offset
+                        )
+                    });
+                    (Some(e), Some(offset))
+                }
+                None => {
+                    context.info(|| {
+                        format!(
+                            "No edge within {}m of coordinate (lon: {}, lat: {})",
+                            context.params.search_radius, self.longitude, self.latitude
+                        )
+                    });
+                    (None, None)
+                }
+            }
+        } else {
+            (None, None)
+        };
+
+        Ok(Location::GeoCoordinate(GeoCoordinateLocation {
+            longitude: self.longitude,
+            latitude: self.latitude,
+            edge,
+            offset,
+        }))
+    }
+}
+
+impl DeserializableReference for GeoCoordinateLocationReference {
+    type T = GeoCoordinateLocationReference;
+    fn from_binary(bytes: &Vec<u8>) -> Result<Self::T, OpenLrErr> {
+        // header (1) + absolute coordinate (6)
+        if bytes.len() != 7 {
+            return Err(OpenLrErr::InvalidBinaryStringLength(bytes.len()));
+        }
+
+        Ok(GeoCoordinateLocationReference {
+            header: BinaryHeader::new(bytes[0]),
+            longitude: int2deg(bytes[1], bytes[2], bytes[3]),
+            latitude: int2deg(bytes[4], bytes[5], bytes[6]),
+        })
+    }
+
+    fn from_xml(_xml: &str) -> Result<Self::T, OpenLrErr> {
+        Err(OpenLrErr::UnsupportedLocationTypeError(
+            "GeoCoordinate in XML".to_owned(),
+        ))
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct GeoCoordinateLocation {
+    pub longitude: f64,
+    pub latitude: f64,
+    pub edge: Option<Edge>,
+    pub offset: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_from_binary() {
+        let bytes: Vec<u8> = vec![0x23, 0x04, 0x5b, 0x5b, 0x23, 0x46, 0xf5];
+        let gc = GeoCoordinateLocationReference::from_binary(&bytes).unwrap();
+        assert_relative_eq!(gc.longitude, 6.12683, epsilon = 1e-4);
+        assert_relative_eq!(gc.latitude, 49.60851, epsilon = 1e-4);
+
+        assert!(GeoCoordinateLocationReference::from_binary(&bytes[..6].to_vec()).is_err());
+    }
+}
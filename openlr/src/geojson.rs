@@ -0,0 +1,321 @@
+use geo::{Coord, Polygon, Rect};
+use serde_json::{json, Value};
+
+use crate::edge::Edge;
+use crate::location::Location;
+use crate::location_reference::LocationReference;
+use crate::location_reference_point::LocationReferencePoint;
+use crate::point_along_line::PointAlongLineLocation;
+use crate::xml::midpoint;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// GeoJSON (RFC 7946) representations of locations and references    //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+/// Returns a GeoJSON FeatureCollection describing a decoded location.  Line
+/// geometries are clipped to the positive and negative offsets of the location,
+/// and points along lines are placed at their offset.
+pub fn from_location(loc: &Location) -> Value {
+    feature_collection(location_features(loc))
+}
+
+/// Returns a GeoJSON FeatureCollection describing a location reference: one Point
+/// feature per LRP, carrying its attributes (bearing, DNP, etc.), or the area of
+/// an area location reference.
+pub fn from_location_reference(loc_ref: &LocationReference) -> Value {
+    feature_collection(location_reference_features(loc_ref))
+}
+
+/// Returns the GeoJSON Features describing a decoded location
+pub fn location_features(loc: &Location) -> Vec<Value> {
+    let location_type = loc.type_name();
+    match loc {
+        Location::Line(line) => vec![feature(
+            line_string(&line.get_geometry().0),
+            json!({
+                "locationType": location_type,
+                "edges": edge_ids(&line.edges),
+                "length": line.get_length(),
+                "positiveOffset": line.p_off.map(|(lb, _)| lb),
+                "negativeOffset": line.n_off.map(|(lb, _)| lb),
+            }),
+        )],
+        Location::PointAlongLine(pal) => vec![point_along_line_feature(pal, location_type)],
+        Location::PoiWithAccessPoint(poi) => vec![
+            point_along_line_feature(&poi.access_point, "AccessPoint"),
+            feature(
+                point(poi.poi_longitude, poi.poi_latitude),
+                json!({ "locationType": location_type }),
+            ),
+        ],
+        Location::GeoCoordinate(gc) => vec![feature(
+            point(gc.longitude, gc.latitude),
+            json!({
+                "locationType": location_type,
+                "edge": gc.edge.as_ref().map(|e| e.get_id()),
+                "offset": gc.offset,
+            }),
+        )],
+        Location::Circle(circle) => area_features(
+            &circle.get_polygon(),
+            json!({ "locationType": location_type, "radius": circle.radius }),
+            &circle.edges,
+        ),
+        Location::Rectangle(rectangle) => area_features(
+            &rectangle.get_polygon(),
+            json!({ "locationType": location_type }),
+            &rectangle.edges,
+        ),
+        Location::Grid(grid) => grid
+            .get_polygon()
+            .map(|polygon| {
+                area_features(
+                    &polygon,
+                    json!({
+                        "locationType": location_type,
+                        "columns": grid.columns,
+                        "rows": grid.rows,
+                    }),
+                    &grid.edges,
+                )
+            })
+            .unwrap_or_default(),
+        Location::Polygon(p) => area_features(
+            &p.get_polygon(),
+            json!({ "locationType": location_type }),
+            &p.edges,
+        ),
+        Location::ClosedLine(closed_line) => vec![feature(
+            line_string(&join_edges(&closed_line.edges)),
+            json!({
+                "locationType": location_type,
+                "edges": edge_ids(&closed_line.edges),
+            }),
+        )],
+        Location::Unknown => vec![],
+    }
+}
+
+/// Returns the GeoJSON Features describing a location reference
+pub fn location_reference_features(loc_ref: &LocationReference) -> Vec<Value> {
+    let location_type = loc_ref.type_name();
+    match loc_ref {
+        LocationReference::Line(line) => lrp_features(&line.lrps),
+        LocationReference::PointAlongLine(pal) => lrp_features(&pal.lrps),
+        LocationReference::ClosedLine(closed_line) => lrp_features(&closed_line.lrps),
+        LocationReference::PoiWithAccessPoint(poi) => {
+            let mut features = lrp_features(&poi.lrps);
+            features.push(feature(
+                point(poi.poi_longitude, poi.poi_latitude),
+                json!({ "locationType": location_type }),
+            ));
+            features
+        }
+        LocationReference::GeoCoordinate(gc) => vec![feature(
+            point(gc.longitude, gc.latitude),
+            json!({ "locationType": location_type }),
+        )],
+        LocationReference::Circle(circle) => vec![feature(
+            point(circle.longitude, circle.latitude),
+            json!({ "locationType": location_type, "radius": circle.radius }),
+        )],
+        LocationReference::Rectangle(rectangle) => vec![feature(
+            polygon(&rect(
+                rectangle.lower_left_longitude,
+                rectangle.lower_left_latitude,
+                rectangle.upper_right_longitude,
+                rectangle.upper_right_latitude,
+            )),
+            json!({ "locationType": location_type }),
+        )],
+        // The corners of a grid reference describe its lower-left cell
+        LocationReference::Grid(grid) => vec![feature(
+            polygon(&rect(
+                grid.lower_left_longitude,
+                grid.lower_left_latitude,
+                grid.upper_right_longitude,
+                grid.upper_right_latitude,
+            )),
+            json!({
+                "locationType": location_type,
+                "columns": grid.columns,
+                "rows": grid.rows,
+            }),
+        )],
+        LocationReference::Polygon(p) => vec![feature(
+            json!({
+                "type": "Polygon",
+                "coordinates": [p
+                    .corners
+                    .iter()
+                    .chain(p.corners.first())
+                    .map(|(x, y)| json!([x, y]))
+                    .collect::<Vec<Value>>()],
+            }),
+            json!({ "locationType": location_type }),
+        )],
+    }
+}
+
+/// Wraps a set of Features in a FeatureCollection
+pub fn feature_collection(features: Vec<Value>) -> Value {
+    json!({ "type": "FeatureCollection", "features": features })
+}
+
+fn feature(geometry: Value, properties: Value) -> Value {
+    json!({ "type": "Feature", "geometry": geometry, "properties": properties })
+}
+
+fn point(longitude: f64, latitude: f64) -> Value {
+    json!({ "type": "Point", "coordinates": [longitude, latitude] })
+}
+
+fn line_string(coords: &[Coord]) -> Value {
+    json!({
+        "type": "LineString",
+        "coordinates": coords.iter().map(|c| json!([c.x, c.y])).collect::<Vec<Value>>(),
+    })
+}
+
+fn polygon(p: &Polygon) -> Value {
+    // GeoJSON rings must be closed, which geo::Polygon guarantees
+    json!({
+        "type": "Polygon",
+        "coordinates": [p.exterior().coords().map(|c| json!([c.x, c.y])).collect::<Vec<Value>>()],
+    })
+}
+
+fn rect(min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Polygon {
+    Rect::new(Coord { x: min_x, y: min_y }, Coord { x: max_x, y: max_y }).to_polygon()
+}
+
+fn edge_ids(edges: &[Edge]) -> Vec<i64> {
+    edges.iter().map(|e| e.get_id()).collect::<Vec<i64>>()
+}
+
+fn point_along_line_feature(pal: &PointAlongLineLocation, location_type: &str) -> Value {
+    let (offset, p) = match (pal.edges.first(), pal.p_off) {
+        (Some(edge), Some((lb, _))) => (lb, edge.get_point_along_line(lb)),
+        (Some(edge), None) => (0, edge.get_start_point()),
+        (None, _) => return feature(Value::Null, json!({ "locationType": location_type })),
+    };
+    feature(
+        point(p.x(), p.y()),
+        json!({
+            "locationType": location_type,
+            "edge": pal.edges[0].get_id(),
+            "offset": offset,
+            "sideOfRoad": pal.side_of_road,
+            "orientation": pal.orientation,
+        }),
+    )
+}
+
+// An area location is drawn as its boundary, followed by the edges found within it
+fn area_features(area: &Polygon, properties: Value, edges: &Option<Vec<Edge>>) -> Vec<Value> {
+    let mut features = vec![feature(polygon(area), properties)];
+    if let Some(edges) = edges {
+        features.extend(edges.iter().map(|e| {
+            feature(
+                line_string(&e.geom.0),
+                json!({ "edge": e.get_id(), "frc": e.get_frc().to_xml(), "fow": e.get_fow().to_xml() }),
+            )
+        }));
+    }
+    features
+}
+
+fn lrp_features(lrps: &[LocationReferencePoint]) -> Vec<Value> {
+    lrps.iter()
+        .map(|lrp| {
+            feature(
+                point(lrp.longitude, lrp.latitude),
+                json!({
+                    "index": lrp.index,
+                    "isLast": lrp.is_last,
+                    "frc": lrp.frc.to_xml(),
+                    "fow": lrp.fow.to_xml(),
+                    "bearing": (lrp.bearing.0 + lrp.bearing.1) / 2.0,
+                    "lowestFrcToNextPoint": lrp.lowest_frc_to_next_point.map(|frc| frc.to_xml()),
+                    "dnp": lrp.dnp.map(midpoint),
+                }),
+            )
+        })
+        .collect::<Vec<Value>>()
+}
+
+// Join the geometries of consecutive edges, which share their end and start points
+fn join_edges(edges: &[Edge]) -> Vec<Coord> {
+    let mut coords: Vec<Coord> = Vec::new();
+    for c in edges.iter().flat_map(|e| e.geom.coords()) {
+        if coords.last() != Some(c) {
+            coords.push(*c);
+        }
+    }
+    coords
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fow::FOW;
+    use crate::frc::FRC;
+    use crate::grid::GridLocation;
+    use crate::line::LineLocation;
+
+    fn edge(id: i64, coords: Vec<(f64, f64)>) -> Edge {
+        Edge::new_from_coords(
+            id,
+            "".to_owned(),
+            FOW::SingleCarriageway,
+            FRC::FRC3,
+            100,
+            coords.into_iter().map(Coord::from).collect(),
+        )
+    }
+
+    #[test]
+    fn test_from_location() {
+        let line = Location::Line(LineLocation {
+            edges: vec![edge(1, vec![(0.0, 0.0), (1.0, 0.0)])],
+            p_off: Some((10, 10)),
+            n_off: None,
+        });
+        let collection = from_location(&line);
+        assert_eq!(collection["type"], "FeatureCollection");
+        let feature = &collection["features"][0];
+        assert_eq!(feature["geometry"]["type"], "LineString");
+        assert_eq!(feature["geometry"]["coordinates"][0][0], 0.1);
+        assert_eq!(feature["properties"]["edges"][0], 1);
+        assert_eq!(feature["properties"]["positiveOffset"], 10);
+        assert_eq!(feature["properties"]["length"], 90);
+
+        // a grid without rows covers no area to draw
+        let grid = Location::Grid(GridLocation {
+            lower_left_longitude: 0.0,
+            lower_left_latitude: 0.0,
+            upper_right_longitude: 1.0,
+            upper_right_latitude: 1.0,
+            columns: 2,
+            rows: 0,
+            edges: None,
+        });
+        assert!(location_features(&grid).is_empty());
+    }
+
+    #[test]
+    fn test_location_reference_features() {
+        let loc_ref = crate::deserialize_binary("C/7VOCaEbSu/BP+5AMUrbJEQ").unwrap();
+        let features = location_reference_features(&loc_ref);
+        assert_eq!(features.len(), 2);
+        for (i, feature) in features.iter().enumerate() {
+            assert_eq!(feature["geometry"]["type"], "Point");
+            assert_eq!(feature["properties"]["index"], i);
+            assert_eq!(feature["properties"]["isLast"], i == 1);
+        }
+        assert!(features[0]["properties"]["dnp"].is_number());
+        assert!(features[1]["properties"]["dnp"].is_null());
+    }
+}
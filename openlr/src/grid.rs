@@ -0,0 +1,187 @@
+use crate::binary_header::BinaryHeader;
+use crate::common::find_edges_in_area;
+use crate::decodable_reference::DecodableReference;
+use crate::decoding_parameters::DecodingParameters;
+use crate::deserializable_reference::DeserializableReference;
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::location::Location;
+use crate::rectangle::parse_corners;
+use crate::request_context::RequestContext;
+use async_trait::async_trait;
+use geo::{Coord, Polygon, Rect};
+use serde::Serialize;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// OpenLR Grid                                                        //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+/// A grid is described by its lower-left cell, which is replicated `columns`
+/// times to the east and `rows` times to the north.
+#[derive(Debug, Serialize)]
+pub struct GridLocationReference {
+    pub(crate) header: BinaryHeader,
+    pub lower_left_longitude: f64,
+    pub lower_left_latitude: f64,
+    pub upper_right_longitude: f64,
+    pub upper_right_latitude: f64,
+    pub columns: u16,
+    pub rows: u16,
+}
+
+#[async_trait]
+impl DecodableReference for GridLocationReference {
+    type Peer = GridLocation;
+    async fn decode(
+        &self,
+        context: &RequestContext<DecodingParameters>,
+    ) -> Result<Location, OpenLrErr> {
+        let mut grid = GridLocation {
+            lower_left_longitude: self.lower_left_longitude,
+            lower_left_latitude: self.lower_left_latitude,
+            upper_right_longitude: self.upper_right_longitude,
+            upper_right_latitude: self.upper_right_latitude,
+            columns: self.columns,
+            rows: self.rows,
+            edges: None,
+        };
+        grid.edges = find_edges_in_area(grid.get_polygon()?, context).await?;
+        Ok(Location::Grid(grid))
+    }
+}
+
+impl DeserializableReference for GridLocationReference {
+    type T = GridLocationReference;
+    fn from_binary(bytes: &Vec<u8>) -> Result<Self::T, OpenLrErr> {
+        // header (1) + lower-left cell (10 or 12) + columns (2) + rows (2)
+        let len = bytes.len();
+        if len != 15 && len != 17 {
+            return Err(OpenLrErr::InvalidBinaryStringLength(len));
+        }
+
+        let (lower_left_longitude, lower_left_latitude, upper_right_longitude, upper_right_latitude) =
+            parse_corners(&bytes[1..len - 4]);
+        let columns = u16::from_be_bytes([bytes[len - 4], bytes[len - 3]]);
+        let rows = u16::from_be_bytes([bytes[len - 2], bytes[len - 1]]);
+        if columns == 0 || rows == 0 {
+            return Err(OpenLrErr::InvalidGridDimensions(columns, rows));
+        }
+
+        Ok(GridLocationReference {
+            header: BinaryHeader::new(bytes[0]),
+            lower_left_longitude,
+            lower_left_latitude,
+            upper_right_longitude,
+            upper_right_latitude,
+            columns,
+            rows,
+        })
+    }
+
+    fn from_xml(_xml: &str) -> Result<Self::T, OpenLrErr> {
+        Err(OpenLrErr::UnsupportedLocationTypeError(
+            "Grid in XML".to_owned(),
+        ))
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct GridLocation {
+    pub lower_left_longitude: f64,
+    pub lower_left_latitude: f64,
+    pub upper_right_longitude: f64,
+    pub upper_right_latitude: f64,
+    pub columns: u16,
+    pub rows: u16,
+    pub edges: Option<Vec<Edge>>,
+}
+
+impl GridLocation {
+    // Returns the rectangle covering the cell in the given column and row
+    fn get_cell_rect(&self, column: u16, row: u16) -> Rect {
+        let width = self.upper_right_longitude - self.lower_left_longitude;
+        let height = self.upper_right_latitude - self.lower_left_latitude;
+        let min = Coord {
+            x: self.lower_left_longitude + column as f64 * width,
+            y: self.lower_left_latitude + row as f64 * height,
+        };
+        Rect::new(
+            min,
+            Coord {
+                x: min.x + width,
+                y: min.y + height,
+            },
+        )
+    }
+
+    /// Returns the polygon covering the entire grid, which has none should it
+    /// have no columns or rows
+    pub fn get_polygon(&self) -> Result<Polygon, OpenLrErr> {
+        let (last_column, last_row) = self
+            .columns
+            .checked_sub(1)
+            .zip(self.rows.checked_sub(1))
+            .ok_or(OpenLrErr::InvalidGridDimensions(self.columns, self.rows))?;
+        let lower_left = self.get_cell_rect(0, 0).min();
+        let upper_right = self.get_cell_rect(last_column, last_row).max();
+        Ok(Rect::new(lower_left, upper_right).to_polygon())
+    }
+
+    /// Returns the polygon of each cell in the grid, row by row starting
+    /// with the lower-left cell
+    pub fn get_cells(&self) -> Vec<Polygon> {
+        (0..self.rows)
+            .flat_map(|row| (0..self.columns).map(move |column| (column, row)))
+            .map(|(column, row)| self.get_cell_rect(column, row).to_polygon())
+            .collect::<Vec<Polygon>>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+    use geo::Area;
+
+    #[test]
+    fn test_from_binary() {
+        let bytes: Vec<u8> = vec![
+            0x43, 0x04, 0x5b, 0x5b, 0x23, 0x46, 0xf5, 0x03, 0xe8, 0x01, 0xf4, 0x00, 0x03, 0x00,
+            0x02,
+        ];
+        let grid = GridLocationReference::from_binary(&bytes).unwrap();
+        assert_eq!(grid.columns, 3);
+        assert_eq!(grid.rows, 2);
+        assert_relative_eq!(grid.upper_right_longitude, grid.lower_left_longitude + 0.01);
+
+        let mut bytes = bytes;
+        bytes[12] = 0x00;
+        assert!(GridLocationReference::from_binary(&bytes).is_err());
+    }
+
+    #[test]
+    fn test_cells() {
+        let grid = GridLocation {
+            lower_left_longitude: 0.0,
+            lower_left_latitude: 0.0,
+            upper_right_longitude: 1.0,
+            upper_right_latitude: 2.0,
+            columns: 3,
+            rows: 2,
+            edges: None,
+        };
+        let cells = grid.get_cells();
+        assert_eq!(cells.len(), 6);
+        assert_relative_eq!(grid.get_polygon().unwrap().unsigned_area(), 12.0);
+        assert_relative_eq!(cells.iter().map(|c| c.unsigned_area()).sum::<f64>(), 12.0);
+
+        let empty = GridLocation { rows: 0, ..grid };
+        assert!(empty.get_cells().is_empty());
+        assert!(matches!(
+            empty.get_polygon(),
+            Err(OpenLrErr::InvalidGridDimensions(3, 0))
+        ));
+    }
+}
@@ -0,0 +1,402 @@
+use async_trait::async_trait;
+use geo::{BoundingRect, Coord, Intersects, Polygon, Rect};
+use rstar::primitives::{GeomWithData, Rectangle};
+use rstar::{RTree, AABB};
+use rustc_hash::FxHashMap;
+
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::map::Map;
+use crate::map_info::{AttributeSemantics, Capabilities, MapInfo};
+use crate::map_loader::osm::OsmMapping;
+use crate::map_loader::{self, AttributeMapping};
+use crate::subgraph::Subgraph;
+use crate::turn_restriction::TurnRestriction;
+
+// A conservative (i.e. short) length of one degree of latitude, in meters, so that
+// the envelope used to search around a point always covers the search radius
+const METERS_PER_DEGREE: f64 = 110_000.0;
+
+type IndexedEnvelope = GeomWithData<Rectangle<[f64; 2]>, usize>;
+
+/// An edge of an InMemoryMap, along with the nodes at which it starts and ends
+#[derive(Debug, Clone)]
+pub struct MapEdge {
+    pub edge: Edge,
+    pub start_node: i64,
+    pub end_node: i64,
+}
+
+impl MapEdge {
+    pub fn new(edge: Edge, start_node: i64, end_node: i64) -> Self {
+        MapEdge {
+            edge,
+            start_node,
+            end_node,
+        }
+    }
+}
+
+/// A Map held in memory, with an R-tree over the envelopes of its edges for
+/// radius and area searches, and indices from each node to the edges which
+/// start and end there for following the network.
+pub struct InMemoryMap {
+    edges: Vec<MapEdge>,
+    ids: FxHashMap<i64, usize>,
+    rtree: RTree<IndexedEnvelope>,
+    outgoing: FxHashMap<i64, Vec<usize>>,
+    incoming: FxHashMap<i64, Vec<usize>>,
+    restrictions: FxHashMap<i64, Vec<TurnRestriction>>,
+    // the parts of the map's info which are not derived from its edges
+    info: MapInfo,
+}
+
+impl InMemoryMap {
+    /// Builds the map and its indices from a set of edges.  Edge ids must be unique,
+    /// and each edge needs a geometry of at least two points.
+    pub fn new(edges: Vec<MapEdge>) -> Result<Self, OpenLrErr> {
+        let mut ids = FxHashMap::default();
+        let mut outgoing = FxHashMap::<i64, Vec<usize>>::default();
+        let mut incoming = FxHashMap::<i64, Vec<usize>>::default();
+        let mut envelopes = Vec::with_capacity(edges.len());
+
+        for (i, me) in edges.iter().enumerate() {
+            if ids.insert(me.edge.get_id(), i).is_some() {
+                return Err(OpenLrErr::MapLoadError(format!(
+                    "Duplicate edge id {}",
+                    me.edge.get_id()
+                )));
+            }
+            let rect = match me.edge.geom.bounding_rect() {
+                Some(rect) if me.edge.geom.0.len() >= 2 => rect,
+                _ => {
+                    return Err(OpenLrErr::MapLoadError(format!(
+                        "The geometry of edge {} needs at least two points",
+                        me.edge.get_id()
+                    )))
+                }
+            };
+            envelopes.push(IndexedEnvelope::new(
+                Rectangle::from_corners(rect.min().x_y().into(), rect.max().x_y().into()),
+                i,
+            ));
+            outgoing.entry(me.start_node).or_default().push(i);
+            incoming.entry(me.end_node).or_default().push(i);
+        }
+
+        Ok(InMemoryMap {
+            edges,
+            ids,
+            // bulk loading builds a better balanced tree, much faster than insertion
+            rtree: RTree::bulk_load(envelopes),
+            outgoing,
+            incoming,
+            restrictions: FxHashMap::default(),
+            info: MapInfo::default(),
+        })
+    }
+
+    /// Builds the map from a subgraph of another map.  Turn restrictions which leave the
+    /// subgraph are dropped.
+    pub fn new_from_subgraph(subgraph: Subgraph) -> Result<Self, OpenLrErr> {
+        let mut map = InMemoryMap::new(subgraph.edges)?;
+        let restrictions = subgraph
+            .turn_restrictions
+            .into_iter()
+            .filter(|r| r.edges.iter().all(|id| map.ids.contains_key(id)))
+            .collect::<Vec<TurnRestriction>>();
+        map.add_turn_restrictions(restrictions)?;
+        Ok(map)
+    }
+
+    /// Adds turn restrictions to the map.  Each restriction must follow two or more
+    /// of the map's edges, each starting where the last ends.
+    pub fn add_turn_restrictions(
+        &mut self,
+        restrictions: Vec<TurnRestriction>,
+    ) -> Result<(), OpenLrErr> {
+        for restriction in restrictions {
+            if restriction.edges.len() < 2 {
+                return Err(OpenLrErr::MapLoadError(format!(
+                    "The turn restriction {:?} needs at least two edges",
+                    restriction.edges
+                )));
+            }
+            for pair in restriction.edges.windows(2) {
+                let (from, to) = match (self.get_edge(pair[0]), self.get_edge(pair[1])) {
+                    (Some(from), Some(to)) => (from, to),
+                    _ => {
+                        return Err(OpenLrErr::MapLoadError(format!(
+                            "The turn restriction {:?} refers to an unknown edge",
+                            restriction.edges
+                        )))
+                    }
+                };
+                if from.end_node != to.start_node {
+                    return Err(OpenLrErr::MapLoadError(format!(
+                        "The edges {} and {} of the turn restriction {:?} are not connected",
+                        pair[0], pair[1], restriction.edges
+                    )));
+                }
+            }
+            self.restrictions
+                .entry(restriction.edges[0])
+                .or_default()
+                .push(restriction);
+        }
+        Ok(())
+    }
+
+    /// Builds the map from a CSV file in the format of the test data, as read by
+    /// [`map_loader::csv::load`]
+    pub fn new_from_csv(filename: &str) -> Result<Self, OpenLrErr> {
+        InMemoryMap::new(map_loader::csv::load(filename)?)
+    }
+
+    /// Builds the map from a GeoJSON, GeoPackage or FlatGeobuf file, as read by
+    /// [`map_loader::load`]
+    pub fn new_from_file(path: &str, mapping: &AttributeMapping) -> Result<Self, OpenLrErr> {
+        InMemoryMap::new(map_loader::load(path, mapping)?)
+    }
+
+    /// Builds the map from the roads of an OpenStreetMap PBF extract, as read by
+    /// [`map_loader::osm::load`]
+    pub fn new_from_osm_pbf(path: &str, mapping: &OsmMapping) -> Result<Self, OpenLrErr> {
+        let mut map = InMemoryMap::new(map_loader::osm::load(path, mapping)?)?;
+        map.info.vendor = "OpenStreetMap".to_owned();
+        let semantics = AttributeSemantics::Derived("OSM highway and junction tags".to_owned());
+        map.info.fow_semantics = semantics.clone();
+        map.info.frc_semantics = semantics;
+        Ok(map)
+    }
+
+    /// Sets the vendor and version of the map, as reported by its info
+    pub fn set_source(&mut self, vendor: &str, version: &str) {
+        self.info.vendor = vendor.to_owned();
+        self.info.version = version.to_owned();
+    }
+
+    /// Returns the number of edges in the map
+    pub fn len(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edges.is_empty()
+    }
+
+    /// Returns the edge with the given id, along with its start and end nodes
+    pub fn get_edge(&self, id: i64) -> Option<&MapEdge> {
+        self.ids.get(&id).map(|i| &self.edges[*i])
+    }
+
+    // Returns the edge with the given id, or else the given kind of error
+    fn get_indexed_edge(
+        &self,
+        id: i64,
+        err: fn(String) -> OpenLrErr,
+    ) -> Result<&MapEdge, OpenLrErr> {
+        self.get_edge(id)
+            .ok_or_else(|| err(format!("Unknown edge {}", id)))
+    }
+
+    // The envelope of the points within `radius` meters of a point
+    fn search_envelope(c: &Coord, radius: u32) -> AABB<[f64; 2]> {
+        let dlat = radius as f64 / METERS_PER_DEGREE;
+        let max_lat = f64::min(90.0, c.y.abs() + dlat);
+        let cos = max_lat.to_radians().cos();
+        let dlon = if cos * METERS_PER_DEGREE * 180.0 > radius as f64 {
+            radius as f64 / (METERS_PER_DEGREE * cos)
+        } else {
+            // close to the poles, every longitude lies within reach
+            360.0
+        };
+        AABB::from_corners([c.x - dlon, c.y - dlat], [c.x + dlon, c.y + dlat])
+    }
+}
+
+#[async_trait]
+impl Map for InMemoryMap {
+    async fn get_nearby_edges(
+        &self,
+        points: Vec<Coord>,
+        radius: u32,
+    ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+        Ok(points
+            .iter()
+            .map(|c| {
+                self.rtree
+                    .locate_in_envelope_intersecting(&InMemoryMap::search_envelope(c, radius))
+                    .map(|envelope| &self.edges[envelope.data].edge)
+                    .filter(|e| e.distance_to_point(c.x, c.y) <= radius)
+                    .cloned()
+                    .collect::<Vec<Edge>>()
+            })
+            .collect::<Vec<Vec<Edge>>>())
+    }
+
+    async fn get_next_edges(&self, id: i64, _meta: String) -> Result<Vec<Edge>, OpenLrErr> {
+        let src = self.get_indexed_edge(id, OpenLrErr::NextEdgeError)?;
+        Ok(self
+            .outgoing
+            .get(&src.end_node)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.edges[*i])
+            .filter(|me| me.end_node != src.start_node)
+            .map(|me| me.edge.clone())
+            .collect::<Vec<Edge>>())
+    }
+
+    async fn get_previous_edges(&self, id: i64, _meta: String) -> Result<Vec<Edge>, OpenLrErr> {
+        let dst = self.get_indexed_edge(id, OpenLrErr::PreviousEdgeError)?;
+        Ok(self
+            .incoming
+            .get(&dst.start_node)
+            .into_iter()
+            .flatten()
+            .map(|i| &self.edges[*i])
+            .filter(|me| me.start_node != dst.end_node)
+            .map(|me| me.edge.clone())
+            .collect::<Vec<Edge>>())
+    }
+
+    async fn get_info(&self) -> Result<MapInfo, OpenLrErr> {
+        let bounding_box = match self.edges.is_empty() {
+            true => None,
+            false => {
+                let envelope = self.rtree.root().envelope();
+                Some(Rect::new(
+                    Coord::from(envelope.lower()),
+                    Coord::from(envelope.upper()),
+                ))
+            }
+        };
+        Ok(MapInfo {
+            bounding_box,
+            capabilities: Capabilities {
+                turn_restrictions: !self.restrictions.is_empty(),
+                area_search: true,
+                ..Default::default()
+            },
+            ..self.info.clone()
+        })
+    }
+
+    async fn get_turn_restrictions(
+        &self,
+        id: i64,
+        _meta: String,
+    ) -> Result<Vec<TurnRestriction>, OpenLrErr> {
+        self.get_indexed_edge(id, OpenLrErr::TurnRestrictionError)?;
+        Ok(self.restrictions.get(&id).cloned().unwrap_or_default())
+    }
+
+    async fn get_edges_in_area(&self, area: Polygon) -> Result<Vec<Edge>, OpenLrErr> {
+        let rect = match area.bounding_rect() {
+            Some(rect) => rect,
+            None => return Ok(vec![]),
+        };
+        let envelope = AABB::from_corners(rect.min().x_y().into(), rect.max().x_y().into());
+        Ok(self
+            .rtree
+            .locate_in_envelope_intersecting(&envelope)
+            .map(|envelope| &self.edges[envelope.data].edge)
+            .filter(|e| area.intersects(&e.geom))
+            .cloned()
+            .collect::<Vec<Edge>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fow::FOW;
+    use crate::frc::FRC;
+    use futures::executor::block_on;
+
+    fn edge(id: i64, coords: Vec<(f64, f64)>) -> Edge {
+        Edge::new_from_coords(
+            id,
+            "".to_owned(),
+            FOW::SingleCarriageway,
+            FRC::FRC3,
+            100,
+            coords.into_iter().map(Coord::from).collect(),
+        )
+    }
+
+    #[test]
+    fn test_new() {
+        let map = InMemoryMap::new(vec![
+            MapEdge::new(edge(1, vec![(0.0, 0.0), (0.001, 0.0)]), 1, 2),
+            MapEdge::new(edge(-1, vec![(0.001, 0.0), (0.0, 0.0)]), 2, 1),
+            MapEdge::new(edge(2, vec![(0.001, 0.0), (0.002, 0.0)]), 2, 3),
+            MapEdge::new(edge(3, vec![(0.001, 0.0), (0.001, 0.001)]), 2, 4),
+        ])
+        .unwrap();
+        assert_eq!(map.len(), 4);
+        let info = block_on(map.get_info()).unwrap();
+        assert_eq!(
+            info.bounding_box,
+            Some(Rect::new(
+                Coord { x: 0.0, y: 0.0 },
+                Coord { x: 0.002, y: 0.001 }
+            ))
+        );
+        assert!(info.capabilities.area_search && !info.capabilities.turn_restrictions);
+
+        // the U-turn onto edge -1 is excluded
+        let mut next = block_on(map.get_next_edges(1, String::new()))
+            .unwrap()
+            .iter()
+            .map(|e| e.get_id())
+            .collect::<Vec<i64>>();
+        next.sort();
+        assert_eq!(next, vec![2, 3]);
+        let previous = block_on(map.get_previous_edges(2, String::new())).unwrap();
+        assert_eq!(previous.len(), 1);
+        assert_eq!(previous[0].get_id(), 1);
+        assert!(matches!(
+            block_on(map.get_next_edges(5, String::new())),
+            Err(OpenLrErr::NextEdgeError(_))
+        ));
+        assert!(matches!(
+            block_on(map.get_previous_edges(5, String::new())),
+            Err(OpenLrErr::PreviousEdgeError(_))
+        ));
+        assert!(matches!(
+            block_on(map.get_turn_restrictions(5, String::new())),
+            Err(OpenLrErr::TurnRestrictionError(_))
+        ));
+
+        // edge 3 lies some 100m north of the point, the others within a meter
+        let nearby = block_on(map.get_nearby_edges(
+            vec![Coord {
+                x: 0.0015,
+                y: 0.00001,
+            }],
+            10,
+        ))
+        .unwrap();
+        assert_eq!(nearby[0].len(), 1);
+        assert_eq!(nearby[0][0].get_id(), 2);
+
+        assert!(InMemoryMap::new(vec![
+            MapEdge::new(edge(1, vec![(0.0, 0.0), (0.001, 0.0)]), 1, 2),
+            MapEdge::new(edge(1, vec![(0.001, 0.0), (0.0, 0.0)]), 2, 1),
+        ])
+        .is_err());
+        assert!(InMemoryMap::new(vec![MapEdge::new(edge(1, vec![(0.0, 0.0)]), 1, 2)]).is_err());
+    }
+
+    #[test]
+    fn test_search_envelope() {
+        let envelope = InMemoryMap::search_envelope(&Coord { x: 4.9, y: 52.3 }, 1000);
+        assert!(envelope.lower()[1] < 52.3 - 0.009 && envelope.upper()[1] > 52.3 + 0.009);
+        assert!(envelope.lower()[0] < 4.9 - 0.0147 && envelope.upper()[0] > 4.9 + 0.0147);
+
+        let envelope = InMemoryMap::search_envelope(&Coord { x: 0.0, y: 89.99 }, 10000);
+        assert!(envelope.lower()[0] <= -180.0 && envelope.upper()[0] >= 180.0);
+    }
+}
@@ -1,17 +1,24 @@
 pub(crate) mod astar;
 pub(crate) mod binary_header;
 pub(crate) mod candidate_edge;
+pub mod caching_map;
+pub mod circle;
+pub mod closed_line;
 pub(crate) mod common;
 pub(crate) mod decodable_reference;
 pub mod decoding_parameters;
 pub(crate) mod deserializable_reference;
 pub mod edge;
-// pub mod geojson;
+pub mod geojson;
 pub(crate) mod encodable_location;
+pub(crate) mod encoder;
 pub mod encoding_parameters;
 pub mod errors;
 pub mod fow;
 pub mod frc;
+pub mod geo_coordinate;
+pub mod grid;
+pub mod in_memory_map;
 pub mod line;
 pub mod location;
 pub mod location_reference;
@@ -19,15 +26,27 @@ pub(crate) mod location_reference_point;
 pub(crate) mod location_type;
 pub mod log;
 pub mod map;
+pub mod map_info;
+pub mod map_loader;
+pub mod orientation;
+pub mod point_along_line;
+pub mod poi_with_access_point;
+pub mod polygon;
+pub mod rectangle;
 pub(crate) mod request_context;
 pub mod request_result;
 pub(crate) mod route_generator;
 pub(crate) mod serializable_reference;
+pub mod side_of_road;
+pub mod subgraph;
+pub mod turn_restriction;
+pub(crate) mod xml;
 
 use std::time::{Duration, SystemTime};
 
 use crate::decodable_reference::DecodableReference;
 use crate::decoding_parameters::DecodingParameters;
+use crate::encodable_location::EncodableLocation;
 use crate::encoding_parameters::EncodingParameters;
 use crate::errors::OpenLrErr;
 use crate::location::Location;
@@ -35,10 +54,19 @@ use crate::location_reference::LocationReference;
 use crate::location_type::LocationType;
 use crate::log::LogLevel;
 use crate::map::Map;
+use circle::CircleLocationReference;
+use closed_line::ClosedLineLocationReference;
 use deserializable_reference::DeserializableReference;
+use geo_coordinate::GeoCoordinateLocationReference;
+use grid::GridLocationReference;
 use line::LineLocationReference;
+use point_along_line::PointAlongLineLocationReference;
+use poi_with_access_point::PoiWithAccessPointLocationReference;
+use polygon::PolygonLocationReference;
+use rectangle::RectangleLocationReference;
 use crate::log::LogEntry;
 use request_context::RequestContext;
+use serializable_reference::SerializableReference;
 use request_result::RequestResult;
 
 pub async fn decode(
@@ -50,9 +78,19 @@ pub async fn decode(
 ) -> RequestResult<Location> {
     let start_time = SystemTime::now();
     let context = RequestContext::<DecodingParameters>::new(map_server, params, level);
-    let result = match locref {
-        LocationReference::Line(line_loc_ref) => line_loc_ref.decode(&context).await,
-        lr => Err(OpenLrErr::UnsupportedLocationTypeError(format!("{:?}", lr))),
+    let result = match common::check_map_coverage(locref, &context).await {
+        Err(e) => Err(e),
+        Ok(()) => match locref {
+            LocationReference::Line(line_loc_ref) => line_loc_ref.decode(&context).await,
+            LocationReference::PointAlongLine(pal_loc_ref) => pal_loc_ref.decode(&context).await,
+            LocationReference::PoiWithAccessPoint(poi_loc_ref) => poi_loc_ref.decode(&context).await,
+            LocationReference::GeoCoordinate(geo_loc_ref) => geo_loc_ref.decode(&context).await,
+            LocationReference::Circle(circle_loc_ref) => circle_loc_ref.decode(&context).await,
+            LocationReference::Rectangle(rect_loc_ref) => rect_loc_ref.decode(&context).await,
+            LocationReference::Grid(grid_loc_ref) => grid_loc_ref.decode(&context).await,
+            LocationReference::Polygon(poly_loc_ref) => poly_loc_ref.decode(&context).await,
+            LocationReference::ClosedLine(cl_loc_ref) => cl_loc_ref.decode(&context).await,
+        },
     };
 
     let elapsed = SystemTime::now().duration_since(start_time).unwrap();
@@ -65,7 +103,14 @@ pub async fn encode(
     params: &EncodingParameters,
     level: LogLevel,
 ) -> Result<LocationReference, OpenLrErr> {
-    todo!()
+    let context = RequestContext::<EncodingParameters>::new(map_server, params, level);
+    match loc {
+        Location::Line(line_loc) => line_loc.encode(&context).await,
+        Location::PointAlongLine(pal_loc) => pal_loc.encode(&context).await,
+        loc => Err(OpenLrErr::UnsupportedLocationTypeError(
+            loc.type_name().to_owned(),
+        )),
+    }
 }
 
 pub fn deserialize_binary(bin: &str) -> Result<LocationReference, OpenLrErr> {
@@ -81,13 +126,70 @@ pub fn deserialize_binary(bin: &str) -> Result<LocationReference, OpenLrErr> {
 
     match LocationType::from(&bytes) {
         LocationType::Line => Ok(LocationReference::Line(
-            LineLocationReference::from_binary(&bytes).unwrap(),
+            LineLocationReference::from_binary(&bytes)?,
+        )),
+        LocationType::GeoCoordinate => Ok(LocationReference::GeoCoordinate(
+            GeoCoordinateLocationReference::from_binary(&bytes)?,
+        )),
+        LocationType::PointAlongLine => Ok(LocationReference::PointAlongLine(
+            PointAlongLineLocationReference::from_binary(&bytes)?,
+        )),
+        LocationType::PoiWithAccessPoint => Ok(LocationReference::PoiWithAccessPoint(
+            PoiWithAccessPointLocationReference::from_binary(&bytes)?,
+        )),
+        LocationType::Circle => Ok(LocationReference::Circle(
+            CircleLocationReference::from_binary(&bytes)?,
+        )),
+        LocationType::Rectangle => Ok(LocationReference::Rectangle(
+            RectangleLocationReference::from_binary(&bytes)?,
+        )),
+        LocationType::Grid => Ok(LocationReference::Grid(
+            GridLocationReference::from_binary(&bytes)?,
+        )),
+        LocationType::Polygon => Ok(LocationReference::Polygon(
+            PolygonLocationReference::from_binary(&bytes)?,
+        )),
+        LocationType::ClosedLine => Ok(LocationReference::ClosedLine(
+            ClosedLineLocationReference::from_binary(&bytes)?,
         )),
         LocationType::Unknown => Err(OpenLrErr::UnknownLocationTypeError),
-        loc => Err(OpenLrErr::UnsupportedLocationTypeError(format!(
-            "{:?}",
-            loc
-        ))),
+    }
+}
+
+pub fn serialize_binary(locref: &LocationReference) -> Result<String, OpenLrErr> {
+    let bytes = match locref {
+        LocationReference::Line(line_loc_ref) => line_loc_ref.to_binary()?,
+        LocationReference::PointAlongLine(pal_loc_ref) => pal_loc_ref.to_binary()?,
+        lr => {
+            return Err(OpenLrErr::UnsupportedSerializationError(
+                lr.type_name().to_owned(),
+            ))
+        }
+    };
+
+    Ok(base64::encode(bytes))
+}
+
+pub fn deserialize_xml(xml: &str) -> Result<LocationReference, OpenLrErr> {
+    let doc = xml::parse(xml)?;
+    match xml::location_reference_node(&doc)?.tag_name().name() {
+        "LineLocationReference" => Ok(LocationReference::Line(
+            LineLocationReference::from_xml(xml)?,
+        )),
+        "PointLocationReference" => Ok(LocationReference::PointAlongLine(
+            PointAlongLineLocationReference::from_xml(xml)?,
+        )),
+        name => Err(OpenLrErr::UnsupportedLocationTypeError(name.to_owned())),
+    }
+}
+
+pub fn serialize_xml(locref: &LocationReference) -> Result<String, OpenLrErr> {
+    match locref {
+        LocationReference::Line(line_loc_ref) => line_loc_ref.to_xml(),
+        LocationReference::PointAlongLine(pal_loc_ref) => pal_loc_ref.to_xml(),
+        lr => Err(OpenLrErr::UnsupportedSerializationError(
+            lr.type_name().to_owned(),
+        )),
     }
 }
 
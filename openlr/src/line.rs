@@ -1,10 +1,14 @@
 use crate::binary_header::BinaryHeader;
-use crate::common::{calculate_offset, find_route_across_lrps, get_next_coordinate, int2deg, trim};
+use crate::common::{
+    calculate_offset, deg2int, find_route_across_lrps, get_next_coordinate, get_relative_coordinate,
+    int2deg, offset2int, trim,
+};
 use crate::decodable_reference::DecodableReference;
 use crate::decoding_parameters::DecodingParameters;
 use crate::deserializable_reference::DeserializableReference;
 use crate::edge::Edge;
 use crate::encodable_location::EncodableLocation;
+use crate::encoder::encode_path;
 use crate::encoding_parameters::EncodingParameters;
 use crate::errors::OpenLrErr;
 use crate::location::Location;
@@ -12,7 +16,10 @@ use crate::location_reference::LocationReference;
 use crate::location_reference_point::LocationReferencePoint;
 use crate::request_context::RequestContext;
 use crate::serializable_reference::SerializableReference;
+use crate::xml;
 use async_trait::async_trait;
+use geo::{Coord, LineString};
+use serde::ser::SerializeStruct;
 use serde::Serialize;
 
 //--------------------------------------------------------------------//
@@ -226,26 +233,180 @@ impl DeserializableReference for LineLocationReference {
     }
 
     fn from_xml(xml: &str) -> Result<Self::T, OpenLrErr> {
-        todo!()
+        let doc = xml::parse(xml)?;
+        let node = xml::location_reference_node(&doc)?;
+        if node.tag_name().name() != "LineLocationReference" {
+            return Err(OpenLrErr::XmlParseError(format!(
+                "Expected <LineLocationReference> but found <{}>",
+                node.tag_name().name()
+            )));
+        }
+
+        // Parse the LRPs, which are followed by the last LRP
+        let mut lrps = node
+            .children()
+            .filter(|n| n.tag_name().name() == "LocationReferencePoint")
+            .enumerate()
+            .map(|(i, n)| LocationReferencePoint::new_from_xml(n, i, false))
+            .collect::<Result<Vec<LocationReferencePoint>, OpenLrErr>>()?;
+        if lrps.is_empty() {
+            return Err(OpenLrErr::XmlParseError(
+                "A line location reference requires at least two LRPs".to_owned(),
+            ));
+        }
+        let index = lrps.len();
+        lrps.push(LocationReferencePoint::new_from_xml(
+            xml::child(node, "LastLocationReferencePoint")?,
+            index,
+            true,
+        )?);
+
+        // Offsets are optional, and are exact distances in meters
+        let (pos_offset, neg_offset) = match xml::child(node, "Offsets") {
+            Ok(offsets) => (
+                xml::child_offset(offsets, "PosOff")?,
+                xml::child_offset(offsets, "NegOff")?,
+            ),
+            Err(_) => (None, None),
+        };
+
+        Ok(LineLocationReference {
+            // version 3 line location with attributes
+            header: BinaryHeader::new(0x0b),
+            lrps,
+            pos_offset,
+            neg_offset,
+        })
     }
 }
 
 impl SerializableReference for LineLocationReference {
     fn to_binary(&self) -> Result<Vec<u8>, OpenLrErr> {
-        todo!()
+        let mut bytes = vec![self.header.to_u8()];
+
+        // The first LRP has absolute coordinates, the remaining ones are relative to their predecessor
+        for (i, lrp) in self.lrps.iter().enumerate() {
+            if i == 0 {
+                bytes.extend_from_slice(&deg2int(lrp.longitude));
+                bytes.extend_from_slice(&deg2int(lrp.latitude));
+            } else {
+                let prev = &self.lrps[i - 1];
+                bytes.extend_from_slice(&get_relative_coordinate(lrp.longitude, prev.longitude)?);
+                bytes.extend_from_slice(&get_relative_coordinate(lrp.latitude, prev.latitude)?);
+            }
+            bytes.extend(lrp.to_byte_array());
+        }
+
+        // Flag the presence of offsets in the last LRP's fourth attribute byte, and
+        // append the offset buckets relative to the DNP of the first / penultimate LRP
+        let last = bytes.len() - 1;
+        if let Some(offset) = self.pos_offset {
+            bytes[last] |= 0b01000000;
+            bytes.push(offset2int(offset, self.lrps[0].dnp.unwrap()));
+        }
+        if let Some(offset) = self.neg_offset {
+            bytes[last] |= 0b00100000;
+            bytes.push(offset2int(
+                offset,
+                self.lrps[self.lrps.len() - 2].dnp.unwrap(),
+            ));
+        }
+
+        Ok(bytes)
     }
     fn to_xml(&self) -> Result<String, OpenLrErr> {
-        todo!()
+        let mut body = self
+            .lrps
+            .iter()
+            .map(|lrp| lrp.to_xml())
+            .collect::<Vec<String>>()
+            .concat();
+
+        if self.pos_offset.is_some() || self.neg_offset.is_some() {
+            body.push_str("<Offsets>");
+            if let Some(offset) = self.pos_offset {
+                body.push_str(&format!("<PosOff>{}</PosOff>", xml::midpoint(offset)));
+            }
+            if let Some(offset) = self.neg_offset {
+                body.push_str(&format!("<NegOff>{}</NegOff>", xml::midpoint(offset)));
+            }
+            body.push_str("</Offsets>");
+        }
+
+        Ok(xml::to_document(&format!(
+            "<LineLocationReference>{}</LineLocationReference>",
+            body
+        )))
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Debug)]
 pub struct LineLocation {
     pub edges: Vec<Edge>,
     pub p_off: Option<(u32, u32)>,
     pub n_off: Option<(u32, u32)>,
 }
 
+impl LineLocation {
+    /// Returns the geometry of the location, which runs from the positive offset into
+    /// the first edge to the negative offset from the end of the last edge.  Where an
+    /// offset is a range, its lower bound is used.
+    pub fn get_geometry(&self) -> LineString {
+        let mut coords: Vec<Coord> = Vec::new();
+        for (i, edge) in self.edges.iter().enumerate() {
+            let from = match self.p_off {
+                Some((lb, _)) if i == 0 => lb,
+                _ => 0,
+            };
+            let to = match self.n_off {
+                Some((lb, _)) if i == self.edges.len() - 1 => {
+                    edge.get_line_length().saturating_sub(lb)
+                }
+                _ => edge.get_line_length(),
+            };
+
+            // consecutive edges share their end and start points
+            for c in edge.get_sub_line(from, to).0 {
+                if coords.last() != Some(&c) {
+                    coords.push(c);
+                }
+            }
+        }
+        LineString::new(coords)
+    }
+
+    /// Returns the length of the location in meters, i.e. the length of its edges
+    /// less the offsets
+    pub fn get_length(&self) -> u32 {
+        self.edges
+            .iter()
+            .map(|e| e.get_line_length())
+            .sum::<u32>()
+            .saturating_sub(self.p_off.map_or(0, |(lb, _)| lb))
+            .saturating_sub(self.n_off.map_or(0, |(lb, _)| lb))
+    }
+}
+
+impl Serialize for LineLocation {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let coords = self
+            .get_geometry()
+            .coords()
+            .map(|c| (c.x, c.y))
+            .collect::<Vec<(f64, f64)>>();
+        let mut state = serializer.serialize_struct("LineLocation", 5)?;
+        state.serialize_field("edges", &self.edges)?;
+        state.serialize_field("p_off", &self.p_off)?;
+        state.serialize_field("n_off", &self.n_off)?;
+        state.serialize_field("geom", &coords)?;
+        state.serialize_field("length", &self.get_length())?;
+        state.end()
+    }
+}
+
 #[async_trait]
 impl EncodableLocation for LineLocation {
     type Peer = LineLocationReference;
@@ -253,6 +414,28 @@ impl EncodableLocation for LineLocation {
         &self,
         context: &RequestContext<EncodingParameters>,
     ) -> Result<LocationReference, OpenLrErr> {
-        todo!()
+        let (lrps, pos_offset, neg_offset) = encode_path(
+            &self.edges,
+            self.p_off.map_or(0, |(lb, _)| lb),
+            self.n_off.map_or(0, |(lb, _)| lb),
+            context,
+        )
+        .await?;
+
+        Ok(LocationReference::Line(LineLocationReference {
+            // version 3 line location with attributes
+            header: BinaryHeader::new(0x0b),
+            lrps,
+            pos_offset: if pos_offset > 0 {
+                Some((pos_offset, pos_offset))
+            } else {
+                None
+            },
+            neg_offset: if neg_offset > 0 {
+                Some((neg_offset, neg_offset))
+            } else {
+                None
+            },
+        }))
     }
 }
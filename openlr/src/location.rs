@@ -1,10 +1,42 @@
 use serde::Serialize;
 
+use crate::circle::CircleLocation;
+use crate::closed_line::ClosedLineLocation;
+use crate::geo_coordinate::GeoCoordinateLocation;
+use crate::grid::GridLocation;
 use crate::line::LineLocation;
+use crate::point_along_line::PointAlongLineLocation;
+use crate::poi_with_access_point::PoiWithAccessPointLocation;
+use crate::polygon::PolygonLocation;
+use crate::rectangle::RectangleLocation;
 
 #[derive(Serialize, Debug)]
 pub enum Location {
     Line(LineLocation),
-    // TODO: Add support for more location types
+    PointAlongLine(PointAlongLineLocation),
+    PoiWithAccessPoint(PoiWithAccessPointLocation),
+    GeoCoordinate(GeoCoordinateLocation),
+    Circle(CircleLocation),
+    Rectangle(RectangleLocation),
+    Grid(GridLocation),
+    Polygon(PolygonLocation),
+    ClosedLine(ClosedLineLocation),
     Unknown,
 }
+
+impl Location {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Location::Line(_) => "Line",
+            Location::PointAlongLine(_) => "PointAlongLine",
+            Location::PoiWithAccessPoint(_) => "PoiWithAccessPoint",
+            Location::GeoCoordinate(_) => "GeoCoordinate",
+            Location::Circle(_) => "Circle",
+            Location::Rectangle(_) => "Rectangle",
+            Location::Grid(_) => "Grid",
+            Location::Polygon(_) => "Polygon",
+            Location::ClosedLine(_) => "ClosedLine",
+            Location::Unknown => "Unknown",
+        }
+    }
+}
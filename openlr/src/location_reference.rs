@@ -1,11 +1,53 @@
 use serde::Serialize;
 
+use crate::circle::CircleLocationReference;
+use crate::closed_line::ClosedLineLocationReference;
+use crate::geo_coordinate::GeoCoordinateLocationReference;
+use crate::grid::GridLocationReference;
 use crate::line::LineLocationReference;
+use crate::location_reference_point::LocationReferencePoint;
+use crate::point_along_line::PointAlongLineLocationReference;
+use crate::poi_with_access_point::PoiWithAccessPointLocationReference;
+use crate::polygon::PolygonLocationReference;
+use crate::rectangle::RectangleLocationReference;
 
 #[derive(Debug, Serialize)]
 pub enum LocationReference {
     Line(LineLocationReference),
-    // TODO: Add support for more location reference types
+    PointAlongLine(PointAlongLineLocationReference),
+    PoiWithAccessPoint(PoiWithAccessPointLocationReference),
+    GeoCoordinate(GeoCoordinateLocationReference),
+    Circle(CircleLocationReference),
+    Rectangle(RectangleLocationReference),
+    Grid(GridLocationReference),
+    Polygon(PolygonLocationReference),
+    ClosedLine(ClosedLineLocationReference),
 }
 
-impl LocationReference {}
+impl LocationReference {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            LocationReference::Line(_) => "Line",
+            LocationReference::PointAlongLine(_) => "PointAlongLine",
+            LocationReference::PoiWithAccessPoint(_) => "PoiWithAccessPoint",
+            LocationReference::GeoCoordinate(_) => "GeoCoordinate",
+            LocationReference::Circle(_) => "Circle",
+            LocationReference::Rectangle(_) => "Rectangle",
+            LocationReference::Grid(_) => "Grid",
+            LocationReference::Polygon(_) => "Polygon",
+            LocationReference::ClosedLine(_) => "ClosedLine",
+        }
+    }
+
+    /// Returns the LRPs of the location reference, if it is decoded by finding a path
+    /// between them
+    pub(crate) fn lrps(&self) -> &[LocationReferencePoint] {
+        match self {
+            LocationReference::Line(l) => &l.lrps,
+            LocationReference::PointAlongLine(pal) => &pal.lrps,
+            LocationReference::PoiWithAccessPoint(poi) => &poi.lrps,
+            LocationReference::ClosedLine(cl) => &cl.lrps,
+            _ => &[],
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use crate::candidate_edge::CandidateEdge;
 use crate::common;
-use crate::common::{distance_to_next_lrp, int2bearing};
+use crate::common::{bearing2int, distance_to_next_lrp, dnp2int, int2bearing};
 use crate::decoding_parameters::DecodingParameters;
 use crate::edge::Edge;
 use crate::errors::OpenLrErr;
 use crate::fow::FOW;
 use crate::frc::FRC;
 use crate::request_context::RequestContext;
+use crate::xml;
+use roxmltree::Node;
 use serde::Serialize;
 use std::cmp::Ordering;
 
@@ -61,6 +63,79 @@ impl LocationReferencePoint {
         }
     }
 
+    // build an LRP from a <LocationReferencePoint> or <LastLocationReferencePoint> element
+    // of the XML format.  Unlike the binary format, bearings and DNPs are exact values.
+    pub fn new_from_xml(node: Node, index: usize, is_last: bool) -> Result<Self, OpenLrErr> {
+        let coordinates = xml::child(node, "Coordinates")?;
+        let line_attributes = xml::child(node, "LineAttributes")?;
+        let bearing = xml::child_value::<f64>(line_attributes, "BEAR")?;
+        let (lowest_frc_to_next_point, dnp) = if !is_last {
+            let path_attributes = xml::child(node, "PathAttributes")?;
+            let dnp = xml::child_value::<u32>(path_attributes, "DNP")?;
+            (
+                Some(xml::child_frc(path_attributes, "LFRCNP")?),
+                Some((dnp, dnp)),
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(LocationReferencePoint {
+            longitude: xml::child_value(coordinates, "Longitude")?,
+            latitude: xml::child_value(coordinates, "Latitude")?,
+            fow: xml::child_fow(line_attributes, "FOW")?,
+            frc: xml::child_frc(line_attributes, "FRC")?,
+            lowest_frc_to_next_point,
+            bearing: (bearing, bearing),
+            bearing_sector: 0,
+            dnp,
+            index,
+            is_last,
+        })
+    }
+
+    // the inverse of new_from_xml
+    pub fn to_xml(&self) -> String {
+        let element = if self.is_last {
+            "LastLocationReferencePoint"
+        } else {
+            "LocationReferencePoint"
+        };
+        let path_attributes = match (self.lowest_frc_to_next_point, self.dnp) {
+            (Some(lfrcnp), Some(dnp)) if !self.is_last => format!(
+                "<PathAttributes><LFRCNP>{}</LFRCNP><DNP>{}</DNP></PathAttributes>",
+                lfrcnp.to_xml(),
+                xml::midpoint(dnp)
+            ),
+            _ => String::new(),
+        };
+        format!(
+            "<{element}><Coordinates><Longitude>{}</Longitude><Latitude>{}</Latitude></Coordinates>\
+             <LineAttributes><FRC>{}</FRC><FOW>{}</FOW><BEAR>{}</BEAR></LineAttributes>{}</{element}>",
+            self.longitude,
+            self.latitude,
+            self.frc.to_xml(),
+            self.fow.to_xml(),
+            (((self.bearing.0 + self.bearing.1) / 2.0).round() as u32) % 360,
+            path_attributes,
+        )
+    }
+
+    // the inverse of new_from_byte_array: build the attribute bytes of an LRP
+    // (two bytes for the last LRP, three otherwise)
+    pub fn to_byte_array(&self) -> Vec<u8> {
+        let attr1 = ((self.frc.to_usize() as u8) << 3) | self.fow.to_usize() as u8;
+        let bearing = bearing2int(self.bearing);
+        match (self.lowest_frc_to_next_point, self.dnp) {
+            (Some(lfrcnp), Some(dnp)) if !self.is_last => vec![
+                attr1,
+                ((lfrcnp.to_usize() as u8) << 5) | bearing,
+                dnp2int(dnp),
+            ],
+            _ => vec![attr1, bearing],
+        }
+    }
+
     fn score_candidate_edge(
         &self,
         candidate: &Edge,
@@ -76,11 +151,11 @@ impl LocationReferencePoint {
         let bearing_score = if self.bearing.0 == self.bearing.1 {
             // The bearing is a single value (i.e. derived from parsing XML). The
             // bearing component raw score is calculated by taking the absolute value of
-            // the difference between the LRP's and edge's bearings, normalized to 180
-            // degrees.
+            // the difference between the LRP's and edge's bearings, and penalizing each
+            // degree of difference by params.bearing_delta_penalty.
             let delta = f64::abs(self.bearing.0 - bearing);
             let delta = if delta <= 180.0 { delta } else { 360.0 - delta };
-            delta / 180.0
+            f64::min(1.0, delta * context.params.bearing_delta_penalty)
         } else {
             // The bearing is within a range, sp determine how many "wedges" separate
             // the real and expected bearings.  That delta is used as an index into the
@@ -284,4 +359,15 @@ mod tests {
         assert_eq!(lrp.dnp, None);
         assert_eq!(lrp.lowest_frc_to_next_point, None);
     }
+
+    #[test]
+    fn test_to_byte_array() {
+        let ba: &[u8] = &[0x31, 0xcf, 0xff];
+        let lrp = LocationReferencePoint::new_from_byte_array(1.999, 2.999, ba, 0, false);
+        assert_eq!(lrp.to_byte_array(), ba);
+
+        let ba: &[u8] = &[0x31, 0x0f];
+        let lrp = LocationReferencePoint::new_from_byte_array(1.999, 2.999, ba, 0, true);
+        assert_eq!(lrp.to_byte_array(), ba);
+    }
 }
@@ -1,10 +1,20 @@
 use crate::edge::Edge;
 use crate::errors::OpenLrErr;
+use crate::map_info::MapInfo;
+use crate::subgraph::Subgraph;
+use crate::turn_restriction::TurnRestriction;
 use ::async_trait::async_trait;
-use geo::Coord;
+use geo::{Coord, Polygon};
 
 #[async_trait]
 pub trait Map: Sync + Send {
+    /// Returns metadata about the map: its bounding box, vendor and version, optional
+    /// capabilities, and the semantics of its FOW and FRC values.  By default nothing is
+    /// known about the map, and it claims no optional capabilities.
+    async fn get_info(&self) -> Result<MapInfo, OpenLrErr> {
+        Ok(MapInfo::default())
+    }
+
     async fn get_nearby_edges(
         &self,
         points: Vec<Coord>,
@@ -18,4 +28,59 @@ pub trait Map: Sync + Send {
         id: i64,
         meta: String,
     ) -> Result<Vec<Edge>, OpenLrErr>;
+
+    /// Returns the sets of lines which follow each of the given lines (by id and metadata),
+    /// in the same order, as get_next_edges does for a single line.  Maps served over a
+    /// network can answer them in a single round trip, but by default the lines are simply
+    /// looked up one after the other.
+    async fn get_next_edges_many(
+        &self,
+        edges: &[(i64, String)],
+    ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+        let mut next_edges = Vec::with_capacity(edges.len());
+        for (id, meta) in edges {
+            next_edges.push(self.get_next_edges(*id, meta.clone()).await?);
+        }
+        Ok(next_edges)
+    }
+
+    /// Returns a set of lines which precede this line in the same direction. The set of lines
+    /// is equal to the set of incoming lines of the start node of this line. Only the encoder
+    /// needs this, to find valid nodes, so by default the map reports that it cannot.
+    async fn get_previous_edges(
+        &self,
+        _id: i64,
+        _meta: String,
+    ) -> Result<Vec<Edge>, OpenLrErr> {
+        Err(OpenLrErr::PreviousEdgeError(
+            "The map does not support previous edge searches".to_owned(),
+        ))
+    }
+
+    /// Returns the turn restrictions which begin on this line, so that paths never
+    /// follow all the lines of a restriction in succession.  Turn restrictions are
+    /// optional, so by default the map reports that there are none.
+    async fn get_turn_restrictions(
+        &self,
+        _id: i64,
+        _meta: String,
+    ) -> Result<Vec<TurnRestriction>, OpenLrErr> {
+        Ok(Vec::new())
+    }
+
+    /// Returns all lines whose geometry intersects the given area. Area searches are
+    /// optional, so by default the map reports that it does not support them.
+    async fn get_edges_in_area(&self, _area: Polygon) -> Result<Vec<Edge>, OpenLrErr> {
+        Err(OpenLrErr::AreaSearchError(
+            "Area searches are not supported by this map".to_owned(),
+        ))
+    }
+
+    /// Returns the subgraph of all lines whose geometry intersects the given area, so that
+    /// the decoder can search it locally instead of querying the map line by line.  Subgraphs
+    /// are optional, so by default the map returns None to report that it does not support
+    /// them.
+    async fn get_subgraph(&self, _area: Polygon) -> Result<Option<Subgraph>, OpenLrErr> {
+        Ok(None)
+    }
 }
@@ -0,0 +1,39 @@
+use geo::Rect;
+
+/// The optional features of a map, beyond the radius and next line searches which every
+/// map provides
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Whether the next lines of several lines are found with a single request
+    pub next_edges_batch: bool,
+    /// Whether the subgraph within an area can be fetched for a local search
+    pub subgraph_prefetch: bool,
+    /// Whether the map has turn restrictions
+    pub turn_restrictions: bool,
+    /// Whether the lines within an area can be searched for
+    pub area_search: bool,
+}
+
+/// How the FOW or FRC values of a map's lines relate to those of the OpenLR standard
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AttributeSemantics {
+    /// The values are assigned as the OpenLR standard defines them
+    #[default]
+    OpenLr,
+    /// The values are derived from another classification, described by the string
+    /// (e.g. "OSM highway tags")
+    Derived(String),
+}
+
+/// Metadata about a map: the area it covers, where it comes from, what it supports, and
+/// how its lines are classified
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MapInfo {
+    /// The bounding box of the map's lines in WGS84, if known
+    pub bounding_box: Option<Rect>,
+    pub vendor: String,
+    pub version: String,
+    pub capabilities: Capabilities,
+    pub fow_semantics: AttributeSemantics,
+    pub frc_semantics: AttributeSemantics,
+}
@@ -0,0 +1,103 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use super::{directed_edges, Direction};
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::fow::FOW;
+use crate::frc::FRC;
+use crate::in_memory_map::MapEdge;
+
+/// Loads the edges of a map from a CSV file with one road per line, in the
+/// format used by the test data: `id:"meta":fow:frc:flowdir:start_node:end_node:len:"wkt"`,
+/// where the geometry runs from the start node to the end node, and `flowdir`
+/// gives the direction of travel (see [`Direction::from_flowdir`]).
+pub fn load(path: &str) -> Result<Vec<MapEdge>, OpenLrErr> {
+    let file =
+        File::open(path).map_err(|e| OpenLrErr::MapLoadError(format!("{}: {}", path, e)))?;
+
+    let mut edges = Vec::new();
+    for (n, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| OpenLrErr::MapLoadError(format!("{}: {}", path, e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let invalid = |field: &str| {
+            OpenLrErr::InvalidMapFeature(
+                path.to_owned(),
+                n + 1,
+                format!("invalid {} in '{}'", field, line),
+            )
+        };
+
+        let v = line.splitn(9, ':').collect::<Vec<&str>>();
+        if v.len() != 9 {
+            return Err(invalid("number of fields"));
+        }
+        let class = |i: usize, field: &str| {
+            v[i].parse::<u8>()
+                .ok()
+                .filter(|c| *c <= 7)
+                .ok_or_else(|| invalid(field))
+        };
+        let integer = |i: usize, field: &str| v[i].parse::<i64>().map_err(|_| invalid(field));
+
+        let direction = integer(4, "flowdir")
+            .ok()
+            .and_then(Direction::from_flowdir)
+            .ok_or_else(|| invalid("flowdir"))?;
+        let edge = Edge::new_from_wkt(
+            integer(0, "id")?,
+            v[1].trim_matches('\"').to_owned(),
+            FOW::from_u8(class(2, "fow")?),
+            FRC::from_u8(class(3, "frc")?),
+            v[7].parse::<u32>().map_err(|_| invalid("len"))?,
+            v[8].trim_matches('\"'),
+        )
+        .map_err(|_| invalid("geometry"))?;
+        edges.extend(directed_edges(
+            edge,
+            integer(5, "start node")?,
+            integer(6, "end node")?,
+            direction,
+        ));
+    }
+    Ok(edges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("openlr_csv_test_{}.csv", std::process::id()));
+        std::fs::write(
+            &path,
+            "1:\"a\":3:5:1:10:11:111:\"LINESTRING(0 0,0.001 0)\"\n\
+             2:\"b\":3:5:2:11:12:111:\"LINESTRING(0.001 0,0.002 0)\"\n\
+             3:\"c\":3:5:3:12:13:111:\"LINESTRING(0.002 0,0.003 0)\"\n\
+             4:\"d\":3:5:0:13:14:111:\"LINESTRING(0.003 0,0.004 0)\"\n",
+        )
+        .unwrap();
+        let edges = load(path.to_str().unwrap()).unwrap();
+        let mut ids = edges.iter().map(|me| me.edge.id).collect::<Vec<i64>>();
+        ids.sort();
+        assert_eq!(ids, vec![-2, -1, 1, 3]);
+        let backward = edges.iter().find(|me| me.edge.id == -2).unwrap();
+        assert_eq!((backward.start_node, backward.end_node), (12, 11));
+        assert_eq!(backward.edge.meta, "b");
+
+        std::fs::write(
+            &path,
+            "1:\"a\":3:5:3:10:11:111:\"LINESTRING(0 0,0.001 0)\"\n\
+             2:\"b\":3:5:7:11:12:111:\"LINESTRING(0.001 0,0.002 0)\"\n",
+        )
+        .unwrap();
+        match load(path.to_str().unwrap()) {
+            Err(OpenLrErr::InvalidMapFeature(_, 2, reason)) => assert!(reason.contains("flowdir")),
+            r => panic!("Unexpected result: {:?}", r.map(|edges| edges.len())),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,401 @@
+use std::fs::File;
+use std::io::{self, BufReader, ErrorKind, Read};
+
+use geo::Coord;
+
+use super::{edges_from_feature, AttributeMapping, AttributeValue};
+use crate::errors::OpenLrErr;
+use crate::in_memory_map::MapEdge;
+
+// The size of a node of the packed Hilbert R-tree which may follow the header
+const NODE_ITEM_LEN: u64 = 40;
+
+// FlatGeobuf geometry types
+const LINE_STRING: u8 = 2;
+const MULTI_LINE_STRING: u8 = 5;
+
+/// Loads the edges of a map from a FlatGeobuf file, which is read one feature
+/// at a time.  The geometries must be in WGS84.
+pub fn load(path: &str, mapping: &AttributeMapping) -> Result<Vec<MapEdge>, OpenLrErr> {
+    let load_error = |e: io::Error| OpenLrErr::MapLoadError(format!("{}: {}", path, e));
+    let mut reader = BufReader::new(File::open(path).map_err(load_error)?);
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic).map_err(load_error)?;
+    if &magic[0..3] != b"fgb" || magic[3] != 3 || &magic[4..7] != b"fgb" {
+        return Err(OpenLrErr::MapLoadError(format!(
+            "{}: not a FlatGeobuf (version 3) file",
+            path
+        )));
+    }
+
+    let header_buf = read_size_prefixed(&mut reader)
+        .map_err(load_error)?
+        .ok_or_else(|| OpenLrErr::MapLoadError(format!("{}: missing header", path)))?;
+    let header = Header::read(&header_buf)
+        .map_err(|e| OpenLrErr::MapLoadError(format!("{}: invalid header: {}", path, e)))?;
+
+    // the spatial index isn't needed, as the whole map is loaded
+    let index_len = packed_rtree_len(header.features_count, header.index_node_size);
+    let skipped =
+        io::copy(&mut (&mut reader).take(index_len), &mut io::sink()).map_err(load_error)?;
+    if skipped != index_len {
+        return Err(OpenLrErr::MapLoadError(format!(
+            "{}: truncated index",
+            path
+        )));
+    }
+
+    let mut edges = Vec::new();
+    let mut number = 0;
+    while let Some(buf) = read_size_prefixed(&mut reader).map_err(load_error)? {
+        number += 1;
+        let (coords, properties) = read_feature(&buf, &header)
+            .map_err(|reason| OpenLrErr::InvalidMapFeature(path.to_owned(), number, reason))?;
+        edges.extend(edges_from_feature(mapping, path, number, coords, |name| {
+            properties
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.clone())
+        })?);
+    }
+    Ok(edges)
+}
+
+// Reads a flatbuffer preceded by its length, or None at the end of the file
+fn read_size_prefixed(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+    let mut buf = vec![0u8; u32::from_le_bytes(len) as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(Some(buf))
+}
+
+// The number of bytes in a packed R-tree over a number of features
+fn packed_rtree_len(features_count: u64, node_size: u16) -> u64 {
+    if features_count == 0 || node_size == 0 {
+        return 0;
+    }
+    let node_size = u64::max(node_size as u64, 2);
+    // the levels of the tree, from the leaves up to the root, which is always
+    // above the leaves even if there is only one
+    let mut n = features_count;
+    let mut nodes = n;
+    loop {
+        n = n.div_ceil(node_size);
+        nodes += n;
+        if n == 1 {
+            break nodes * NODE_ITEM_LEN;
+        }
+    }
+}
+
+// The named values of the properties of a feature
+type Properties = Vec<(String, AttributeValue)>;
+
+struct Column {
+    name: String,
+    column_type: u8,
+}
+
+struct Header {
+    geometry_type: u8,
+    columns: Vec<Column>,
+    features_count: u64,
+    index_node_size: u16,
+}
+
+impl Header {
+    fn read(buf: &[u8]) -> Result<Self, String> {
+        let table = Table::root(buf)?;
+        Ok(Header {
+            geometry_type: table.get_u8(2)?,
+            columns: read_columns(&table, 7)?,
+            features_count: table.get_u64(8)?,
+            index_node_size: table.get_u16(9, 16)?,
+        })
+    }
+}
+
+fn read_columns(table: &Table, slot: usize) -> Result<Vec<Column>, String> {
+    table
+        .get_tables(slot)?
+        .iter()
+        .map(|column| {
+            Ok(Column {
+                name: column.get_str(0)?.ok_or("unnamed column")?.to_owned(),
+                column_type: column.get_u8(1)?,
+            })
+        })
+        .collect::<Result<Vec<Column>, String>>()
+}
+
+fn read_feature(buf: &[u8], header: &Header) -> Result<(Vec<Coord>, Properties), String> {
+    let feature = Table::root(buf)?;
+    let geometry = feature.get_table(0)?.ok_or("missing geometry")?;
+    let coords = read_line(&geometry, header.geometry_type)?;
+
+    // a feature may carry its own schema, overriding that of the header
+    let own_columns = read_columns(&feature, 2)?;
+    let columns = match own_columns.is_empty() {
+        true => &header.columns,
+        false => &own_columns,
+    };
+    let properties = match feature.get_vector(1, 1)? {
+        Some((start, len)) => read_properties(&buf[start..start + len], columns)?,
+        None => vec![],
+    };
+    Ok((coords, properties))
+}
+
+// The coordinates of a LineString, or of a MultiLineString holding a single line
+fn read_line(geometry: &Table, geometry_type: u8) -> Result<Vec<Coord>, String> {
+    // the type of each geometry is only given when the header leaves it unknown
+    let geometry_type = match geometry_type {
+        0 => geometry.get_u8(6)?,
+        t => t,
+    };
+    match geometry_type {
+        LINE_STRING => {}
+        MULTI_LINE_STRING => {
+            let parts = geometry.get_tables(7)?;
+            match parts.len() {
+                0 => {}
+                1 => return read_line(&parts[0], LINE_STRING),
+                _ => return Err("the MultiLineString has more than one line".to_owned()),
+            }
+            if geometry.get_vector(0, 4)?.map_or(0, |(_, len)| len) > 1 {
+                return Err("the MultiLineString has more than one line".to_owned());
+            }
+        }
+        t => return Err(format!("unsupported geometry type {}", t)),
+    }
+    let (start, len) = geometry.get_vector(1, 8)?.ok_or("missing coordinates")?;
+    Ok(geometry.buf[start..start + len * 8]
+        .chunks_exact(16)
+        .map(|xy| Coord {
+            x: f64::from_le_bytes(xy[0..8].try_into().expect("8 bytes")),
+            y: f64::from_le_bytes(xy[8..16].try_into().expect("8 bytes")),
+        })
+        .collect::<Vec<Coord>>())
+}
+
+// Properties are a sequence of column indices, each followed by its value
+fn read_properties(mut buf: &[u8], columns: &[Column]) -> Result<Properties, String> {
+    fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8], String> {
+        if buf.len() < n {
+            return Err("truncated properties".to_owned());
+        }
+        let (value, rest) = buf.split_at(n);
+        *buf = rest;
+        Ok(value)
+    }
+    fn bytes<const N: usize>(buf: &mut &[u8]) -> Result<[u8; N], String> {
+        Ok(take(buf, N)?.try_into().expect("slice of N bytes"))
+    }
+
+    let mut properties = Vec::new();
+    while !buf.is_empty() {
+        let index = u16::from_le_bytes(bytes(&mut buf)?) as usize;
+        let column = columns
+            .get(index)
+            .ok_or_else(|| format!("invalid column index {}", index))?;
+        let value = match column.column_type {
+            0 => AttributeValue::Integer(i8::from_le_bytes(bytes(&mut buf)?) as i64),
+            1 | 2 => AttributeValue::Integer(u8::from_le_bytes(bytes(&mut buf)?) as i64),
+            3 => AttributeValue::Integer(i16::from_le_bytes(bytes(&mut buf)?) as i64),
+            4 => AttributeValue::Integer(u16::from_le_bytes(bytes(&mut buf)?) as i64),
+            5 => AttributeValue::Integer(i32::from_le_bytes(bytes(&mut buf)?) as i64),
+            6 => AttributeValue::Integer(u32::from_le_bytes(bytes(&mut buf)?) as i64),
+            7 => AttributeValue::Integer(i64::from_le_bytes(bytes(&mut buf)?)),
+            8 => {
+                let v = u64::from_le_bytes(bytes(&mut buf)?);
+                match i64::try_from(v) {
+                    Ok(i) => AttributeValue::Integer(i),
+                    Err(_) => AttributeValue::Real(v as f64),
+                }
+            }
+            9 => AttributeValue::Real(f32::from_le_bytes(bytes(&mut buf)?) as f64),
+            10 => AttributeValue::Real(f64::from_le_bytes(bytes(&mut buf)?)),
+            11..=14 => {
+                let len = u32::from_le_bytes(bytes(&mut buf)?) as usize;
+                AttributeValue::Text(String::from_utf8_lossy(take(&mut buf, len)?).into_owned())
+            }
+            t => return Err(format!("unsupported column type {}", t)),
+        };
+        properties.push((column.name.clone(), value));
+    }
+    Ok(properties)
+}
+
+// A minimal, bounds checked reader of flatbuffer tables
+struct Table<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Table<'a> {
+    fn root(buf: &'a [u8]) -> Result<Self, String> {
+        Ok(Table {
+            buf,
+            pos: read_u32(buf, 0)? as usize,
+        })
+    }
+
+    // The position of a field, if present
+    fn field(&self, slot: usize) -> Result<Option<usize>, String> {
+        let vtable = self.pos as i64 - read_u32(self.buf, self.pos)? as i32 as i64;
+        let vtable = usize::try_from(vtable).map_err(|_| "invalid vtable offset")?;
+        let vtable_len = read_u16(self.buf, vtable)? as usize;
+        let entry = 4 + 2 * slot;
+        if entry + 2 > vtable_len {
+            return Ok(None);
+        }
+        Ok(match read_u16(self.buf, vtable + entry)? {
+            0 => None,
+            offset => Some(self.pos + offset as usize),
+        })
+    }
+
+    // The position of the target of an offset field, if present
+    fn indirect(&self, slot: usize) -> Result<Option<usize>, String> {
+        self.field(slot)?
+            .map(|pos| Ok(pos + read_u32(self.buf, pos)? as usize))
+            .transpose()
+    }
+
+    fn get_u8(&self, slot: usize) -> Result<u8, String> {
+        self.field(slot)?
+            .map_or(Ok(0), |pos| bytes(self.buf, pos).map(|b: [u8; 1]| b[0]))
+    }
+
+    fn get_u16(&self, slot: usize, default: u16) -> Result<u16, String> {
+        self.field(slot)?
+            .map_or(Ok(default), |pos| read_u16(self.buf, pos))
+    }
+
+    fn get_u64(&self, slot: usize) -> Result<u64, String> {
+        self.field(slot)?
+            .map_or(Ok(0), |pos| bytes(self.buf, pos).map(u64::from_le_bytes))
+    }
+
+    fn get_table(&self, slot: usize) -> Result<Option<Table<'a>>, String> {
+        Ok(self.indirect(slot)?.map(|pos| Table { buf: self.buf, pos }))
+    }
+
+    // The start and length of a vector of scalars of the given size
+    fn get_vector(&self, slot: usize, size: usize) -> Result<Option<(usize, usize)>, String> {
+        match self.indirect(slot)? {
+            Some(pos) => {
+                let len = read_u32(self.buf, pos)? as usize;
+                if self.buf.len() < pos + 4 + len * size {
+                    return Err("truncated vector".to_owned());
+                }
+                Ok(Some((pos + 4, len)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn get_str(&self, slot: usize) -> Result<Option<&'a str>, String> {
+        match self.get_vector(slot, 1)? {
+            Some((start, len)) => std::str::from_utf8(&self.buf[start..start + len])
+                .map(Some)
+                .map_err(|e| e.to_string()),
+            None => Ok(None),
+        }
+    }
+
+    fn get_tables(&self, slot: usize) -> Result<Vec<Table<'a>>, String> {
+        match self.get_vector(slot, 4)? {
+            Some((start, len)) => (0..len)
+                .map(|i| {
+                    let pos = start + 4 * i;
+                    Ok(Table {
+                        buf: self.buf,
+                        pos: pos + read_u32(self.buf, pos)? as usize,
+                    })
+                })
+                .collect::<Result<Vec<Table>, String>>(),
+            None => Ok(vec![]),
+        }
+    }
+}
+
+fn bytes<const N: usize>(buf: &[u8], pos: usize) -> Result<[u8; N], String> {
+    buf.get(pos..pos + N)
+        .map(|b| b.try_into().expect("slice of N bytes"))
+        .ok_or_else(|| "truncated flatbuffer".to_owned())
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Result<u16, String> {
+    bytes(buf, pos).map(u16::from_le_bytes)
+}
+
+fn read_u32(buf: &[u8], pos: usize) -> Result<u32, String> {
+    bytes(buf, pos).map(u32::from_le_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_packed_rtree_len() {
+        assert_eq!(packed_rtree_len(0, 16), 0);
+        assert_eq!(packed_rtree_len(100, 0), 0);
+        assert_eq!(packed_rtree_len(1, 16), 2 * NODE_ITEM_LEN);
+        // 100 leaves, 7 nodes above them and the root
+        assert_eq!(packed_rtree_len(100, 16), 108 * NODE_ITEM_LEN);
+    }
+
+    #[test]
+    fn test_read_properties() {
+        let columns = vec![
+            Column {
+                name: "id".to_owned(),
+                column_type: 7,
+            },
+            Column {
+                name: "meta".to_owned(),
+                column_type: 11,
+            },
+            Column {
+                name: "len".to_owned(),
+                column_type: 10,
+            },
+            Column {
+                name: "count".to_owned(),
+                column_type: 8,
+            },
+        ];
+        let mut buf = vec![];
+        buf.extend(1u16.to_le_bytes());
+        buf.extend(2u32.to_le_bytes());
+        buf.extend(b"ab");
+        buf.extend(0u16.to_le_bytes());
+        buf.extend((-42i64).to_le_bytes());
+        assert_eq!(
+            read_properties(&buf, &columns).unwrap(),
+            vec![
+                ("meta".to_owned(), AttributeValue::Text("ab".to_owned())),
+                ("id".to_owned(), AttributeValue::Integer(-42)),
+            ]
+        );
+        assert!(read_properties(&buf[..buf.len() - 1], &columns).is_err());
+        assert!(read_properties(&4u16.to_le_bytes(), &columns).is_err());
+
+        // unsigned values beyond the range of i64 are kept as reals
+        let mut buf = vec![];
+        buf.extend(3u16.to_le_bytes());
+        buf.extend((1u64 << 63).to_le_bytes());
+        assert_eq!(
+            read_properties(&buf, &columns).unwrap(),
+            vec![("count".to_owned(), AttributeValue::Real(2f64.powi(63)))]
+        );
+    }
+}
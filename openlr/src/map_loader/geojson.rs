@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::BufReader;
+
+use geo::Coord;
+use serde_json::{Map as JsonMap, Value};
+
+use super::{edges_from_feature, AttributeMapping, AttributeValue};
+use crate::errors::OpenLrErr;
+use crate::in_memory_map::MapEdge;
+
+/// Loads the edges of a map from a GeoJSON FeatureCollection, whose features
+/// carry the mapped attributes in their properties
+pub fn load(path: &str, mapping: &AttributeMapping) -> Result<Vec<MapEdge>, OpenLrErr> {
+    let file = File::open(path).map_err(|e| OpenLrErr::MapLoadError(format!("{}: {}", path, e)))?;
+    // serde_json reports the line and column of any syntax error
+    let collection: Value = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| OpenLrErr::MapLoadError(format!("{}: {}", path, e)))?;
+    from_value(&collection, path, mapping)
+}
+
+/// Builds the edges of a map from a parsed GeoJSON FeatureCollection.  `source`
+/// names the collection in errors.
+pub fn from_value(
+    collection: &Value,
+    source: &str,
+    mapping: &AttributeMapping,
+) -> Result<Vec<MapEdge>, OpenLrErr> {
+    let features = match (
+        collection["type"].as_str(),
+        collection["features"].as_array(),
+    ) {
+        (Some("FeatureCollection"), Some(features)) => features,
+        _ => {
+            return Err(OpenLrErr::MapLoadError(format!(
+                "{}: not a GeoJSON FeatureCollection",
+                source
+            )))
+        }
+    };
+
+    let no_properties = JsonMap::new();
+    let mut edges = Vec::with_capacity(features.len());
+    for (i, feature) in features.iter().enumerate() {
+        let coords = line_coords(&feature["geometry"])
+            .map_err(|reason| OpenLrErr::InvalidMapFeature(source.to_owned(), i + 1, reason))?;
+        let properties = feature["properties"].as_object().unwrap_or(&no_properties);
+        edges.extend(edges_from_feature(
+            mapping,
+            source,
+            i + 1,
+            coords,
+            |name| properties.get(name).map(attribute_value),
+        )?);
+    }
+    Ok(edges)
+}
+
+fn attribute_value(value: &Value) -> AttributeValue {
+    match value {
+        Value::Null => AttributeValue::Null,
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => AttributeValue::Integer(i),
+            None => AttributeValue::Real(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => AttributeValue::Text(s.clone()),
+        v => AttributeValue::Text(v.to_string()),
+    }
+}
+
+// The coordinates of a LineString, or of a MultiLineString holding a single line
+fn line_coords(geometry: &Value) -> Result<Vec<Coord>, String> {
+    let coordinates = &geometry["coordinates"];
+    let line = match geometry["type"].as_str() {
+        Some("LineString") => coordinates,
+        Some("MultiLineString") => match coordinates.as_array() {
+            Some(lines) if lines.len() == 1 => &lines[0],
+            _ => return Err("the MultiLineString has more than one line".to_owned()),
+        },
+        Some(t) => return Err(format!("unsupported geometry type {}", t)),
+        None => return Err("missing geometry".to_owned()),
+    };
+    line.as_array()
+        .ok_or_else(|| "invalid coordinates".to_owned())?
+        .iter()
+        .map(
+            |position| match (position[0].as_f64(), position[1].as_f64()) {
+                (Some(x), Some(y)) => Ok(Coord { x, y }),
+                _ => Err(format!("invalid position {}", position)),
+            },
+        )
+        .collect::<Result<Vec<Coord>, String>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_value() {
+        let collection = json!({
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "geometry": {"type": "LineString", "coordinates": [[0.0, 0.0], [0.001, 0.0]]},
+                    "properties": {"id": 1, "fow": 3, "frc": 5, "len": 111,
+                                   "from_int": 10, "to_int": 11, "flowdir": 1}
+                },
+                {
+                    "type": "Feature",
+                    "geometry": {"type": "MultiLineString", "coordinates": [[[0.001, 0.0], [0.002, 0.0]]]},
+                    "properties": {"id": 2, "meta": "m", "fow": 3, "frc": 5, "len": 111.2,
+                                   "from_int": 11, "to_int": 12, "flowdir": "3"}
+                }
+            ]
+        });
+        let edges = from_value(&collection, "roads.geojson", &AttributeMapping::default()).unwrap();
+        let mut ids = edges.iter().map(|me| me.edge.id).collect::<Vec<i64>>();
+        ids.sort();
+        assert_eq!(ids, vec![-1, 1, 2]);
+        assert_eq!(edges[2].edge.meta, "m");
+
+        let point = json!({
+            "type": "FeatureCollection",
+            "features": [{"type": "Feature", "geometry": {"type": "Point", "coordinates": [0.0, 0.0]},
+                          "properties": {}}]
+        });
+        match from_value(&point, "roads.geojson", &AttributeMapping::default()) {
+            Err(OpenLrErr::InvalidMapFeature(_, 1, reason)) => assert!(reason.contains("Point")),
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        assert!(from_value(
+            &json!({"type": "Feature"}),
+            "",
+            &AttributeMapping::default()
+        )
+        .is_err());
+    }
+}
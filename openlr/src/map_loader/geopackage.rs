@@ -0,0 +1,249 @@
+use geo::Coord;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+
+use super::{edges_from_feature, AttributeMapping, AttributeValue};
+use crate::errors::OpenLrErr;
+use crate::in_memory_map::MapEdge;
+
+/// Loads the edges of a map from a feature table of a GeoPackage, or from its
+/// first feature table (by name) if none is given.  The table's geometries must
+/// be in WGS84 (EPSG:4326).
+pub fn load(
+    path: &str,
+    table: Option<&str>,
+    mapping: &AttributeMapping,
+) -> Result<Vec<MapEdge>, OpenLrErr> {
+    let load_error = |e: rusqlite::Error| OpenLrErr::MapLoadError(format!("{}: {}", path, e));
+    let conn =
+        Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(load_error)?;
+
+    let table = match table {
+        Some(table) => table.to_owned(),
+        None => conn
+            .query_row(
+                "SELECT table_name FROM gpkg_contents WHERE data_type = 'features' \
+                 ORDER BY table_name LIMIT 1",
+                [],
+                |row| row.get::<_, String>(0),
+            )
+            .optional()
+            .map_err(load_error)?
+            .ok_or_else(|| OpenLrErr::MapLoadError(format!("{}: no feature table found", path)))?,
+    };
+    let (geometry_column, srs_id) = conn
+        .query_row(
+            "SELECT column_name, srs_id FROM gpkg_geometry_columns WHERE table_name = ?1",
+            [&table],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .optional()
+        .map_err(load_error)?
+        .ok_or_else(|| {
+            OpenLrErr::MapLoadError(format!("{}: {} is not a feature table", path, table))
+        })?;
+    let srs = conn
+        .query_row(
+            "SELECT organization, organization_coordsys_id FROM gpkg_spatial_ref_sys \
+             WHERE srs_id = ?1",
+            [srs_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)),
+        )
+        .optional()
+        .map_err(load_error)?;
+    match srs {
+        Some((organization, 4326)) if organization.eq_ignore_ascii_case("EPSG") => {}
+        _ => {
+            return Err(OpenLrErr::MapLoadError(format!(
+                "{}: the geometries of {} are not in WGS84 (srs_id {})",
+                path, table, srs_id
+            )))
+        }
+    }
+
+    // select the geometry, followed by each of the mapped attributes
+    let mut columns = vec![
+        Some(&mapping.id),
+        mapping.meta.as_ref(),
+        Some(&mapping.fow),
+        Some(&mapping.frc),
+        mapping.length.as_ref(),
+        Some(&mapping.start_node),
+        Some(&mapping.end_node),
+        mapping.direction.as_ref(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<&String>>();
+    columns.sort();
+    columns.dedup();
+    let sql = format!(
+        "SELECT {} FROM {}",
+        std::iter::once(&geometry_column)
+            .chain(columns.iter().copied())
+            .map(|c| quote_identifier(c))
+            .collect::<Vec<String>>()
+            .join(", "),
+        quote_identifier(&table)
+    );
+
+    let mut statement = conn.prepare(&sql).map_err(load_error)?;
+    let mut rows = statement.query([]).map_err(load_error)?;
+    let mut edges = Vec::new();
+    let mut number = 0;
+    while let Some(row) = rows.next().map_err(load_error)? {
+        number += 1;
+        let invalid =
+            |reason: String| OpenLrErr::InvalidMapFeature(path.to_owned(), number, reason);
+        let coords = match row.get_ref(0).map_err(load_error)? {
+            ValueRef::Blob(blob) => read_geometry(blob).map_err(invalid)?,
+            _ => return Err(invalid("missing geometry".to_owned())),
+        };
+        let values = (1..=columns.len())
+            .map(|i| row.get_ref(i).map(attribute_value))
+            .collect::<Result<Vec<AttributeValue>, rusqlite::Error>>()
+            .map_err(load_error)?;
+        edges.extend(edges_from_feature(mapping, path, number, coords, |name| {
+            columns
+                .iter()
+                .position(|c| *c == name)
+                .map(|i| values[i].clone())
+        })?);
+    }
+    Ok(edges)
+}
+
+fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn attribute_value(value: ValueRef) -> AttributeValue {
+    match value {
+        ValueRef::Null => AttributeValue::Null,
+        ValueRef::Integer(i) => AttributeValue::Integer(i),
+        ValueRef::Real(f) => AttributeValue::Real(f),
+        ValueRef::Text(t) | ValueRef::Blob(t) => {
+            AttributeValue::Text(String::from_utf8_lossy(t).into_owned())
+        }
+    }
+}
+
+// Reads a GeoPackage geometry blob: a header, an optional envelope, and the
+// geometry itself as WKB
+fn read_geometry(blob: &[u8]) -> Result<Vec<Coord>, String> {
+    if blob.len() < 8 || &blob[0..2] != b"GP" {
+        return Err("invalid GeoPackage geometry".to_owned());
+    }
+    let flags = blob[3];
+    if flags & 0x20 != 0 {
+        return Err("unsupported extended GeoPackage geometry".to_owned());
+    }
+    if flags & 0x10 != 0 {
+        return Err("empty geometry".to_owned());
+    }
+    let envelope_len = match (flags >> 1) & 0x07 {
+        0 => 0,
+        1 => 32,
+        2 | 3 => 48,
+        4 => 64,
+        e => return Err(format!("invalid envelope contents indicator {}", e)),
+    };
+    let mut wkb = Wkb {
+        data: blob.get(8 + envelope_len..).ok_or("truncated geometry")?,
+        pos: 0,
+    };
+    wkb.read_line()
+}
+
+struct Wkb<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Wkb<'a> {
+    fn read_bytes<const N: usize>(&mut self) -> Result<[u8; N], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + N)
+            .ok_or("truncated WKB geometry")?;
+        self.pos += N;
+        Ok(bytes.try_into().expect("slice of N bytes"))
+    }
+
+    fn read_u32(&mut self, little_endian: bool) -> Result<u32, String> {
+        let bytes = self.read_bytes::<4>()?;
+        Ok(match little_endian {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    }
+
+    fn read_f64(&mut self, little_endian: bool) -> Result<f64, String> {
+        let bytes = self.read_bytes::<8>()?;
+        Ok(match little_endian {
+            true => f64::from_le_bytes(bytes),
+            false => f64::from_be_bytes(bytes),
+        })
+    }
+
+    // Reads a LineString, or a MultiLineString holding a single line, ignoring any
+    // Z and M coordinates
+    fn read_line(&mut self) -> Result<Vec<Coord>, String> {
+        let little_endian = self.read_bytes::<1>()?[0] == 1;
+        let geometry_type = self.read_u32(little_endian)?;
+
+        // ISO WKB adds 1000 (Z), 2000 (M) or 3000 (ZM) to the type, while extended
+        // WKB sets its high bits instead
+        let iso_dimensions = (geometry_type & 0x0fff_ffff) / 1000;
+        let has_z = geometry_type & 0x8000_0000 != 0 || iso_dimensions & 1 != 0;
+        let has_m = geometry_type & 0x4000_0000 != 0 || iso_dimensions & 2 != 0;
+        let dimensions = 2 + has_z as usize + has_m as usize;
+
+        match (geometry_type & 0x0fff_ffff) % 1000 {
+            2 => {
+                let n = self.read_u32(little_endian)? as usize;
+                let mut coords = Vec::with_capacity(n.min(self.data.len() / 16));
+                for _ in 0..n {
+                    let x = self.read_f64(little_endian)?;
+                    let y = self.read_f64(little_endian)?;
+                    for _ in 2..dimensions {
+                        self.read_f64(little_endian)?;
+                    }
+                    coords.push(Coord { x, y });
+                }
+                Ok(coords)
+            }
+            5 => match self.read_u32(little_endian)? {
+                1 => self.read_line(),
+                _ => Err("the MultiLineString has more than one line".to_owned()),
+            },
+            t => Err(format!("unsupported WKB geometry type {}", t)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_geometry() {
+        // little endian header with an XY envelope, and a big endian LineString Z
+        let mut blob = vec![b'G', b'P', 0, 0x03];
+        blob.extend(4326i32.to_le_bytes());
+        blob.extend([0u8; 32]);
+        blob.push(0);
+        blob.extend(1002u32.to_be_bytes());
+        blob.extend(2u32.to_be_bytes());
+        for v in [1.0f64, 2.0, 100.0, 3.0, 4.0, 100.0] {
+            blob.extend(v.to_be_bytes());
+        }
+        assert_eq!(
+            read_geometry(&blob).unwrap(),
+            vec![Coord { x: 1.0, y: 2.0 }, Coord { x: 3.0, y: 4.0 }]
+        );
+
+        assert!(read_geometry(&blob[..blob.len() - 1]).is_err());
+        assert!(read_geometry(b"GP").is_err());
+    }
+}
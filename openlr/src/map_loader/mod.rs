@@ -0,0 +1,361 @@
+//! Loaders which read road networks from GeoJSON, GeoPackage and FlatGeobuf
+//! files, producing the edges of an [`InMemoryMap`](crate::in_memory_map::InMemoryMap).
+//!
+//! Each feature of a file is a road, with a LineString (or single-part
+//! MultiLineString) geometry in WGS84 longitude/latitude, digitized from its
+//! start node to its end node.  The attributes of the feature are mapped onto
+//! the edge by an [`AttributeMapping`], whose defaults match the columns of the
+//! test data (`id`, `meta`, `fow`, `frc`, `len`, `from_int`, `to_int` and
+//! `flowdir`).
+//!
+//! A malformed feature is reported as an [`OpenLrErr::InvalidMapFeature`] giving
+//! the file and the number of the feature (counting from 1), and any other
+//! problem reading the file as an [`OpenLrErr::MapLoadError`].
+
+pub mod csv;
+pub mod flatgeobuf;
+pub mod geojson;
+pub mod geopackage;
+pub mod osm;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use geo::{Coord, HaversineLength, LineString};
+
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::fow::FOW;
+use crate::frc::FRC;
+use crate::in_memory_map::MapEdge;
+
+/// The directions in which a road may be travelled, relative to the direction
+/// in which its geometry is digitized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Open to traffic in both directions
+    Both,
+    /// Open to traffic from the first to the last point of the geometry only
+    Forward,
+    /// Open to traffic from the last to the first point of the geometry only
+    Backward,
+    /// Closed to traffic
+    Closed,
+}
+
+impl Direction {
+    /// Returns the direction denoted by a `flowdir` value of the test data: 0
+    /// for closed roads, 1 for roads open in both directions, and 2 or 3 for
+    /// roads open against or along their geometry only
+    pub fn from_flowdir(flowdir: i64) -> Option<Self> {
+        match flowdir {
+            0 => Some(Direction::Closed),
+            1 => Some(Direction::Both),
+            2 => Some(Direction::Backward),
+            3 => Some(Direction::Forward),
+            _ => None,
+        }
+    }
+}
+
+/// The names of the attributes from which the edges of a map are built
+#[derive(Debug, Clone)]
+pub struct AttributeMapping {
+    /// Integer id of the road, unique within the map
+    pub id: String,
+    /// Metadata passed back to the map in next edge requests, empty if unset
+    pub meta: Option<String>,
+    /// Numeric OpenLR form of way (0-7)
+    pub fow: String,
+    /// Numeric OpenLR functional road class (0-7)
+    pub frc: String,
+    /// Length in meters, calculated from the geometry if unset
+    pub length: Option<String>,
+    /// Integer id of the node at which the geometry starts
+    pub start_node: String,
+    /// Integer id of the node at which the geometry ends
+    pub end_node: String,
+    /// Direction of travel, read through `direction_values`.  If unset, every
+    /// road is open in the direction of its geometry only.
+    pub direction: Option<String>,
+    /// The direction of travel denoted by each value of the direction attribute
+    pub direction_values: HashMap<String, Direction>,
+}
+
+impl Default for AttributeMapping {
+    fn default() -> Self {
+        AttributeMapping {
+            id: "id".to_owned(),
+            meta: Some("meta".to_owned()),
+            fow: "fow".to_owned(),
+            frc: "frc".to_owned(),
+            length: Some("len".to_owned()),
+            start_node: "from_int".to_owned(),
+            end_node: "to_int".to_owned(),
+            direction: Some("flowdir".to_owned()),
+            direction_values: (0..=3)
+                .filter_map(|f| Some((f.to_string(), Direction::from_flowdir(f)?)))
+                .collect(),
+        }
+    }
+}
+
+/// Loads the edges of a map from a file, choosing the loader from its
+/// extension: `.geojson` or `.json`, `.gpkg` (reading the first feature table),
+/// `.fgb`, or `.csv` (in the format of the test data, ignoring the mapping).
+pub fn load(path: &str, mapping: &AttributeMapping) -> Result<Vec<MapEdge>, OpenLrErr> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_lowercase());
+    match extension.as_deref() {
+        Some("geojson") | Some("json") => geojson::load(path, mapping),
+        Some("gpkg") => geopackage::load(path, None, mapping),
+        Some("fgb") => flatgeobuf::load(path, mapping),
+        Some("csv") => csv::load(path),
+        _ => Err(OpenLrErr::MapLoadError(format!(
+            "{}: unsupported file format",
+            path
+        ))),
+    }
+}
+
+// The value of an attribute, as read from any of the formats
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum AttributeValue {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+}
+
+impl AttributeValue {
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            AttributeValue::Integer(i) => Some(*i),
+            AttributeValue::Real(f) if f.fract() == 0.0 => Some(*f as i64),
+            AttributeValue::Text(s) => s.trim().parse::<i64>().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            AttributeValue::Integer(i) => Some(*i as f64),
+            AttributeValue::Real(f) => Some(*f),
+            AttributeValue::Text(s) => s.trim().parse::<f64>().ok(),
+            _ => None,
+        }
+    }
+
+    // The value as text, with whole numbers written as integers whatever their type
+    fn as_text(&self) -> String {
+        match self {
+            AttributeValue::Null => String::new(),
+            AttributeValue::Integer(i) => i.to_string(),
+            AttributeValue::Real(f) if f.fract() == 0.0 => (*f as i64).to_string(),
+            AttributeValue::Real(f) => f.to_string(),
+            AttributeValue::Text(s) => s.clone(),
+        }
+    }
+}
+
+// Builds the edges for one feature of a file
+pub(crate) fn edges_from_feature(
+    mapping: &AttributeMapping,
+    source: &str,
+    number: usize,
+    coords: Vec<Coord>,
+    attribute: impl Fn(&str) -> Option<AttributeValue>,
+) -> Result<Vec<MapEdge>, OpenLrErr> {
+    let invalid = |reason: String| OpenLrErr::InvalidMapFeature(source.to_owned(), number, reason);
+    let required = |name: &str| match attribute(name) {
+        None | Some(AttributeValue::Null) => Err(invalid(format!("missing attribute '{}'", name))),
+        Some(value) => Ok(value),
+    };
+    let integer = |name: &str| {
+        required(name)?
+            .as_i64()
+            .ok_or_else(|| invalid(format!("attribute '{}' is not an integer", name)))
+    };
+    let class = |name: &str| {
+        integer(name).and_then(|i| match u8::try_from(i) {
+            Ok(i) if i <= 7 => Ok(i),
+            _ => Err(invalid(format!(
+                "attribute '{}' is not within 0-7: {}",
+                name, i
+            ))),
+        })
+    };
+
+    let id = integer(&mapping.id)?;
+    let fow = FOW::from_u8(class(&mapping.fow)?);
+    let frc = FRC::from_u8(class(&mapping.frc)?);
+    let start_node = integer(&mapping.start_node)?;
+    let end_node = integer(&mapping.end_node)?;
+    let meta = mapping
+        .meta
+        .as_deref()
+        .and_then(&attribute)
+        .map(|m| m.as_text())
+        .unwrap_or_default();
+
+    if coords.len() < 2 {
+        return Err(invalid("the geometry needs at least two points".to_owned()));
+    }
+    let geom = LineString::new(coords);
+    let len = match &mapping.length {
+        Some(name) => match required(name)?.as_f64() {
+            Some(len) if len >= 0.0 && len <= u32::MAX as f64 => len.round() as u32,
+            _ => return Err(invalid(format!("attribute '{}' is not a length", name))),
+        },
+        None => geom.haversine_length().round() as u32,
+    };
+    let direction = match &mapping.direction {
+        Some(name) => {
+            let value = required(name)?.as_text();
+            *mapping.direction_values.get(&value).ok_or_else(|| {
+                invalid(format!(
+                    "unknown direction '{}' in attribute '{}'",
+                    value, name
+                ))
+            })?
+        }
+        None => Direction::Forward,
+    };
+
+    Ok(directed_edges(
+        Edge::new(id, meta, fow, frc, len, geom),
+        start_node,
+        end_node,
+        direction,
+    ))
+}
+
+// The edges along which a road may be travelled: none if it is closed, one for
+// each direction in which it is open.  The edge against the direction of the
+// geometry has the negated id of the road.
+pub(crate) fn directed_edges(
+    edge: Edge,
+    start_node: i64,
+    end_node: i64,
+    direction: Direction,
+) -> Vec<MapEdge> {
+    let backward = |edge: &Edge| MapEdge::new(Edge::new_from_reverse(edge), end_node, start_node);
+    match direction {
+        Direction::Both => vec![backward(&edge), MapEdge::new(edge, start_node, end_node)],
+        Direction::Forward => vec![MapEdge::new(edge, start_node, end_node)],
+        Direction::Backward => vec![backward(&edge)],
+        Direction::Closed => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(flowdir: &str) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            ("id".to_owned(), AttributeValue::Integer(7)),
+            ("fow".to_owned(), AttributeValue::Integer(3)),
+            ("frc".to_owned(), AttributeValue::Real(5.0)),
+            ("len".to_owned(), AttributeValue::Real(99.6)),
+            ("from_int".to_owned(), AttributeValue::Text("10".to_owned())),
+            ("to_int".to_owned(), AttributeValue::Integer(11)),
+            (
+                "flowdir".to_owned(),
+                AttributeValue::Text(flowdir.to_owned()),
+            ),
+        ])
+    }
+
+    fn edges(attributes: &HashMap<String, AttributeValue>) -> Result<Vec<MapEdge>, OpenLrErr> {
+        edges_from_feature(
+            &AttributeMapping::default(),
+            "roads",
+            3,
+            vec![Coord { x: 0.0, y: 0.0 }, Coord { x: 0.001, y: 0.0 }],
+            |name| attributes.get(name).cloned(),
+        )
+    }
+
+    #[test]
+    fn test_edges_from_feature() {
+        let both = edges(&attributes("1")).unwrap();
+        assert_eq!(both.len(), 2);
+        let forward = both.iter().find(|me| me.edge.id == 7).unwrap();
+        assert_eq!((forward.start_node, forward.end_node), (10, 11));
+        assert_eq!(forward.edge.len, 100);
+        assert_eq!(forward.edge.meta, "");
+        let backward = both.iter().find(|me| me.edge.id == -7).unwrap();
+        assert_eq!((backward.start_node, backward.end_node), (11, 10));
+        assert_eq!(backward.edge.geom.0[0], Coord { x: 0.001, y: 0.0 });
+
+        let backward = edges(&attributes("2")).unwrap();
+        assert_eq!(backward.len(), 1);
+        assert_eq!(backward[0].edge.id, -7);
+        assert_eq!(edges(&attributes("3")).unwrap()[0].edge.id, 7);
+
+        let a = attributes("0");
+        let none = edges_from_feature(
+            &AttributeMapping::default(),
+            "roads",
+            3,
+            vec![Coord { x: 0.0, y: 0.0 }, Coord { x: 0.001, y: 0.0 }],
+            |name| a.get(name).cloned(),
+        )
+        .unwrap();
+        assert!(none.is_empty());
+
+        // the length is calculated when unmapped
+        let unmapped = AttributeMapping {
+            length: None,
+            direction: None,
+            ..Default::default()
+        };
+        let a = attributes("");
+        let edges = edges_from_feature(
+            &unmapped,
+            "roads",
+            3,
+            vec![Coord { x: 0.0, y: 0.0 }, Coord { x: 0.001, y: 0.0 }],
+            |name| a.get(name).cloned(),
+        )
+        .unwrap();
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].edge.len, 111);
+    }
+
+    #[test]
+    fn test_invalid_features() {
+        match edges(&attributes("4")) {
+            Err(OpenLrErr::InvalidMapFeature(source, number, reason)) => {
+                assert_eq!((source.as_str(), number), ("roads", 3));
+                assert!(reason.contains("flowdir"));
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+
+        let mut a = attributes("1");
+        a.insert("fow".to_owned(), AttributeValue::Integer(8));
+        assert!(matches!(
+            edges(&a),
+            Err(OpenLrErr::InvalidMapFeature(_, 3, _))
+        ));
+        a.remove("fow");
+        assert!(matches!(
+            edges(&a),
+            Err(OpenLrErr::InvalidMapFeature(_, 3, _))
+        ));
+
+        let a = attributes("1");
+        assert!(edges_from_feature(
+            &AttributeMapping::default(),
+            "roads",
+            3,
+            vec![Coord { x: 0.0, y: 0.0 }],
+            |name| a.get(name).cloned(),
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,423 @@
+//! Importer which builds a road network from an OpenStreetMap PBF extract.
+//!
+//! Ways whose `highway` tag is in the [`OsmMapping`] are split at every node
+//! they share with another such way (or visit twice), giving one edge per
+//! stretch of road between intersections.  The FRC and FOW of the edges come
+//! from the `highway` and `junction` tags, and their direction of travel from
+//! the `oneway` tag.  Each edge is identified by the id of its way times 4096
+//! plus its index along the way, and edges against the direction of the way
+//! by the negation of that id.
+//!
+//! The file is read twice, first for the ways and then for the coordinates of
+//! their nodes, so only the nodes of the road network are held in memory.
+//! Stretches of road with nodes missing from the extract are left out.
+
+mod pbf;
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::BufReader;
+
+use geo::{Coord, HaversineLength, LineString};
+use rustc_hash::FxHashMap;
+
+use self::pbf::{PbfReader, PrimitiveBlock};
+use super::{directed_edges, Direction};
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::fow::FOW;
+use crate::frc::FRC;
+use crate::in_memory_map::MapEdge;
+
+// The edges of a way are numbered below this
+const EDGES_PER_WAY: i64 = 4096;
+
+/// The tables deriving the attributes of edges from the tags of OSM ways
+#[derive(Debug, Clone)]
+pub struct OsmMapping {
+    /// The FRC and FOW of each value of the `highway` tag to be imported
+    pub highways: HashMap<String, (FRC, FOW)>,
+    /// The FOW of each value of the `junction` tag overriding that of the highway
+    pub junctions: HashMap<String, FOW>,
+    /// The direction of travel of each value of the `oneway` tag
+    pub oneway_values: HashMap<String, Direction>,
+    /// Values of the `highway` or `junction` tags which make a way one-way in
+    /// its own direction, unless its `oneway` tag says otherwise
+    pub implied_oneway: HashSet<String>,
+}
+
+impl Default for OsmMapping {
+    fn default() -> Self {
+        let highways = [
+            ("motorway", FRC::FRC0, FOW::Motorway),
+            ("motorway_link", FRC::FRC0, FOW::SlipRoad),
+            ("trunk", FRC::FRC1, FOW::MultipleCarriageway),
+            ("trunk_link", FRC::FRC1, FOW::SlipRoad),
+            ("primary", FRC::FRC2, FOW::SingleCarriageway),
+            ("primary_link", FRC::FRC2, FOW::SlipRoad),
+            ("secondary", FRC::FRC3, FOW::SingleCarriageway),
+            ("secondary_link", FRC::FRC3, FOW::SlipRoad),
+            ("tertiary", FRC::FRC4, FOW::SingleCarriageway),
+            ("tertiary_link", FRC::FRC4, FOW::SlipRoad),
+            ("unclassified", FRC::FRC5, FOW::SingleCarriageway),
+            ("residential", FRC::FRC6, FOW::SingleCarriageway),
+            ("living_street", FRC::FRC6, FOW::SingleCarriageway),
+            ("service", FRC::FRC7, FOW::Other),
+        ];
+        OsmMapping {
+            highways: highways
+                .into_iter()
+                .map(|(highway, frc, fow)| (highway.to_owned(), (frc, fow)))
+                .collect(),
+            junctions: HashMap::from([
+                ("roundabout".to_owned(), FOW::Roundabout),
+                ("circular".to_owned(), FOW::Roundabout),
+            ]),
+            oneway_values: HashMap::from([
+                ("yes".to_owned(), Direction::Forward),
+                ("true".to_owned(), Direction::Forward),
+                ("1".to_owned(), Direction::Forward),
+                ("-1".to_owned(), Direction::Backward),
+                ("reverse".to_owned(), Direction::Backward),
+                ("no".to_owned(), Direction::Both),
+                ("false".to_owned(), Direction::Both),
+                ("0".to_owned(), Direction::Both),
+                // the direction of these changes over time
+                ("reversible".to_owned(), Direction::Closed),
+                ("alternating".to_owned(), Direction::Closed),
+            ]),
+            implied_oneway: HashSet::from([
+                "motorway".to_owned(),
+                "roundabout".to_owned(),
+                "circular".to_owned(),
+            ]),
+        }
+    }
+}
+
+// A way of the road network
+struct Road {
+    id: i64,
+    nodes: Vec<i64>,
+    frc: FRC,
+    fow: FOW,
+    direction: Direction,
+}
+
+/// Loads the edges of a road network from an OSM PBF file
+pub fn load(path: &str, mapping: &OsmMapping) -> Result<Vec<MapEdge>, OpenLrErr> {
+    let roads = read_blocks(path, Vec::new(), |roads, block| {
+        read_roads(block, mapping, roads)
+    })?;
+
+    // the number of times each node is visited by the roads
+    let mut visits = FxHashMap::<i64, u32>::default();
+    for node in roads.iter().flat_map(|r| r.nodes.iter()) {
+        *visits.entry(*node).or_default() += 1;
+    }
+
+    let coords = read_blocks(path, FxHashMap::default(), |coords, block| {
+        read_coords(block, &visits, coords);
+        Ok(())
+    })?;
+
+    let mut edges = Vec::new();
+    for road in roads {
+        let mut start = 0;
+        let mut index = 0;
+        for end in 1..road.nodes.len() {
+            let node = road.nodes[end];
+            if end + 1 < road.nodes.len() && visits[&node] == 1 {
+                continue;
+            }
+            if index == EDGES_PER_WAY {
+                return Err(OpenLrErr::MapLoadError(format!(
+                    "{}: way {} has more than {} edges",
+                    path, road.id, EDGES_PER_WAY
+                )));
+            }
+            let geom = road.nodes[start..=end]
+                .iter()
+                .map(|n| coords.get(n).copied())
+                .collect::<Option<Vec<Coord>>>();
+            if let Some(geom) = geom {
+                let geom = LineString::new(geom);
+                let len = geom.haversine_length().round() as u32;
+                let edge = Edge::new(
+                    road.id * EDGES_PER_WAY + index,
+                    String::new(),
+                    road.fow,
+                    road.frc,
+                    len,
+                    geom,
+                );
+                edges.extend(directed_edges(
+                    edge,
+                    road.nodes[start],
+                    node,
+                    road.direction,
+                ));
+            }
+            start = end;
+            index += 1;
+        }
+    }
+    Ok(edges)
+}
+
+// Reads each block of a file into a state
+fn read_blocks<T>(
+    path: &str,
+    mut state: T,
+    mut read: impl FnMut(&mut T, &PrimitiveBlock) -> Result<(), OpenLrErr>,
+) -> Result<T, OpenLrErr> {
+    let file = File::open(path).map_err(|e| OpenLrErr::MapLoadError(format!("{}: {}", path, e)))?;
+    let mut reader = PbfReader::new(BufReader::new(file));
+    while let Some(block) = reader
+        .next_block()
+        .map_err(|e| OpenLrErr::MapLoadError(format!("{}: {}", path, e)))?
+    {
+        read(&mut state, &block)?;
+    }
+    Ok(state)
+}
+
+fn read_roads(
+    block: &PrimitiveBlock,
+    mapping: &OsmMapping,
+    roads: &mut Vec<Road>,
+) -> Result<(), OpenLrErr> {
+    for way in block.primitivegroup.iter().flat_map(|g| g.ways.iter()) {
+        let tag = |key: &str| {
+            way.keys
+                .iter()
+                .position(|k| block.string(*k) == Some(key))
+                .and_then(|i| way.vals.get(i))
+                .and_then(|v| block.string(*v))
+        };
+        let (frc, mut fow) = match tag("highway").and_then(|h| mapping.highways.get(h)) {
+            Some(class) if tag("area") != Some("yes") => *class,
+            _ => continue,
+        };
+        if way.id <= 0 {
+            return Err(OpenLrErr::MapLoadError(format!(
+                "way {} has a non-positive id",
+                way.id
+            )));
+        }
+
+        let junction = tag("junction");
+        if let Some(junction_fow) = junction.and_then(|j| mapping.junctions.get(j)) {
+            fow = *junction_fow;
+        }
+        let implied_oneway = [tag("highway"), junction]
+            .into_iter()
+            .flatten()
+            .any(|t| mapping.implied_oneway.contains(t));
+        let direction = match tag("oneway").and_then(|o| mapping.oneway_values.get(o)) {
+            Some(direction) => *direction,
+            None if implied_oneway => Direction::Forward,
+            None => Direction::Both,
+        };
+
+        let nodes = way
+            .refs
+            .iter()
+            .scan(0, |id, delta| {
+                *id += delta;
+                Some(*id)
+            })
+            .collect::<Vec<i64>>();
+        if nodes.len() >= 2 {
+            roads.push(Road {
+                id: way.id,
+                nodes,
+                frc,
+                fow,
+                direction,
+            });
+        }
+    }
+    Ok(())
+}
+
+// Keeps the coordinates of the nodes of a block which are visited by roads
+fn read_coords(
+    block: &PrimitiveBlock,
+    visits: &FxHashMap<i64, u32>,
+    coords: &mut FxHashMap<i64, Coord>,
+) {
+    let mut keep = |id: i64, lon: i64, lat: i64| {
+        if visits.contains_key(&id) {
+            let (x, y) = block.position(lon, lat);
+            coords.insert(id, Coord { x, y });
+        }
+    };
+    for group in &block.primitivegroup {
+        for node in &group.nodes {
+            keep(node.id, node.lon, node.lat);
+        }
+        if let Some(dense) = &group.dense {
+            let (mut id, mut lat, mut lon) = (0, 0, 0);
+            for ((d_id, d_lat), d_lon) in dense.id.iter().zip(&dense.lat).zip(&dense.lon) {
+                id += d_id;
+                lat += d_lat;
+                lon += d_lon;
+                keep(id, lon, lat);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::pbf::{Blob, BlobHeader, DenseNodes, HeaderBlock, PrimitiveGroup, StringTable, Way};
+    use super::*;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+    use prost::Message;
+    use std::io::Write;
+
+    fn delta(values: &[i64]) -> Vec<i64> {
+        let mut previous = 0;
+        values
+            .iter()
+            .map(|v| {
+                let d = v - previous;
+                previous = *v;
+                d
+            })
+            .collect()
+    }
+
+    fn write_blob(data: &mut Vec<u8>, blob_type: &str, block: Vec<u8>, compress: bool) {
+        let blob = match compress {
+            true => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(&block).unwrap();
+                Blob {
+                    raw: None,
+                    raw_size: Some(block.len() as i32),
+                    zlib_data: Some(encoder.finish().unwrap()),
+                }
+            }
+            false => Blob {
+                raw: Some(block),
+                raw_size: None,
+                zlib_data: None,
+            },
+        }
+        .encode_to_vec();
+        let header = BlobHeader {
+            r#type: blob_type.to_owned(),
+            datasize: blob.len() as i32,
+        }
+        .encode_to_vec();
+        data.extend((header.len() as u32).to_be_bytes());
+        data.extend(header);
+        data.extend(blob);
+    }
+
+    // A crossroads at node 2 of a two-way primary road (way 1: 1-2-3) and a
+    // one-way residential road (way 2: 4-2-5), a footway (way 3: 3-5), and a
+    // roundabout (way 4: 3-6-7-3)
+    fn write_pbf(path: &std::path::Path, required_feature: &str) {
+        let strings = [
+            "",
+            "highway",
+            "primary",
+            "residential",
+            "footway",
+            "oneway",
+            "yes",
+            "junction",
+            "roundabout",
+        ];
+        let way = |id: i64, refs: &[i64], tags: &[(u32, u32)]| Way {
+            id,
+            keys: tags.iter().map(|(k, _)| *k).collect(),
+            vals: tags.iter().map(|(_, v)| *v).collect(),
+            refs: delta(refs),
+        };
+        let ids = [1, 2, 3, 4, 5, 6, 7];
+        let lons = [0.0, 0.001, 0.002, 0.001, 0.001, 0.003, 0.003];
+        let lats = [0.0, 0.0, 0.0, 0.001, -0.001, 0.001, -0.001];
+        let block = PrimitiveBlock {
+            stringtable: Some(StringTable {
+                s: strings.iter().map(|s| s.as_bytes().to_vec()).collect(),
+            }),
+            primitivegroup: vec![
+                PrimitiveGroup {
+                    nodes: vec![],
+                    dense: Some(DenseNodes {
+                        id: delta(&ids),
+                        // granularity of 1000 nanodegrees, offset by one degree
+                        lat: delta(&lats.map(|l: f64| ((l - 1.0) * 1e6).round() as i64)),
+                        lon: delta(&lons.map(|l: f64| ((l - 1.0) * 1e6).round() as i64)),
+                    }),
+                    ways: vec![],
+                },
+                PrimitiveGroup {
+                    nodes: vec![],
+                    dense: None,
+                    ways: vec![
+                        way(1, &[1, 2, 3], &[(1, 2)]),
+                        way(2, &[4, 2, 5], &[(1, 3), (5, 6)]),
+                        way(3, &[3, 5], &[(1, 4)]),
+                        way(4, &[3, 6, 7, 3], &[(1, 3), (7, 8)]),
+                    ],
+                },
+            ],
+            granularity: Some(1000),
+            lat_offset: Some(1_000_000_000),
+            lon_offset: Some(1_000_000_000),
+        };
+
+        let mut data = Vec::new();
+        let header = HeaderBlock {
+            required_features: vec!["OsmSchema-V0.6".to_owned(), required_feature.to_owned()],
+        };
+        write_blob(&mut data, "OSMHeader", header.encode_to_vec(), false);
+        write_blob(&mut data, "OSMData", block.encode_to_vec(), true);
+        std::fs::write(path, data).unwrap();
+    }
+
+    fn ids(edges: &[MapEdge]) -> Vec<i64> {
+        let mut ids = edges.iter().map(|me| me.edge.id).collect::<Vec<i64>>();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_load() {
+        let path = std::env::temp_dir().join(format!("openlr_osm_test_{}.pbf", std::process::id()));
+        write_pbf(&path, "DenseNodes");
+        let edges = load(path.to_str().unwrap(), &OsmMapping::default()).unwrap();
+
+        // both roads are split at the crossroads, the one-way road and the
+        // roundabout only have edges in their own direction, and the footway none
+        assert_eq!(
+            ids(&edges),
+            vec![-4097, -4096, 4096, 4097, 8192, 8193, 16384]
+        );
+        let edge = |id: i64| edges.iter().find(|me| me.edge.id == id).unwrap();
+        assert_eq!((edge(4096).start_node, edge(4096).end_node), (1, 2));
+        assert_eq!((edge(-4096).start_node, edge(-4096).end_node), (2, 1));
+        assert_eq!(edge(4096).edge.frc, FRC::FRC2);
+        assert_eq!(edge(4096).edge.len, 111);
+        assert_eq!(edge(8193).edge.fow, FOW::SingleCarriageway);
+        assert_eq!(edge(16384).edge.fow, FOW::Roundabout);
+        assert_eq!((edge(16384).start_node, edge(16384).end_node), (3, 3));
+        let start = edge(-4097).edge.geom.0[0];
+        assert!((start.x - 0.002).abs() < 1e-9 && start.y.abs() < 1e-9);
+
+        write_pbf(&path, "HistoricalInformation");
+        match load(path.to_str().unwrap(), &OsmMapping::default()) {
+            Err(OpenLrErr::MapLoadError(reason)) => {
+                assert!(reason.contains("HistoricalInformation"))
+            }
+            r => panic!("Unexpected result: {:?}", r.map(|edges| edges.len())),
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
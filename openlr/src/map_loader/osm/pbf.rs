@@ -0,0 +1,212 @@
+//! The messages of the OSM PBF format (fileformat.proto and osmformat.proto),
+//! limited to the fields needed to build a road network, and a reader of the
+//! blocks of a file.
+
+use std::io::{ErrorKind, Read};
+
+use flate2::read::ZlibDecoder;
+use prost::Message;
+
+// Limits set by the format on the size of a blob header and of a blob
+const MAX_BLOB_HEADER_SIZE: usize = 64 * 1024;
+const MAX_BLOB_SIZE: usize = 32 * 1024 * 1024;
+
+// The features a reader may be required to understand
+const SUPPORTED_FEATURES: [&str; 2] = ["OsmSchema-V0.6", "DenseNodes"];
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct BlobHeader {
+    #[prost(string, required, tag = "1")]
+    pub r#type: String,
+    #[prost(int32, required, tag = "3")]
+    pub datasize: i32,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct Blob {
+    #[prost(bytes = "vec", optional, tag = "1")]
+    pub raw: Option<Vec<u8>>,
+    #[prost(int32, optional, tag = "2")]
+    pub raw_size: Option<i32>,
+    #[prost(bytes = "vec", optional, tag = "3")]
+    pub zlib_data: Option<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct HeaderBlock {
+    #[prost(string, repeated, tag = "4")]
+    pub required_features: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct PrimitiveBlock {
+    #[prost(message, optional, tag = "1")]
+    pub stringtable: Option<StringTable>,
+    #[prost(message, repeated, tag = "2")]
+    pub primitivegroup: Vec<PrimitiveGroup>,
+    #[prost(int32, optional, tag = "17", default = "100")]
+    pub granularity: Option<i32>,
+    #[prost(int64, optional, tag = "19", default = "0")]
+    pub lat_offset: Option<i64>,
+    #[prost(int64, optional, tag = "20", default = "0")]
+    pub lon_offset: Option<i64>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct StringTable {
+    #[prost(bytes = "vec", repeated, tag = "1")]
+    pub s: Vec<Vec<u8>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct PrimitiveGroup {
+    #[prost(message, repeated, tag = "1")]
+    pub nodes: Vec<Node>,
+    #[prost(message, optional, tag = "2")]
+    pub dense: Option<DenseNodes>,
+    #[prost(message, repeated, tag = "3")]
+    pub ways: Vec<Way>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct Node {
+    #[prost(sint64, required, tag = "1")]
+    pub id: i64,
+    #[prost(sint64, required, tag = "8")]
+    pub lat: i64,
+    #[prost(sint64, required, tag = "9")]
+    pub lon: i64,
+}
+
+// Ids and coordinates are delta coded
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct DenseNodes {
+    #[prost(sint64, repeated, packed = "true", tag = "1")]
+    pub id: Vec<i64>,
+    #[prost(sint64, repeated, packed = "true", tag = "8")]
+    pub lat: Vec<i64>,
+    #[prost(sint64, repeated, packed = "true", tag = "9")]
+    pub lon: Vec<i64>,
+}
+
+// Node references are delta coded
+#[derive(Clone, PartialEq, Message)]
+pub(crate) struct Way {
+    #[prost(int64, required, tag = "1")]
+    pub id: i64,
+    #[prost(uint32, repeated, packed = "true", tag = "2")]
+    pub keys: Vec<u32>,
+    #[prost(uint32, repeated, packed = "true", tag = "3")]
+    pub vals: Vec<u32>,
+    #[prost(sint64, repeated, packed = "true", tag = "8")]
+    pub refs: Vec<i64>,
+}
+
+impl PrimitiveBlock {
+    /// Returns the entry of the string table at an index, if valid
+    pub fn string(&self, index: u32) -> Option<&str> {
+        self.stringtable
+            .as_ref()
+            .and_then(|t| t.s.get(index as usize))
+            .and_then(|s| std::str::from_utf8(s).ok())
+    }
+
+    /// Returns the longitude and latitude of coordinates in units of the block's
+    /// granularity
+    pub fn position(&self, lon: i64, lat: i64) -> (f64, f64) {
+        let granularity = self.granularity() as i64;
+        (
+            1e-9 * (self.lon_offset() + granularity * lon) as f64,
+            1e-9 * (self.lat_offset() + granularity * lat) as f64,
+        )
+    }
+}
+
+/// Reads the blocks of OSM data from a PBF file, one at a time
+pub(crate) struct PbfReader<R: Read> {
+    reader: R,
+}
+
+impl<R: Read> PbfReader<R> {
+    pub fn new(reader: R) -> Self {
+        PbfReader { reader }
+    }
+
+    /// Returns the next block of OSM data, or None at the end of the file,
+    /// checking that any header only requires supported features
+    pub fn next_block(&mut self) -> Result<Option<PrimitiveBlock>, String> {
+        loop {
+            let mut len = [0u8; 4];
+            match self.reader.read_exact(&mut len) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e.to_string()),
+            }
+            let header = BlobHeader::decode(
+                self.read(u32::from_be_bytes(len) as usize, MAX_BLOB_HEADER_SIZE)?
+                    .as_slice(),
+            )
+            .map_err(|e| format!("invalid blob header: {}", e))?;
+            let blob = Blob::decode(
+                self.read(header.datasize.max(0) as usize, MAX_BLOB_SIZE)?
+                    .as_slice(),
+            )
+            .map_err(|e| format!("invalid blob: {}", e))?;
+            let data = decompress(blob)?;
+
+            match header.r#type.as_str() {
+                "OSMHeader" => {
+                    let header = HeaderBlock::decode(data.as_slice())
+                        .map_err(|e| format!("invalid header block: {}", e))?;
+                    if let Some(feature) = header
+                        .required_features
+                        .iter()
+                        .find(|f| !SUPPORTED_FEATURES.contains(&f.as_str()))
+                    {
+                        return Err(format!("unsupported feature {}", feature));
+                    }
+                }
+                "OSMData" => {
+                    return PrimitiveBlock::decode(data.as_slice())
+                        .map(Some)
+                        .map_err(|e| format!("invalid data block: {}", e))
+                }
+                // blobs of unknown types are skipped, as the format requires
+                _ => {}
+            }
+        }
+    }
+
+    fn read(&mut self, len: usize, max: usize) -> Result<Vec<u8>, String> {
+        if len > max {
+            return Err(format!(
+                "blob of {} bytes exceeds the limit of {}",
+                len, max
+            ));
+        }
+        let mut buf = vec![0u8; len];
+        self.reader
+            .read_exact(&mut buf)
+            .map_err(|e| e.to_string())?;
+        Ok(buf)
+    }
+}
+
+fn decompress(blob: Blob) -> Result<Vec<u8>, String> {
+    match (blob.raw, blob.zlib_data) {
+        (Some(raw), _) => Ok(raw),
+        (None, Some(zlib_data)) => {
+            let raw_size = blob.raw_size.unwrap_or(0).max(0) as usize;
+            if raw_size > MAX_BLOB_SIZE {
+                return Err(format!("blob of {} bytes exceeds the limit", raw_size));
+            }
+            let mut data = Vec::with_capacity(raw_size);
+            ZlibDecoder::new(zlib_data.as_slice())
+                .take(MAX_BLOB_SIZE as u64)
+                .read_to_end(&mut data)
+                .map_err(|e| format!("invalid zlib data: {}", e))?;
+            Ok(data)
+        }
+        (None, None) => Err("unsupported blob compression".to_owned()),
+    }
+}
@@ -0,0 +1,49 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum Orientation {
+    NoOrientationOrUnknown = 0,
+    FirstToSecond = 1,
+    SecondToFirst = 2,
+    BothDirections = 3,
+}
+
+impl Copy for Orientation {}
+
+impl Orientation {
+    #[allow(dead_code)]
+    pub fn to_usize(&self) -> usize {
+        match self {
+            Orientation::NoOrientationOrUnknown => 0,
+            Orientation::FirstToSecond => 1,
+            Orientation::SecondToFirst => 2,
+            Orientation::BothDirections => 3,
+        }
+    }
+    pub fn from_u8(i: u8) -> Self {
+        match i {
+            0 => Orientation::NoOrientationOrUnknown,
+            1 => Orientation::FirstToSecond,
+            2 => Orientation::SecondToFirst,
+            3 => Orientation::BothDirections,
+            _ => unreachable!(),
+        }
+    }
+    pub fn to_xml(&self) -> &'static str {
+        match self {
+            Orientation::NoOrientationOrUnknown => "NO_ORIENTATION_OR_UNKNOWN",
+            Orientation::FirstToSecond => "WITH_LINE_DIRECTION",
+            Orientation::SecondToFirst => "AGAINST_LINE_DIRECTION",
+            Orientation::BothDirections => "BOTH",
+        }
+    }
+    pub fn from_xml(s: &str) -> Option<Self> {
+        match s {
+            "NO_ORIENTATION_OR_UNKNOWN" => Some(Orientation::NoOrientationOrUnknown),
+            "WITH_LINE_DIRECTION" => Some(Orientation::FirstToSecond),
+            "AGAINST_LINE_DIRECTION" => Some(Orientation::SecondToFirst),
+            "BOTH" => Some(Orientation::BothDirections),
+            _ => None,
+        }
+    }
+}
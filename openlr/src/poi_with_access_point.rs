@@ -0,0 +1,142 @@
+use crate::binary_header::BinaryHeader;
+use crate::common::{calculate_offset, find_route_across_lrps, get_next_coordinate};
+use crate::decodable_reference::DecodableReference;
+use crate::decoding_parameters::DecodingParameters;
+use crate::deserializable_reference::DeserializableReference;
+use crate::errors::OpenLrErr;
+use crate::line::LineLocationReference;
+use crate::location::Location;
+use crate::location_reference_point::LocationReferencePoint;
+use crate::orientation::Orientation;
+use crate::point_along_line::{build_point_along_line_location, PointAlongLineLocation};
+use crate::request_context::RequestContext;
+use crate::side_of_road::SideOfRoad;
+use async_trait::async_trait;
+use serde::Serialize;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// OpenLR POI with access point                                       //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+#[derive(Debug, Serialize)]
+pub struct PoiWithAccessPointLocationReference {
+    pub(crate) header: BinaryHeader,
+    pub(crate) lrps: Vec<LocationReferencePoint>,
+    pub pos_offset: Option<(u32, u32)>,
+    pub side_of_road: SideOfRoad,
+    pub orientation: Orientation,
+    pub poi_longitude: f64,
+    pub poi_latitude: f64,
+}
+
+#[async_trait]
+impl DecodableReference for PoiWithAccessPointLocationReference {
+    type Peer = PoiWithAccessPointLocation;
+    async fn decode(
+        &self,
+        context: &RequestContext<DecodingParameters>,
+    ) -> Result<Location, OpenLrErr> {
+        // The access point is decoded exactly like a point along line: only the
+        // POI coordinate is carried through unchanged
+        match find_route_across_lrps(&self.lrps, context).await {
+            Ok((lp, pos_offset, neg_offset)) => {
+                Ok(Location::PoiWithAccessPoint(PoiWithAccessPointLocation {
+                    access_point: build_point_along_line_location(
+                        lp,
+                        pos_offset,
+                        neg_offset,
+                        self.pos_offset,
+                        self.side_of_road,
+                        self.orientation,
+                    )?,
+                    poi_longitude: self.poi_longitude,
+                    poi_latitude: self.poi_latitude,
+                }))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl DeserializableReference for PoiWithAccessPointLocationReference {
+    type T = PoiWithAccessPointLocationReference;
+    fn from_binary(bytes: &Vec<u8>) -> Result<Self::T, OpenLrErr> {
+        // header (1) + first LRP (9) + last LRP (6) + optional positive offset (1) + POI (4)
+        if bytes.len() != 20 && bytes.len() != 21 {
+            return Err(OpenLrErr::InvalidBinaryStringLength(bytes.len()));
+        }
+        let header = BinaryHeader::new(bytes[0]);
+
+        // The two most significant bits of the first LRP's first attribute byte
+        // carry the orientation, and those of the last LRP the side of road
+        let first = LineLocationReference::build_lrp(&bytes[1..10], None, 0, false);
+        let last = LineLocationReference::build_lrp(&bytes[10..16], Some(&first), 1, true);
+        let orientation = Orientation::from_u8(bytes[7] >> 6);
+        let side_of_road = SideOfRoad::from_u8(bytes[14] >> 6);
+
+        // Determine if positive offset is present
+        let p_off = bytes[15] & 0b01000000 > 0;
+        if p_off != (bytes.len() == 21) {
+            return Err(OpenLrErr::InvalidBinaryStringLength(bytes.len()));
+        }
+        let pos_offset: Option<(u32, u32)> = if p_off {
+            Some(calculate_offset(bytes[16], first.dnp.unwrap()))
+        } else {
+            None
+        };
+
+        // The POI coordinate trails the location and is relative to the first LRP
+        let poi = if p_off { 17 } else { 16 };
+        let poi_longitude = get_next_coordinate(bytes[poi], bytes[poi + 1], first.longitude);
+        let poi_latitude = get_next_coordinate(bytes[poi + 2], bytes[poi + 3], first.latitude);
+
+        Ok(PoiWithAccessPointLocationReference {
+            header,
+            lrps: vec![first, last],
+            pos_offset,
+            side_of_road,
+            orientation,
+            poi_longitude,
+            poi_latitude,
+        })
+    }
+
+    fn from_xml(_xml: &str) -> Result<Self::T, OpenLrErr> {
+        Err(OpenLrErr::UnsupportedLocationTypeError(
+            "PoiWithAccessPoint in XML".to_owned(),
+        ))
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PoiWithAccessPointLocation {
+    pub access_point: PointAlongLineLocation,
+    pub poi_longitude: f64,
+    pub poi_latitude: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_from_binary() {
+        let bytes: Vec<u8> = vec![
+            0x2b, 0xff, 0xb3, 0x18, 0x26, 0x49, 0x83, 0x6b, 0xaf, 0x04, 0x00, 0x21, 0xff, 0x1f,
+            0x6b, 0x5f, 0x80, 0x00, 0x64, 0xff, 0x38,
+        ];
+        let poi = PoiWithAccessPointLocationReference::from_binary(&bytes).unwrap();
+        assert_eq!(poi.lrps.len(), 2);
+        assert_eq!(poi.orientation, Orientation::FirstToSecond);
+        assert_eq!(poi.side_of_road, SideOfRoad::Right);
+        assert_eq!(poi.pos_offset, Some((117, 146)));
+        assert_relative_eq!(poi.poi_longitude, poi.lrps[0].longitude + 0.001);
+        assert_relative_eq!(poi.poi_latitude, poi.lrps[0].latitude - 0.002);
+
+        // the offset flag is set but the offset byte is missing
+        assert!(PoiWithAccessPointLocationReference::from_binary(&bytes[..20].to_vec()).is_err());
+    }
+}
@@ -0,0 +1,351 @@
+use crate::binary_header::BinaryHeader;
+use crate::common::{
+    calculate_offset, deg2int, find_route_across_lrps, get_relative_coordinate, offset2int, trim,
+};
+use crate::decodable_reference::DecodableReference;
+use crate::decoding_parameters::DecodingParameters;
+use crate::deserializable_reference::DeserializableReference;
+use crate::edge::Edge;
+use crate::encodable_location::EncodableLocation;
+use crate::encoder::encode_two_lrp_path;
+use crate::encoding_parameters::EncodingParameters;
+use crate::errors::OpenLrErr;
+use crate::line::LineLocationReference;
+use crate::location::Location;
+use crate::location_reference::LocationReference;
+use crate::location_reference_point::LocationReferencePoint;
+use crate::orientation::Orientation;
+use crate::request_context::RequestContext;
+use crate::serializable_reference::SerializableReference;
+use crate::side_of_road::SideOfRoad;
+use crate::xml;
+use async_trait::async_trait;
+use serde::Serialize;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// OpenLR PointAlongLine                                              //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+#[derive(Debug, Serialize)]
+pub struct PointAlongLineLocationReference {
+    pub(crate) header: BinaryHeader,
+    pub(crate) lrps: Vec<LocationReferencePoint>,
+    pub pos_offset: Option<(u32, u32)>,
+    pub side_of_road: SideOfRoad,
+    pub orientation: Orientation,
+}
+
+// Given a sequence of Edges that connect the two LRPs of a point location, generate a
+// PointAlongLineLocation whose first edge contains the point
+pub(crate) fn build_point_along_line_location(
+    path: Vec<Edge>,
+    seg_start_offset: u32,
+    seg_end_offset: u32,
+    pos_offset: Option<(u32, u32)>,
+    side_of_road: SideOfRoad,
+    orientation: Orientation,
+) -> Result<PointAlongLineLocation, OpenLrErr> {
+    // The point lies pos_offset meters beyond the projection of the first LRP,
+    // which itself may lie in the interior of the first edge of the path
+    let mut start_index: usize = 0;
+    let mut end_index: usize = path.len() - 1;
+    let mut start_offset: u32 = seg_start_offset + pos_offset.map_or(0, |(lb, _)| lb);
+    let mut end_offset: u32 = seg_end_offset;
+
+    // Drop the leading edges spanned completely by the offset, so that the
+    // point lies on the first edge of the location
+    if start_offset > 0 {
+        match trim(&mut path.iter(), start_offset) {
+            Some((index, offset)) => {
+                start_index = index;
+                start_offset = offset;
+            }
+            None => return Err(OpenLrErr::PostiveOffsetTooLong),
+        }
+    }
+
+    // Drop any trailing edges lying beyond the projection of the last LRP
+    if end_offset > 0 {
+        match trim(&mut path.iter().rev(), end_offset) {
+            Some((index, offset)) => {
+                end_index -= index;
+                end_offset = offset;
+            }
+            None => return Err(OpenLrErr::NegativeOffsetTooLong),
+        }
+    }
+
+    // The point cannot lie beyond the last LRP
+    if end_index < start_index
+        || (end_index == start_index
+            && start_offset + end_offset > path[start_index].get_line_length())
+    {
+        return Err(OpenLrErr::PostiveOffsetTooLong);
+    }
+
+    // Report the offset to the caller, taking into consideration
+    // any margin of error introduced by the binary encoding
+    Ok(PointAlongLineLocation {
+        p_off: if let Some((t0, t1)) = pos_offset {
+            Some((start_offset, start_offset + t1 - t0))
+        } else if start_offset > 0 {
+            Some((start_offset, start_offset))
+        } else {
+            None
+        },
+        edges: path[start_index..end_index + 1].to_owned(),
+        side_of_road,
+        orientation,
+    })
+}
+
+#[async_trait]
+impl DecodableReference for PointAlongLineLocationReference {
+    type Peer = PointAlongLineLocation;
+    async fn decode(
+        &self,
+        context: &RequestContext<DecodingParameters>,
+    ) -> Result<Location, OpenLrErr> {
+        match find_route_across_lrps(&self.lrps, context).await {
+            Ok((lp, pos_offset, neg_offset)) => Ok(Location::PointAlongLine(
+                build_point_along_line_location(
+                    lp,
+                    pos_offset,
+                    neg_offset,
+                    self.pos_offset,
+                    self.side_of_road,
+                    self.orientation,
+                )?,
+            )),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl DeserializableReference for PointAlongLineLocationReference {
+    type T = PointAlongLineLocationReference;
+    fn from_binary(bytes: &Vec<u8>) -> Result<Self::T, OpenLrErr> {
+        // header (1) + first LRP (9) + last LRP (6) + optional positive offset (1)
+        if bytes.len() != 16 && bytes.len() != 17 {
+            return Err(OpenLrErr::InvalidBinaryStringLength(bytes.len()));
+        }
+        let header = BinaryHeader::new(bytes[0]);
+
+        // The two most significant bits of the first LRP's first attribute byte
+        // carry the orientation, and those of the last LRP the side of road
+        let first = LineLocationReference::build_lrp(&bytes[1..10], None, 0, false);
+        let last = LineLocationReference::build_lrp(&bytes[10..16], Some(&first), 1, true);
+        let orientation = Orientation::from_u8(bytes[7] >> 6);
+        let side_of_road = SideOfRoad::from_u8(bytes[14] >> 6);
+
+        // Determine if positive offset is present
+        let pos_offset: Option<(u32, u32)> = if bytes[15] & 0b01000000 > 0 {
+            if bytes.len() != 17 {
+                return Err(OpenLrErr::InvalidBinaryStringLength(bytes.len()));
+            }
+            Some(calculate_offset(bytes[16], first.dnp.unwrap()))
+        } else {
+            None
+        };
+
+        Ok(PointAlongLineLocationReference {
+            header,
+            lrps: vec![first, last],
+            pos_offset,
+            side_of_road,
+            orientation,
+        })
+    }
+
+    fn from_xml(xml: &str) -> Result<Self::T, OpenLrErr> {
+        let doc = xml::parse(xml)?;
+        let node = xml::location_reference_node(&doc)?;
+        if node.tag_name().name() != "PointLocationReference" {
+            return Err(OpenLrErr::XmlParseError(format!(
+                "Expected <PointLocationReference> but found <{}>",
+                node.tag_name().name()
+            )));
+        }
+        let node = xml::child(node, "PointAlongLine")?;
+
+        let lrps = vec![
+            LocationReferencePoint::new_from_xml(
+                xml::child(node, "LocationReferencePoint")?,
+                0,
+                false,
+            )?,
+            LocationReferencePoint::new_from_xml(
+                xml::child(node, "LastLocationReferencePoint")?,
+                1,
+                true,
+            )?,
+        ];
+
+        // The offset, side of road and orientation are optional
+        let pos_offset = match xml::child(node, "Offsets") {
+            Ok(offsets) => xml::child_offset(offsets, "PosOff")?,
+            Err(_) => None,
+        };
+        let side_of_road = match xml::child(node, "SideOfRoad") {
+            Ok(_) => xml::child_side_of_road(node, "SideOfRoad")?,
+            Err(_) => SideOfRoad::OnRoadOrUnknown,
+        };
+        let orientation = match xml::child(node, "Orientation") {
+            Ok(_) => xml::child_orientation(node, "Orientation")?,
+            Err(_) => Orientation::NoOrientationOrUnknown,
+        };
+
+        Ok(PointAlongLineLocationReference {
+            // version 3 point along line location with attributes
+            header: BinaryHeader::new(0x2b),
+            lrps,
+            pos_offset,
+            side_of_road,
+            orientation,
+        })
+    }
+}
+
+impl SerializableReference for PointAlongLineLocationReference {
+    fn to_binary(&self) -> Result<Vec<u8>, OpenLrErr> {
+        let (first, last) = (&self.lrps[0], &self.lrps[1]);
+        let mut bytes = vec![self.header.to_u8()];
+
+        // The orientation and side of road occupy the two most significant bits of the
+        // first attribute byte of the first and last LRP respectively
+        bytes.extend_from_slice(&deg2int(first.longitude));
+        bytes.extend_from_slice(&deg2int(first.latitude));
+        let mut attributes = first.to_byte_array();
+        attributes[0] |= (self.orientation.to_usize() as u8) << 6;
+        bytes.extend(attributes);
+
+        bytes.extend_from_slice(&get_relative_coordinate(last.longitude, first.longitude)?);
+        bytes.extend_from_slice(&get_relative_coordinate(last.latitude, first.latitude)?);
+        let mut attributes = last.to_byte_array();
+        attributes[0] |= (self.side_of_road.to_usize() as u8) << 6;
+        bytes.extend(attributes);
+
+        if let Some(offset) = self.pos_offset {
+            let last = bytes.len() - 1;
+            bytes[last] |= 0b01000000;
+            bytes.push(offset2int(offset, first.dnp.unwrap()));
+        }
+
+        Ok(bytes)
+    }
+    fn to_xml(&self) -> Result<String, OpenLrErr> {
+        let mut body = self
+            .lrps
+            .iter()
+            .map(|lrp| lrp.to_xml())
+            .collect::<Vec<String>>()
+            .concat();
+        if let Some(offset) = self.pos_offset {
+            body.push_str(&format!(
+                "<Offsets><PosOff>{}</PosOff></Offsets>",
+                xml::midpoint(offset)
+            ));
+        }
+        body.push_str(&format!(
+            "<SideOfRoad>{}</SideOfRoad><Orientation>{}</Orientation>",
+            self.side_of_road.to_xml(),
+            self.orientation.to_xml()
+        ));
+
+        Ok(xml::to_document(&format!(
+            "<PointLocationReference><PointAlongLine>{}</PointAlongLine></PointLocationReference>",
+            body
+        )))
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PointAlongLineLocation {
+    pub edges: Vec<Edge>,
+    pub p_off: Option<(u32, u32)>,
+    pub side_of_road: SideOfRoad,
+    pub orientation: Orientation,
+}
+
+#[async_trait]
+impl EncodableLocation for PointAlongLineLocation {
+    type Peer = PointAlongLineLocationReference;
+    async fn encode(
+        &self,
+        context: &RequestContext<EncodingParameters>,
+    ) -> Result<LocationReference, OpenLrErr> {
+        if self.edges.is_empty() {
+            return Err(OpenLrErr::EmptyEdgeVec);
+        }
+
+        // Drop any leading edges spanned by the offset, so that the point lies on the
+        // first edge of the path
+        let (start, offset) = trim(&mut self.edges.iter(), self.p_off.map_or(0, |(lb, _)| lb))
+            .ok_or(OpenLrErr::PostiveOffsetTooLong)?;
+
+        // Reference the whole path if two LRPs can describe it, or else only the edge
+        // on which the point lies
+        let (lrps, pos_offset) =
+            match encode_two_lrp_path(&self.edges[start..], offset, context).await? {
+                Some(encoded) => encoded,
+                None => encode_two_lrp_path(&self.edges[start..=start], offset, context)
+                    .await?
+                    .ok_or_else(|| {
+                        OpenLrErr::MaximumDnpExceeded(
+                            self.edges[start].get_id(),
+                            context.params.max_dnp,
+                        )
+                    })?,
+            };
+
+        Ok(LocationReference::PointAlongLine(
+            PointAlongLineLocationReference {
+                // version 3 point along line location with attributes
+                header: BinaryHeader::new(0x2b),
+                lrps,
+                pos_offset: if pos_offset > 0 {
+                    Some((pos_offset, pos_offset))
+                } else {
+                    None
+                },
+                side_of_road: self.side_of_road,
+                orientation: self.orientation,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_binary() {
+        let bytes: Vec<u8> = vec![
+            0x2b, 0xff, 0xb3, 0x18, 0x26, 0x49, 0x83, 0x6b, 0xaf, 0x04, 0x00, 0x21, 0xff, 0x1f,
+            0xab, 0x5f, 0x80,
+        ];
+        let pal = PointAlongLineLocationReference::from_binary(&bytes).unwrap();
+        assert_eq!(pal.lrps.len(), 2);
+        assert_eq!(pal.orientation, Orientation::FirstToSecond);
+        assert_eq!(pal.side_of_road, SideOfRoad::Left);
+        assert_eq!(pal.lrps[0].dnp, Some((234, 292)));
+        assert_eq!(pal.pos_offset, Some((117, 146)));
+        assert!(pal.lrps[1].is_last);
+
+        // the offset flag is set but the offset byte is missing
+        assert!(PointAlongLineLocationReference::from_binary(&bytes[..16].to_vec()).is_err());
+    }
+
+    #[test]
+    fn test_to_binary() {
+        let bytes: Vec<u8> = vec![
+            0x2b, 0xff, 0xb3, 0x18, 0x26, 0x49, 0x83, 0x6b, 0xaf, 0x04, 0x00, 0x21, 0xff, 0x1f,
+            0xab, 0x5f, 0x80,
+        ];
+        let pal = PointAlongLineLocationReference::from_binary(&bytes).unwrap();
+        assert_eq!(pal.to_binary().unwrap(), bytes);
+    }
+}
@@ -0,0 +1,119 @@
+use crate::binary_header::BinaryHeader;
+use crate::common::{find_edges_in_area, get_next_coordinate, int2deg};
+use crate::decodable_reference::DecodableReference;
+use crate::decoding_parameters::DecodingParameters;
+use crate::deserializable_reference::DeserializableReference;
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::location::Location;
+use crate::request_context::RequestContext;
+use async_trait::async_trait;
+use geo::{Coord, LineString, Polygon};
+use serde::Serialize;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// OpenLR Polygon                                                     //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+#[derive(Debug, Serialize)]
+pub struct PolygonLocationReference {
+    pub(crate) header: BinaryHeader,
+    /// Corners as (longitude, latitude) pairs
+    pub corners: Vec<(f64, f64)>,
+}
+
+#[async_trait]
+impl DecodableReference for PolygonLocationReference {
+    type Peer = PolygonLocation;
+    async fn decode(
+        &self,
+        context: &RequestContext<DecodingParameters>,
+    ) -> Result<Location, OpenLrErr> {
+        let mut polygon = PolygonLocation {
+            corners: self.corners.clone(),
+            edges: None,
+        };
+        polygon.edges = find_edges_in_area(polygon.get_polygon(), context).await?;
+        Ok(Location::Polygon(polygon))
+    }
+}
+
+impl DeserializableReference for PolygonLocationReference {
+    type T = PolygonLocationReference;
+    fn from_binary(bytes: &Vec<u8>) -> Result<Self::T, OpenLrErr> {
+        // header (1) + first corner (6) + at least two relative corners (4 each)
+        let len = bytes.len();
+        if len < 15 || !(len - 7).is_multiple_of(4) {
+            return Err(OpenLrErr::InvalidBinaryStringLength(len));
+        }
+
+        // Each subsequent corner is relative to the one preceding it
+        let mut corners = vec![(
+            int2deg(bytes[1], bytes[2], bytes[3]),
+            int2deg(bytes[4], bytes[5], bytes[6]),
+        )];
+        for chunk in bytes[7..].chunks(4) {
+            let (prev_lon, prev_lat) = corners[corners.len() - 1];
+            corners.push((
+                get_next_coordinate(chunk[0], chunk[1], prev_lon),
+                get_next_coordinate(chunk[2], chunk[3], prev_lat),
+            ));
+        }
+
+        Ok(PolygonLocationReference {
+            header: BinaryHeader::new(bytes[0]),
+            corners,
+        })
+    }
+
+    fn from_xml(_xml: &str) -> Result<Self::T, OpenLrErr> {
+        Err(OpenLrErr::UnsupportedLocationTypeError(
+            "Polygon in XML".to_owned(),
+        ))
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct PolygonLocation {
+    /// Corners as (longitude, latitude) pairs
+    pub corners: Vec<(f64, f64)>,
+    pub edges: Option<Vec<Edge>>,
+}
+
+impl PolygonLocation {
+    /// Returns the polygon described by the corners. The exterior ring is
+    /// closed implicitly.
+    pub fn get_polygon(&self) -> Polygon {
+        let ring = self
+            .corners
+            .iter()
+            .map(|(x, y)| Coord { x: *x, y: *y })
+            .collect::<Vec<Coord>>();
+        Polygon::new(LineString::new(ring), vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_from_binary() {
+        let bytes: Vec<u8> = vec![
+            0x13, 0x04, 0x5b, 0x5b, 0x23, 0x46, 0xf5, 0x03, 0xe8, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0xf4,
+        ];
+        let poly = PolygonLocationReference::from_binary(&bytes).unwrap();
+        assert_eq!(poly.corners.len(), 3);
+        assert_relative_eq!(poly.corners[0].0, 6.12683, epsilon = 1e-4);
+        assert_relative_eq!(poly.corners[0].1, 49.60851, epsilon = 1e-4);
+        assert_relative_eq!(poly.corners[1].0, poly.corners[0].0 + 0.01);
+        assert_relative_eq!(poly.corners[2].1, poly.corners[0].1 + 0.005);
+
+        assert!(PolygonLocationReference::from_binary(&bytes[..14].to_vec()).is_err());
+        assert!(PolygonLocationReference::from_binary(&bytes[..11].to_vec()).is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use crate::binary_header::BinaryHeader;
+use crate::common::{find_edges_in_area, get_next_coordinate, int2deg};
+use crate::decodable_reference::DecodableReference;
+use crate::decoding_parameters::DecodingParameters;
+use crate::deserializable_reference::DeserializableReference;
+use crate::edge::Edge;
+use crate::errors::OpenLrErr;
+use crate::location::Location;
+use crate::request_context::RequestContext;
+use async_trait::async_trait;
+use geo::{Coord, Polygon, Rect};
+use serde::Serialize;
+
+//--------------------------------------------------------------------//
+//                                                                    //
+// OpenLR Rectangle                                                   //
+//                                                                    //
+//--------------------------------------------------------------------//
+
+#[derive(Debug, Serialize)]
+pub struct RectangleLocationReference {
+    pub(crate) header: BinaryHeader,
+    pub lower_left_longitude: f64,
+    pub lower_left_latitude: f64,
+    pub upper_right_longitude: f64,
+    pub upper_right_latitude: f64,
+}
+
+// Parse the lower-left and upper-right corners of a rectangle. The lower-left
+// corner is always absolute, while the upper-right corner is either absolute (12
+// bytes in total) or relative to the lower-left corner (10 bytes in total).
+pub(crate) fn parse_corners(ba: &[u8]) -> (f64, f64, f64, f64) {
+    let lower_left_longitude = int2deg(ba[0], ba[1], ba[2]);
+    let lower_left_latitude = int2deg(ba[3], ba[4], ba[5]);
+    if ba.len() == 12 {
+        (
+            lower_left_longitude,
+            lower_left_latitude,
+            int2deg(ba[6], ba[7], ba[8]),
+            int2deg(ba[9], ba[10], ba[11]),
+        )
+    } else {
+        (
+            lower_left_longitude,
+            lower_left_latitude,
+            get_next_coordinate(ba[6], ba[7], lower_left_longitude),
+            get_next_coordinate(ba[8], ba[9], lower_left_latitude),
+        )
+    }
+}
+
+#[async_trait]
+impl DecodableReference for RectangleLocationReference {
+    type Peer = RectangleLocation;
+    async fn decode(
+        &self,
+        context: &RequestContext<DecodingParameters>,
+    ) -> Result<Location, OpenLrErr> {
+        let mut rectangle = RectangleLocation {
+            lower_left_longitude: self.lower_left_longitude,
+            lower_left_latitude: self.lower_left_latitude,
+            upper_right_longitude: self.upper_right_longitude,
+            upper_right_latitude: self.upper_right_latitude,
+            edges: None,
+        };
+        rectangle.edges = find_edges_in_area(rectangle.get_polygon(), context).await?;
+        Ok(Location::Rectangle(rectangle))
+    }
+}
+
+impl DeserializableReference for RectangleLocationReference {
+    type T = RectangleLocationReference;
+    fn from_binary(bytes: &Vec<u8>) -> Result<Self::T, OpenLrErr> {
+        // header (1) + lower-left (6) + upper-right (4 if relative, 6 if absolute)
+        if bytes.len() != 11 && bytes.len() != 13 {
+            return Err(OpenLrErr::InvalidBinaryStringLength(bytes.len()));
+        }
+
+        let (lower_left_longitude, lower_left_latitude, upper_right_longitude, upper_right_latitude) =
+            parse_corners(&bytes[1..]);
+
+        Ok(RectangleLocationReference {
+            header: BinaryHeader::new(bytes[0]),
+            lower_left_longitude,
+            lower_left_latitude,
+            upper_right_longitude,
+            upper_right_latitude,
+        })
+    }
+
+    fn from_xml(_xml: &str) -> Result<Self::T, OpenLrErr> {
+        Err(OpenLrErr::UnsupportedLocationTypeError(
+            "Rectangle in XML".to_owned(),
+        ))
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct RectangleLocation {
+    pub lower_left_longitude: f64,
+    pub lower_left_latitude: f64,
+    pub upper_right_longitude: f64,
+    pub upper_right_latitude: f64,
+    pub edges: Option<Vec<Edge>>,
+}
+
+impl RectangleLocation {
+    /// Returns the rectangle as a polygon
+    pub fn get_polygon(&self) -> Polygon {
+        Rect::new(
+            Coord {
+                x: self.lower_left_longitude,
+                y: self.lower_left_latitude,
+            },
+            Coord {
+                x: self.upper_right_longitude,
+                y: self.upper_right_latitude,
+            },
+        )
+        .to_polygon()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn test_from_binary() {
+        // upper-right corner relative to lower-left corner
+        let bytes: Vec<u8> = vec![
+            0x43, 0x04, 0x5b, 0x5b, 0x23, 0x46, 0xf5, 0x03, 0xe8, 0x01, 0xf4,
+        ];
+        let rect = RectangleLocationReference::from_binary(&bytes).unwrap();
+        assert_relative_eq!(rect.lower_left_longitude, 6.12683, epsilon = 1e-4);
+        assert_relative_eq!(rect.lower_left_latitude, 49.60851, epsilon = 1e-4);
+        assert_relative_eq!(rect.upper_right_longitude, rect.lower_left_longitude + 0.01);
+        assert_relative_eq!(rect.upper_right_latitude, rect.lower_left_latitude + 0.005);
+
+        // upper-right corner absolute
+        let bytes: Vec<u8> = vec![
+            0x43, 0x04, 0x5b, 0x5b, 0x23, 0x46, 0xf5, 0x04, 0x5b, 0x5b, 0x23, 0x46, 0xf5,
+        ];
+        let rect = RectangleLocationReference::from_binary(&bytes).unwrap();
+        assert_relative_eq!(rect.upper_right_longitude, rect.lower_left_longitude);
+        assert_relative_eq!(rect.upper_right_latitude, rect.lower_left_latitude);
+
+        assert!(RectangleLocationReference::from_binary(&bytes[..12].to_vec()).is_err());
+    }
+}
@@ -92,6 +92,20 @@ impl<'a, ParamType> RequestContext<'a, ParamType> {
         Arc::clone(&self.log).lock().unwrap().clone()
     }
 
+    /// Returns a context for the same request, sharing its parameters and log, which
+    /// queries another map
+    pub fn with_map<'b>(&self, map_server: &'b dyn Map) -> RequestContext<'b, ParamType>
+    where
+        'a: 'b,
+    {
+        RequestContext {
+            map_server,
+            params: self.params,
+            log: Arc::clone(&self.log),
+            log_level: self.log_level,
+        }
+    }
+
     pub fn new(
         map_server: &'a dyn Map,
         params: &'a ParamType,
@@ -0,0 +1,49 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub enum SideOfRoad {
+    OnRoadOrUnknown = 0,
+    Right = 1,
+    Left = 2,
+    Both = 3,
+}
+
+impl Copy for SideOfRoad {}
+
+impl SideOfRoad {
+    #[allow(dead_code)]
+    pub fn to_usize(&self) -> usize {
+        match self {
+            SideOfRoad::OnRoadOrUnknown => 0,
+            SideOfRoad::Right => 1,
+            SideOfRoad::Left => 2,
+            SideOfRoad::Both => 3,
+        }
+    }
+    pub fn from_u8(i: u8) -> Self {
+        match i {
+            0 => SideOfRoad::OnRoadOrUnknown,
+            1 => SideOfRoad::Right,
+            2 => SideOfRoad::Left,
+            3 => SideOfRoad::Both,
+            _ => unreachable!(),
+        }
+    }
+    pub fn to_xml(&self) -> &'static str {
+        match self {
+            SideOfRoad::OnRoadOrUnknown => "ON_ROAD_OR_UNKNOWN",
+            SideOfRoad::Right => "RIGHT",
+            SideOfRoad::Left => "LEFT",
+            SideOfRoad::Both => "BOTH",
+        }
+    }
+    pub fn from_xml(s: &str) -> Option<Self> {
+        match s {
+            "ON_ROAD_OR_UNKNOWN" => Some(SideOfRoad::OnRoadOrUnknown),
+            "RIGHT" => Some(SideOfRoad::Right),
+            "LEFT" => Some(SideOfRoad::Left),
+            "BOTH" => Some(SideOfRoad::Both),
+            _ => None,
+        }
+    }
+}
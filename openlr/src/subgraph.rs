@@ -0,0 +1,20 @@
+use crate::in_memory_map::MapEdge;
+use crate::turn_restriction::TurnRestriction;
+
+/// The part of a map within an area: the lines which intersect it, along with the nodes
+/// at which they start and end, and the turn restrictions among them.  A decoder can fetch
+/// the subgraph around a location reference at once, and then search it locally.
+#[derive(Debug, Clone, Default)]
+pub struct Subgraph {
+    pub edges: Vec<MapEdge>,
+    pub turn_restrictions: Vec<TurnRestriction>,
+}
+
+impl Subgraph {
+    pub fn new(edges: Vec<MapEdge>, turn_restrictions: Vec<TurnRestriction>) -> Self {
+        Subgraph {
+            edges,
+            turn_restrictions,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+/// A turn restriction: a sequence of two or more connected lines which may not be
+/// travelled in succession.  A restriction of two lines forbids the turn from the
+/// first onto the second, while longer restrictions forbid manoeuvres through
+/// the lines between them (e.g. "no U-turn through the dual carriageway").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurnRestriction {
+    pub edges: Vec<i64>,
+}
+
+impl TurnRestriction {
+    pub fn new(edges: Vec<i64>) -> Self {
+        TurnRestriction { edges }
+    }
+
+    /// Returns the id of the line on which the restricted manoeuvre begins
+    pub fn get_from(&self) -> Option<i64> {
+        self.edges.first().copied()
+    }
+}
@@ -0,0 +1,114 @@
+use std::str::FromStr;
+
+use roxmltree::{Document, Node};
+
+use crate::errors::OpenLrErr;
+use crate::fow::FOW;
+use crate::frc::FRC;
+use crate::orientation::Orientation;
+use crate::side_of_road::SideOfRoad;
+
+// Helpers for reading and writing the OpenLR XML physical format (TomTom schema):
+//
+//   <OpenLR xmlns="http://www.openlr.org/openlr">
+//     <LocationID/>
+//     <XMLLocationReference>
+//       <LineLocationReference> ... </LineLocationReference>
+//     </XMLLocationReference>
+//   </OpenLR>
+
+pub(crate) const OPENLR_NAMESPACE: &str = "http://www.openlr.org/openlr";
+
+pub(crate) fn parse(xml: &str) -> Result<Document<'_>, OpenLrErr> {
+    Document::parse(xml).map_err(|e| OpenLrErr::XmlParseError(e.to_string()))
+}
+
+// Return the element describing the location reference, i.e. the single child of
+// <XMLLocationReference>
+pub(crate) fn location_reference_node<'a, 'input>(
+    doc: &'a Document<'input>,
+) -> Result<Node<'a, 'input>, OpenLrErr> {
+    let root = doc.root_element();
+    if root.tag_name().name() != "OpenLR" {
+        return Err(OpenLrErr::XmlParseError(format!(
+            "Unexpected root element <{}>",
+            root.tag_name().name()
+        )));
+    }
+    child(child(root, "XMLLocationReference")?, "*")
+}
+
+// Return the first child element with the given name ("*" matches any element)
+pub(crate) fn child<'a, 'input>(
+    node: Node<'a, 'input>,
+    name: &str,
+) -> Result<Node<'a, 'input>, OpenLrErr> {
+    node.children()
+        .find(|n| n.is_element() && (name == "*" || n.tag_name().name() == name))
+        .ok_or_else(|| {
+            OpenLrErr::XmlParseError(format!(
+                "Missing element <{}> in <{}>",
+                name,
+                node.tag_name().name()
+            ))
+        })
+}
+
+pub(crate) fn child_value<T: FromStr>(node: Node, name: &str) -> Result<T, OpenLrErr> {
+    let text = child(node, name)?.text().unwrap_or_default().trim();
+    text.parse::<T>().map_err(|_| {
+        OpenLrErr::XmlParseError(format!("Invalid value for <{}>: '{}'", name, text))
+    })
+}
+
+// Return the exact offset in meters held by the given child element, if there is one.  An
+// offset of zero is no offset at all.
+pub(crate) fn child_offset(node: Node, name: &str) -> Result<Option<(u32, u32)>, OpenLrErr> {
+    match child(node, name) {
+        Ok(_) => Ok(Some(child_value::<u32>(node, name)?)
+            .filter(|o| *o > 0)
+            .map(|o| (o, o))),
+        Err(_) => Ok(None),
+    }
+}
+
+pub(crate) fn child_frc(node: Node, name: &str) -> Result<FRC, OpenLrErr> {
+    let text = child(node, name)?.text().unwrap_or_default().trim();
+    FRC::from_xml(text)
+        .ok_or_else(|| OpenLrErr::XmlParseError(format!("Invalid FRC for <{}>: '{}'", name, text)))
+}
+
+pub(crate) fn child_fow(node: Node, name: &str) -> Result<FOW, OpenLrErr> {
+    let text = child(node, name)?.text().unwrap_or_default().trim();
+    FOW::from_xml(text)
+        .ok_or_else(|| OpenLrErr::XmlParseError(format!("Invalid FOW for <{}>: '{}'", name, text)))
+}
+
+pub(crate) fn child_side_of_road(node: Node, name: &str) -> Result<SideOfRoad, OpenLrErr> {
+    let text = child(node, name)?.text().unwrap_or_default().trim();
+    SideOfRoad::from_xml(text).ok_or_else(|| {
+        OpenLrErr::XmlParseError(format!("Invalid side of road for <{}>: '{}'", name, text))
+    })
+}
+
+pub(crate) fn child_orientation(node: Node, name: &str) -> Result<Orientation, OpenLrErr> {
+    let text = child(node, name)?.text().unwrap_or_default().trim();
+    Orientation::from_xml(text).ok_or_else(|| {
+        OpenLrErr::XmlParseError(format!("Invalid orientation for <{}>: '{}'", name, text))
+    })
+}
+
+// Wrap the XML of a location reference in the OpenLR document envelope
+pub(crate) fn to_document(location_reference: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         <OpenLR xmlns=\"{}\"><LocationID/><XMLLocationReference>{}</XMLLocationReference></OpenLR>",
+        OPENLR_NAMESPACE, location_reference
+    )
+}
+
+// Binary location references store ranges rather than exact values, in which
+// case the middle of the range is the best available scalar
+pub(crate) fn midpoint(range: (u32, u32)) -> u32 {
+    (range.0 + range.1) / 2
+}
@@ -3,12 +3,15 @@ use mock_map_agent::mock_map::MockMap;
 use futures::executor::block_on;
 use openlr;
 use openlr::decoding_parameters::DecodingParameters;
+use openlr::errors::OpenLrErr;
 use openlr::location::Location;
 use openlr::log::LogLevel;
+use openlr::orientation::Orientation;
+use openlr::side_of_road::SideOfRoad;
 
 #[test]
 fn test_decode1() {
-    let map = MockMap::new_from_csv("test_data/test1.csv");
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
 
     let loc_ref = openlr::deserialize_binary("C/+zGCZJgyuvBAAh/x8rHw==").unwrap();
     let mut params = DecodingParameters::default();
@@ -35,7 +38,7 @@ fn test_decode1() {
 
 #[test]
 fn test_decode2() {
-    let map = MockMap::new_from_csv("test_data/test2.csv");
+    let map = MockMap::new_from_csv("test_data/test2.csv").unwrap();
 
     let loc_ref = openlr::deserialize_binary("C/5kUCVBsjPVAv8f/+QzBw==").unwrap();
     let loc = block_on(openlr::decode(
@@ -65,7 +68,7 @@ fn test_decode3() {
         std::env::current_dir()
     }
     println!("{:?}", get_current_working_dir());
-    let map = MockMap::new_from_csv("./test_data/test3.csv");
+    let map = MockMap::new_from_csv("./test_data/test3.csv").unwrap();
 
     let loc_ref = openlr::deserialize_binary("C/4bnSaa4yu5Af91ACAruQT+r/+9Kwc=").unwrap();
     let loc = block_on(openlr::decode(
@@ -100,7 +103,7 @@ fn test_decode4() {
         std::env::current_dir()
     }
     println!("{:?}", get_current_working_dir());
-    let map = MockMap::new_from_csv("test_data/test4.csv");
+    let map = MockMap::new_from_csv("test_data/test4.csv").unwrap();
 
     let loc_ref = openlr::deserialize_binary("C/7VOCaEbSu/BP+5AMUrbJEQ").unwrap();
     println!("{:?}", loc_ref);
@@ -135,13 +138,29 @@ fn test_serialize_line_loc_ref() {
     println!("{:?}", serde_json::to_string(&loc_ref).unwrap())
 }
 
+#[test]
+fn test_serialize_line_round_trip() {
+    for code in [
+        "C/+zGCZJgyuvBAAh/x8rHw==",
+        "C/5kUCVBsjPVAv8f/+QzBw==",
+        "C/4bnSaa4yu5Af91ACAruQT+r/+9Kwc=",
+        "C/7VOCaEbSu/BP+5AMUrbJEQ",
+    ] {
+        let loc_ref = openlr::deserialize_binary(code).unwrap();
+        assert_eq!(openlr::serialize_binary(&loc_ref).unwrap(), code);
+    }
+
+    let loc_ref = openlr::deserialize_binary("I/+zGCZJgw==").unwrap();
+    assert!(openlr::serialize_binary(&loc_ref).is_err());
+}
+
 #[test]
 fn test_both() {
     fn get_current_working_dir() -> std::io::Result<PathBuf> {
         std::env::current_dir()
     }
     println!("{:?}", get_current_working_dir());
-    let map = MockMap::new_from_csv("test_data/test4.csv");
+    let map = MockMap::new_from_csv("test_data/test4.csv").unwrap();
     let loc = block_on(openlr::decode_binary(
         "C/7VOCaEbSu/BP+5AMUrbJEQ",
         1,
@@ -166,3 +185,282 @@ fn test_both() {
         }
     }
 }
+
+#[test]
+fn test_decode_point_along_line() {
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+
+    let loc_ref = openlr::deserialize_binary("K/+zGCZJg2uvBAAh/x9rX4A=").unwrap();
+    let params = DecodingParameters {
+        bearing_weight: 0.35,
+        fow_weight: 0.2,
+        frc_weight: 0.1,
+        distance_weight: 0.35,
+        ..Default::default()
+    };
+    let loc = block_on(openlr::decode(1, &loc_ref, &map, &params, LogLevel::Debug));
+    println!("{:?}", loc);
+    assert!(loc.result.is_ok());
+    match loc.result {
+        Ok(Location::PointAlongLine(p)) => {
+            assert_eq!(p.edges.len(), 2);
+            assert_eq!(p.p_off, Some((117, 146)));
+            assert_eq!(p.side_of_road, SideOfRoad::Right);
+            assert_eq!(p.orientation, Orientation::FirstToSecond);
+            assert_eq!(p.edges[0].id, 8548148);
+            assert_eq!(p.edges[1].id, 6882819);
+        }
+        _ => {
+            assert_eq!(1, 0);
+        }
+    }
+}
+
+#[test]
+fn test_decode_poi_with_access_point() {
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+
+    let loc_ref = openlr::deserialize_binary("K/+zGCZJg2uvBAAh/x9rX4AAZP84").unwrap();
+    let params = DecodingParameters {
+        bearing_weight: 0.35,
+        fow_weight: 0.2,
+        frc_weight: 0.1,
+        distance_weight: 0.35,
+        ..Default::default()
+    };
+    let loc = block_on(openlr::decode(1, &loc_ref, &map, &params, LogLevel::Debug));
+    println!("{:?}", loc);
+    assert!(loc.result.is_ok());
+    match loc.result {
+        Ok(Location::PoiWithAccessPoint(p)) => {
+            assert_eq!(p.access_point.edges.len(), 2);
+            assert_eq!(p.access_point.p_off, Some((117, 146)));
+            assert_eq!(p.access_point.edges[0].id, 8548148);
+            assert!(p.poi_longitude > -0.4215 && p.poi_longitude < -0.4214);
+            assert!(p.poi_latitude > 53.8392 && p.poi_latitude < 53.8394);
+        }
+        _ => {
+            assert_eq!(1, 0);
+        }
+    }
+}
+
+#[test]
+fn test_decode_geo_coordinate() {
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+
+    // without map matching, the coordinate is returned unchanged
+    let loc_ref = openlr::deserialize_binary("I/+zGCZJgw==").unwrap();
+    let mut params = DecodingParameters::default();
+    let loc = block_on(openlr::decode(1, &loc_ref, &map, &params, LogLevel::Debug));
+    match loc.result {
+        Ok(Location::GeoCoordinate(g)) => {
+            assert!(g.longitude > -0.4225 && g.longitude < -0.4224);
+            assert!(g.latitude > 53.8412 && g.latitude < 53.8414);
+            assert!(g.edge.is_none());
+            assert!(g.offset.is_none());
+        }
+        _ => {
+            assert_eq!(1, 0);
+        }
+    }
+
+    // with map matching, the coordinate is snapped to the nearest edge
+    params.map_match_geo_coordinates = true;
+    let loc = block_on(openlr::decode(1, &loc_ref, &map, &params, LogLevel::Debug));
+    match loc.result {
+        Ok(Location::GeoCoordinate(g)) => {
+            assert!(g.edge.is_some());
+            assert!(g.offset.is_some());
+        }
+        _ => {
+            assert_eq!(1, 0);
+        }
+    }
+}
+
+#[test]
+fn test_decode_circle() {
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+
+    // without an area search, only the circle itself is returned
+    let loc_ref = openlr::deserialize_binary("A/+zGCZJg2Q=").unwrap();
+    let mut params = DecodingParameters::default();
+    let loc = block_on(openlr::decode(1, &loc_ref, &map, &params, LogLevel::Debug));
+    match loc.result {
+        Ok(Location::Circle(c)) => {
+            assert!(c.longitude > -0.4225 && c.longitude < -0.4224);
+            assert!(c.latitude > 53.8412 && c.latitude < 53.8414);
+            assert_eq!(c.radius, 100);
+            assert!(c.edges.is_none());
+        }
+        _ => {
+            assert_eq!(1, 0);
+        }
+    }
+
+    // with an area search, the edges intersecting the circle are returned as well
+    params.return_area_edges = true;
+    let loc = block_on(openlr::decode(1, &loc_ref, &map, &params, LogLevel::Debug));
+    match loc.result {
+        Ok(Location::Circle(c)) => {
+            assert!(!c.edges.unwrap().is_empty());
+        }
+        _ => {
+            assert_eq!(1, 0);
+        }
+    }
+}
+
+#[test]
+fn test_decode_closed_line() {
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+
+    let loc_ref = openlr::deserialize_binary("W/+zUyZJJD/2AT8F").unwrap();
+    let params = DecodingParameters::default();
+    let loc = block_on(openlr::decode(1, &loc_ref, &map, &params, LogLevel::Debug));
+    println!("{:?}", loc);
+    match loc.result {
+        Ok(Location::ClosedLine(cl)) => {
+            assert_eq!(
+                cl.edges.iter().map(|e| e.id).collect::<Vec<i64>>(),
+                vec![280158, 2529211, 2490246]
+            );
+        }
+        _ => {
+            assert_eq!(1, 0);
+        }
+    }
+}
+
+#[test]
+fn test_decode_xml() {
+    let map = MockMap::new_from_csv("test_data/test4.csv").unwrap();
+
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+    <OpenLR xmlns="http://www.openlr.org/openlr">
+        <LocationID>1</LocationID>
+        <XMLLocationReference>
+            <LineLocationReference>
+                <LocationReferencePoint>
+                    <Coordinates>
+                        <Longitude>-1.641244</Longitude>
+                        <Latitude>54.164926</Latitude>
+                    </Coordinates>
+                    <LineAttributes>
+                        <FRC>FRC5</FRC>
+                        <FOW>SINGLE_CARRIAGEWAY</FOW>
+                        <BEAR>354</BEAR>
+                    </LineAttributes>
+                    <PathAttributes>
+                        <LFRCNP>FRC5</LFRCNP>
+                        <DNP>263</DNP>
+                    </PathAttributes>
+                </LocationReferencePoint>
+                <LastLocationReferencePoint>
+                    <Coordinates>
+                        <Longitude>-1.641954</Longitude>
+                        <Latitude>54.166896</Latitude>
+                    </Coordinates>
+                    <LineAttributes>
+                        <FRC>FRC5</FRC>
+                        <FOW>SINGLE_CARRIAGEWAY</FOW>
+                        <BEAR>141</BEAR>
+                    </LineAttributes>
+                </LastLocationReferencePoint>
+                <Offsets>
+                    <PosOff>140</PosOff>
+                    <NegOff>14</NegOff>
+                </Offsets>
+            </LineLocationReference>
+        </XMLLocationReference>
+    </OpenLR>"#;
+    let loc_ref = openlr::deserialize_xml(xml).unwrap();
+    let loc = block_on(openlr::decode(
+        1,
+        &loc_ref,
+        &map,
+        &DecodingParameters::default(),
+        LogLevel::Debug,
+    ));
+    println!("{:?}", loc);
+    match loc.result {
+        Ok(Location::Line(l)) => {
+            assert_eq!(
+                l.edges.iter().map(|e| e.id).collect::<Vec<i64>>(),
+                vec![11384678, 3551999, 12007589, 13042191]
+            );
+            // exact offsets are preserved rather than widened into binary buckets
+            assert_eq!(l.p_off, Some((39, 39)));
+            assert_eq!(l.n_off, Some((15, 15)));
+        }
+        _ => {
+            assert_eq!(1, 0);
+        }
+    }
+
+    // a missing offset is no offset, but one which is not a distance is an error
+    let loc_ref = openlr::deserialize_xml(&xml.replace("<PosOff>140</PosOff>", "")).unwrap();
+    let xml_without_offset = openlr::serialize_xml(&loc_ref).unwrap();
+    assert!(!xml_without_offset.contains("<PosOff>"));
+    assert!(xml_without_offset.contains("<NegOff>14</NegOff>"));
+    for (offset, invalid) in [
+        ("<PosOff>140</PosOff>", "<PosOff>abc</PosOff>"),
+        ("<NegOff>14</NegOff>", "<NegOff>-5</NegOff>"),
+    ] {
+        assert!(matches!(
+            openlr::deserialize_xml(&xml.replace(offset, invalid)),
+            Err(OpenLrErr::XmlParseError(_))
+        ));
+    }
+
+    assert!(openlr::deserialize_xml("<OpenLR><XMLLocationReference/></OpenLR>").is_err());
+    assert!(openlr::deserialize_xml("not xml").is_err());
+}
+
+#[test]
+fn test_serialize_line_xml_round_trip() {
+    for code in [
+        "C/+zGCZJgyuvBAAh/x8rHw==",
+        "C/5kUCVBsjPVAv8f/+QzBw==",
+        "C/4bnSaa4yu5Af91ACAruQT+r/+9Kwc=",
+        "C/7VOCaEbSu/BP+5AMUrbJEQ",
+    ] {
+        let loc_ref = openlr::deserialize_binary(code).unwrap();
+        let xml = openlr::serialize_xml(&loc_ref).unwrap();
+        let loc_ref = openlr::deserialize_xml(&xml).unwrap();
+        assert_eq!(openlr::serialize_binary(&loc_ref).unwrap(), code);
+    }
+}
+
+#[test]
+fn test_decode_line_geometry() {
+    let map = MockMap::new_from_csv("test_data/test4.csv").unwrap();
+
+    let loc_ref = openlr::deserialize_binary("C/7VOCaEbSu/BP+5AMUrbJEQ").unwrap();
+    let loc = block_on(openlr::decode(
+        1,
+        &loc_ref,
+        &map,
+        &DecodingParameters::default(),
+        LogLevel::Debug,
+    ));
+    match loc.result {
+        Ok(Location::Line(l)) => {
+            let edges_length: u32 = l.edges.iter().map(|e| e.len).sum();
+            assert_eq!(l.get_length(), edges_length - 31 - 15);
+
+            // the geometry starts and ends at the offsets rather than at the nodes
+            let geom = l.get_geometry();
+            let first = l.edges.first().unwrap();
+            let last = l.edges.last().unwrap();
+            assert_eq!(geom.0.first().unwrap(), &first.get_point_along_line(31).0);
+            assert_eq!(geom.0.last().unwrap(), &last.get_point_along_line(last.len - 15).0);
+
+            let json: serde_json::Value = serde_json::to_value(&l).unwrap();
+            assert_eq!(json["length"], edges_length - 46);
+            assert_eq!(json["geom"].as_array().unwrap().len(), geom.0.len());
+        }
+        r => panic!("Unexpected decoding result: {:?}", r),
+    }
+}
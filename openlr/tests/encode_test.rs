@@ -0,0 +1,341 @@
+use async_trait::async_trait;
+use futures::executor::block_on;
+use geo::Coord;
+use mock_map_agent::mock_map::MockMap;
+use openlr::decoding_parameters::DecodingParameters;
+use openlr::edge::Edge;
+use openlr::encoding_parameters::EncodingParameters;
+use openlr::errors::OpenLrErr;
+use openlr::line::LineLocation;
+use openlr::location::Location;
+use openlr::log::LogLevel;
+use openlr::map::Map;
+use openlr::orientation::Orientation;
+use openlr::point_along_line::PointAlongLineLocation;
+use openlr::side_of_road::SideOfRoad;
+
+fn decode_line(code: &str, map: &MockMap) -> LineLocation {
+    match block_on(openlr::decode_binary(
+        code,
+        1,
+        map,
+        &DecodingParameters::default(),
+        LogLevel::Debug,
+    ))
+    .result
+    {
+        Ok(Location::Line(l)) => l,
+        r => panic!("Unexpected decoding result: {:?}", r),
+    }
+}
+
+fn edge_ids(line: &LineLocation) -> Vec<i64> {
+    line.edges.iter().map(|e| e.id).collect::<Vec<i64>>()
+}
+
+#[test]
+fn test_encode_line_round_trip() {
+    let map = MockMap::new_from_csv("test_data/test4.csv").unwrap();
+    let line = decode_line("C/7VOCaEbSu/BP+5AMUrbJEQ", &map);
+
+    let loc_ref = block_on(openlr::encode(
+        &Location::Line(line),
+        &map,
+        &EncodingParameters::default(),
+        LogLevel::Debug,
+    ))
+    .unwrap();
+
+    // the encoded reference survives the trip through the binary format
+    let code = openlr::serialize_binary(&loc_ref).unwrap();
+    let decoded = decode_line(&code, &map);
+    assert_eq!(
+        edge_ids(&decoded),
+        vec![11384678, 3551999, 12007589, 13042191]
+    );
+    assert!(decoded.p_off.unwrap().0 <= 31 && decoded.p_off.unwrap().1 >= 31);
+    assert!(decoded.n_off.unwrap().0 <= 15 && decoded.n_off.unwrap().1 >= 15);
+
+    for (code, csv) in [
+        ("C/+zGCZJgyuvBAAh/x8rHw==", "test_data/test1.csv"),
+        ("C/5kUCVBsjPVAv8f/+QzBw==", "test_data/test2.csv"),
+        ("C/4bnSaa4yu5Af91ACAruQT+r/+9Kwc=", "test_data/test3.csv"),
+    ] {
+        let map = MockMap::new_from_csv(csv).unwrap();
+        let line = decode_line(code, &map);
+        let expected = edge_ids(&line);
+        let loc_ref = block_on(openlr::encode(
+            &Location::Line(line),
+            &map,
+            &EncodingParameters::default(),
+            LogLevel::Debug,
+        ))
+        .unwrap();
+        let decoded = decode_line(&openlr::serialize_binary(&loc_ref).unwrap(), &map);
+        assert_eq!(edge_ids(&decoded), expected);
+    }
+}
+
+#[test]
+fn test_encode_line_intermediate_lrps() {
+    let map = MockMap::new_from_csv("test_data/test4.csv").unwrap();
+    let line = decode_line("C/7VOCaEbSu/BP+5AMUrbJEQ", &map);
+    let expected = edge_ids(&line);
+
+    // a small maximum DNP forces intermediate LRPs
+    let params = EncodingParameters {
+        max_dnp: 100,
+        ..Default::default()
+    };
+    let loc_ref = block_on(openlr::encode(
+        &Location::Line(line),
+        &map,
+        &params,
+        LogLevel::Debug,
+    ))
+    .unwrap();
+    let xml = openlr::serialize_xml(&loc_ref).unwrap();
+    assert!(xml.matches("<LocationReferencePoint>").count() > 1);
+
+    let decoded = decode_line(&openlr::serialize_binary(&loc_ref).unwrap(), &map);
+    assert_eq!(edge_ids(&decoded), expected);
+}
+
+#[test]
+fn test_encode_line_errors() {
+    let map = MockMap::new_from_csv("test_data/test4.csv").unwrap();
+    let line = decode_line("C/7VOCaEbSu/BP+5AMUrbJEQ", &map);
+
+    // edges which do not follow one another
+    let mut edges = line.edges.clone();
+    edges.swap(0, 1);
+    let disconnected = LineLocation {
+        edges,
+        p_off: None,
+        n_off: None,
+    };
+    match block_on(openlr::encode(
+        &Location::Line(disconnected),
+        &map,
+        &EncodingParameters::default(),
+        LogLevel::Debug,
+    )) {
+        Err(OpenLrErr::LocationNotConnected(a, b)) => {
+            assert_eq!((a, b), (3551999, 11384678));
+        }
+        r => panic!("Unexpected encoding result: {:?}", r),
+    }
+
+    // offsets covering the whole location
+    let length: u32 = line.edges.iter().map(|e| e.len).sum();
+    let covered = LineLocation {
+        edges: line.edges.clone(),
+        p_off: Some((length / 2, length / 2)),
+        n_off: Some((length / 2, length / 2)),
+    };
+    assert!(block_on(openlr::encode(
+        &Location::Line(covered),
+        &map,
+        &EncodingParameters::default(),
+        LogLevel::Debug,
+    ))
+    .is_err());
+
+    let empty = LineLocation {
+        edges: vec![],
+        p_off: None,
+        n_off: None,
+    };
+    assert!(block_on(openlr::encode(
+        &Location::Line(empty),
+        &map,
+        &EncodingParameters::default(),
+        LogLevel::Debug,
+    ))
+    .is_err());
+}
+
+#[test]
+fn test_encode_line_valid_nodes() {
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+
+    // a line starting with edge 2566542 starts in the middle of the chain of edges
+    // 199640, 3193167 and 2566542, so the first LRP is moved back to the start of the
+    // chain, and the positive offset grows by the length of the edges added
+    let edge = block_on(map.get_next_edges(3193167, String::new()))
+        .unwrap()
+        .into_iter()
+        .find(|e| e.id == 2566542)
+        .unwrap();
+    let line = LineLocation {
+        edges: vec![edge],
+        p_off: Some((60, 60)),
+        n_off: None,
+    };
+    let loc_ref = block_on(openlr::encode(
+        &Location::Line(line),
+        &map,
+        &EncodingParameters::default(),
+        LogLevel::Debug,
+    ))
+    .unwrap();
+    let xml = openlr::serialize_xml(&loc_ref).unwrap();
+    assert!(xml.contains("<Longitude>-0.42573</Longitude><Latitude>53.83837</Latitude>"));
+    assert!(xml.contains("<PosOff>99</PosOff>"));
+
+    let decoded = decode_line(&openlr::serialize_binary(&loc_ref).unwrap(), &map);
+    assert_eq!(edge_ids(&decoded), vec![2566542]);
+    let (lb, ub) = decoded.p_off.unwrap();
+    assert!(lb <= 60 && ub >= 60);
+}
+
+// A map which, like many map agents, does not support previous edge searches
+struct NoPreviousEdgesMap(MockMap);
+
+#[async_trait]
+impl Map for NoPreviousEdgesMap {
+    async fn get_nearby_edges(
+        &self,
+        points: Vec<Coord>,
+        radius: u32,
+    ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+        self.0.get_nearby_edges(points, radius).await
+    }
+
+    async fn get_next_edges(&self, id: i64, meta: String) -> Result<Vec<Edge>, OpenLrErr> {
+        self.0.get_next_edges(id, meta).await
+    }
+}
+
+#[test]
+fn test_encode_without_valid_nodes() {
+    let mock = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+    let edge = block_on(mock.get_next_edges(3193167, String::new()))
+        .unwrap()
+        .into_iter()
+        .find(|e| e.id == 2566542)
+        .unwrap();
+    let line = Location::Line(LineLocation {
+        edges: vec![edge.clone()],
+        p_off: Some((60, 60)),
+        n_off: None,
+    });
+    let point = Location::PointAlongLine(PointAlongLineLocation {
+        edges: vec![edge],
+        p_off: Some((50, 50)),
+        side_of_road: SideOfRoad::Left,
+        orientation: Orientation::NoOrientationOrUnknown,
+    });
+    let no_valid_nodes = EncodingParameters {
+        place_lrps_on_valid_nodes: false,
+        ..Default::default()
+    };
+
+    // the LRPs stay at the ends of the location, whether the encoder is asked not to move
+    // them or the map cannot tell which nodes are valid
+    let map = NoPreviousEdgesMap(MockMap::new_from_csv("test_data/test1.csv").unwrap());
+    for (location, offset) in [(&line, 60), (&point, 50)] {
+        for (map, params) in [
+            (&mock as &dyn Map, &no_valid_nodes),
+            (&map as &dyn Map, &EncodingParameters::default()),
+        ] {
+            let loc_ref = block_on(openlr::encode(location, map, params, LogLevel::Debug)).unwrap();
+            let xml = openlr::serialize_xml(&loc_ref).unwrap();
+            assert!(xml.contains(&format!("<PosOff>{}</PosOff>", offset)));
+        }
+    }
+}
+
+fn pal_decoding_parameters() -> DecodingParameters {
+    DecodingParameters {
+        bearing_weight: 0.35,
+        fow_weight: 0.2,
+        frc_weight: 0.1,
+        distance_weight: 0.35,
+        ..Default::default()
+    }
+}
+
+fn decode_point_along_line(code: &str, map: &MockMap) -> PointAlongLineLocation {
+    match block_on(openlr::decode_binary(
+        code,
+        1,
+        map,
+        &pal_decoding_parameters(),
+        LogLevel::Debug,
+    ))
+    .result
+    {
+        Ok(Location::PointAlongLine(p)) => p,
+        r => panic!("Unexpected decoding result: {:?}", r),
+    }
+}
+
+#[test]
+fn test_encode_point_along_line_round_trip() {
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+    let point = decode_point_along_line("K/+zGCZJg2uvBAAh/x9rX4A=", &map);
+    let edge = point.edges[0].id;
+    let offset = point.p_off.unwrap().0;
+
+    let loc_ref = block_on(openlr::encode(
+        &Location::PointAlongLine(point),
+        &map,
+        &EncodingParameters::default(),
+        LogLevel::Debug,
+    ))
+    .unwrap();
+    let code = openlr::serialize_binary(&loc_ref).unwrap();
+
+    let decoded = decode_point_along_line(&code, &map);
+    assert_eq!(decoded.edges[0].id, edge);
+    let (lb, ub) = decoded.p_off.unwrap();
+    assert!(lb <= offset + 1 && ub + 1 >= offset);
+    assert_eq!(decoded.side_of_road, SideOfRoad::Right);
+    assert_eq!(decoded.orientation, Orientation::FirstToSecond);
+
+    // and through the XML format
+    let xml = openlr::serialize_xml(&loc_ref).unwrap();
+    let from_xml = openlr::deserialize_xml(&xml).unwrap();
+    assert_eq!(openlr::serialize_binary(&from_xml).unwrap(), code);
+
+    // where a malformed offset is an error rather than no offset
+    let pos_off = format!("<PosOff>{}</PosOff>", offset);
+    assert!(xml.contains(&pos_off));
+    assert!(matches!(
+        openlr::deserialize_xml(&xml.replace(&pos_off, "<PosOff>abc</PosOff>")),
+        Err(OpenLrErr::XmlParseError(_))
+    ));
+}
+
+#[test]
+fn test_encode_point_along_line_valid_nodes() {
+    let map = MockMap::new_from_csv("test_data/test1.csv").unwrap();
+
+    // edge 2566542 can only be reached from edge 3193167, which in turn can only be
+    // reached from edge 199640, so the first LRP is moved back to the start of that edge
+    let edge = block_on(map.get_next_edges(3193167, String::new()))
+        .unwrap()
+        .into_iter()
+        .find(|e| e.id == 2566542)
+        .unwrap();
+    let point = PointAlongLineLocation {
+        edges: vec![edge],
+        p_off: Some((50, 50)),
+        side_of_road: SideOfRoad::Left,
+        orientation: Orientation::NoOrientationOrUnknown,
+    };
+    let loc_ref = block_on(openlr::encode(
+        &Location::PointAlongLine(point),
+        &map,
+        &EncodingParameters::default(),
+        LogLevel::Debug,
+    ))
+    .unwrap();
+    let xml = openlr::serialize_xml(&loc_ref).unwrap();
+    assert!(xml.contains("<PosOff>89</PosOff>"));
+
+    let decoded = decode_point_along_line(&openlr::serialize_binary(&loc_ref).unwrap(), &map);
+    assert_eq!(decoded.edges[0].id, 2566542);
+    assert_eq!(decoded.side_of_road, SideOfRoad::Left);
+}
@@ -0,0 +1,247 @@
+use async_trait::async_trait;
+use futures::executor::block_on;
+use geo::{Coord, LineString, Polygon};
+use mock_map_agent::mock_map::MockMap;
+use openlr::decoding_parameters::DecodingParameters;
+use openlr::edge::Edge;
+use openlr::errors::OpenLrErr;
+use openlr::in_memory_map::InMemoryMap;
+use openlr::log::LogLevel;
+use openlr::map::Map;
+use openlr::subgraph::Subgraph;
+use openlr::turn_restriction::TurnRestriction;
+
+fn sorted_ids(edges: &[Edge]) -> Vec<i64> {
+    let mut ids = edges.iter().map(|e| e.get_id()).collect::<Vec<i64>>();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_decode_with_prefetched_subgraph() {
+    let prefetch = DecodingParameters::default();
+    let no_prefetch = DecodingParameters {
+        prefetch_subgraphs: false,
+        ..Default::default()
+    };
+    for (code, csv) in [
+        ("C/+zGCZJgyuvBAAh/x8rHw==", "test_data/test1.csv"),
+        ("C/4bnSaa4yu5Af91ACAruQT+r/+9Kwc=", "test_data/test3.csv"),
+        ("C/7VOCaEbSu/BP+5AMUrbJEQ", "test_data/test4.csv"),
+    ] {
+        let mock = MockMap::new_from_csv(csv).unwrap();
+        let expected = block_on(openlr::decode_binary(
+            code,
+            1,
+            &mock,
+            &no_prefetch,
+            LogLevel::Debug,
+        ));
+        let decoded = block_on(openlr::decode_binary(
+            code,
+            1,
+            &mock,
+            &prefetch,
+            LogLevel::Debug,
+        ));
+        assert!(decoded.result.is_ok());
+        assert_eq!(
+            format!("{:?}", decoded.result),
+            format!("{:?}", expected.result)
+        );
+        // the route was found within the subgraph, without falling back to the map
+        assert!(decoded
+            .log
+            .iter()
+            .any(|entry| entry.txt.starts_with("Prefetched a subgraph")));
+        assert!(!decoded
+            .log
+            .iter()
+            .any(|entry| entry.txt.starts_with("No route found within the prefetched subgraph")));
+        assert!(!expected
+            .log
+            .iter()
+            .any(|entry| entry.txt.starts_with("Prefetched a subgraph")));
+    }
+}
+
+// A map whose subgraphs cannot be searched: each holds an invalid turn restriction
+struct BrokenSubgraphMap(MockMap);
+
+#[async_trait]
+impl Map for BrokenSubgraphMap {
+    async fn get_nearby_edges(
+        &self,
+        points: Vec<Coord>,
+        radius: u32,
+    ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+        self.0.get_nearby_edges(points, radius).await
+    }
+
+    async fn get_next_edges(&self, id: i64, meta: String) -> Result<Vec<Edge>, OpenLrErr> {
+        self.0.get_next_edges(id, meta).await
+    }
+
+    async fn get_subgraph(&self, area: Polygon) -> Result<Option<Subgraph>, OpenLrErr> {
+        let mut subgraph = self.0.get_subgraph(area).await?.unwrap();
+        let id = subgraph.edges[0].edge.get_id();
+        subgraph.turn_restrictions.push(TurnRestriction::new(vec![id]));
+        Ok(Some(subgraph))
+    }
+}
+
+#[test]
+fn test_decode_with_broken_subgraph() {
+    let map = BrokenSubgraphMap(MockMap::new_from_csv("test_data/test4.csv").unwrap());
+    let decoded = block_on(openlr::decode_binary(
+        "C/7VOCaEbSu/BP+5AMUrbJEQ",
+        1,
+        &map,
+        &DecodingParameters::default(),
+        LogLevel::Debug,
+    ));
+    // building the subgraph fails, so the map itself is searched instead
+    assert!(decoded.result.is_ok());
+    assert!(decoded
+        .log
+        .iter()
+        .any(|entry| entry.txt.starts_with("Unable to prefetch the subgraph")));
+}
+
+#[test]
+fn test_decode_same_as_mock_map() {
+    let params = DecodingParameters {
+        bearing_weight: 0.35,
+        fow_weight: 0.2,
+        frc_weight: 0.1,
+        distance_weight: 0.35,
+        ..Default::default()
+    };
+    for (code, csv) in [
+        ("C/+zGCZJgyuvBAAh/x8rHw==", "test_data/test1.csv"),
+        ("C/5kUCVBsjPVAv8f/+QzBw==", "test_data/test2.csv"),
+        ("C/4bnSaa4yu5Af91ACAruQT+r/+9Kwc=", "test_data/test3.csv"),
+        ("C/7VOCaEbSu/BP+5AMUrbJEQ", "test_data/test4.csv"),
+        ("K/+zGCZJg2uvBAAh/x9rX4A=", "test_data/test1.csv"),
+    ] {
+        let mock = MockMap::new_from_csv(csv).unwrap();
+        let map = InMemoryMap::new_from_csv(csv).unwrap();
+
+        let expected = block_on(openlr::decode_binary(
+            code,
+            1,
+            &mock,
+            &params,
+            LogLevel::Debug,
+        ));
+        let decoded = block_on(openlr::decode_binary(
+            code,
+            1,
+            &map,
+            &params,
+            LogLevel::Debug,
+        ));
+        assert!(decoded.result.is_ok());
+        assert_eq!(
+            format!("{:?}", decoded.result),
+            format!("{:?}", expected.result)
+        );
+    }
+}
+
+#[test]
+fn test_queries_same_as_mock_map() {
+    let mock = MockMap::new_from_csv("test_data/test4.csv").unwrap();
+    let map = InMemoryMap::new_from_csv("test_data/test4.csv").unwrap();
+    assert!(!map.is_empty());
+
+    let points = vec![
+        Coord {
+            x: -1.64277,
+            y: 54.16731,
+        },
+        Coord {
+            x: -1.64133,
+            y: 54.16401,
+        },
+    ];
+    for radius in [10, 50, 200] {
+        let expected = block_on(mock.get_nearby_edges(points.clone(), radius)).unwrap();
+        let nearby = block_on(map.get_nearby_edges(points.clone(), radius)).unwrap();
+        assert_eq!(nearby.len(), points.len());
+        for (edges, expected) in nearby.iter().zip(expected.iter()) {
+            assert!(!edges.is_empty());
+            assert_eq!(sorted_ids(edges), sorted_ids(expected));
+        }
+    }
+
+    for id in [11384678, 3551999, 12007589, 13042191] {
+        let expected = block_on(mock.get_next_edges(id, String::new())).unwrap();
+        let next = block_on(map.get_next_edges(id, String::new())).unwrap();
+        assert_eq!(sorted_ids(&next), sorted_ids(&expected));
+
+        let expected = block_on(mock.get_previous_edges(id, String::new())).unwrap();
+        let previous = block_on(map.get_previous_edges(id, String::new())).unwrap();
+        assert_eq!(sorted_ids(&previous), sorted_ids(&expected));
+    }
+
+    let ids = [11384678, 3551999, 12007589, 13042191];
+    let batch = block_on(
+        map.get_next_edges_many(
+            &ids.iter()
+                .map(|id| (*id, String::new()))
+                .collect::<Vec<(i64, String)>>(),
+        ),
+    )
+    .unwrap();
+    assert_eq!(batch.len(), ids.len());
+    for (next, id) in batch.iter().zip(ids) {
+        let expected = block_on(mock.get_next_edges(id, String::new())).unwrap();
+        assert_eq!(sorted_ids(next), sorted_ids(&expected));
+    }
+
+    let area = Polygon::new(
+        LineString::from(vec![
+            (-1.644, 54.164),
+            (-1.642, 54.164),
+            (-1.642, 54.168),
+            (-1.644, 54.168),
+            (-1.644, 54.164),
+        ]),
+        vec![],
+    );
+    let expected = block_on(mock.get_edges_in_area(area.clone())).unwrap();
+    let edges = block_on(map.get_edges_in_area(area)).unwrap();
+    assert!(!edges.is_empty());
+    assert_eq!(sorted_ids(&edges), sorted_ids(&expected));
+}
+
+#[test]
+fn test_load_errors() {
+    match InMemoryMap::new_from_csv("test_data/missing.csv") {
+        Err(OpenLrErr::MapLoadError(reason)) => assert!(reason.contains("missing.csv")),
+        _ => panic!("Expected a MapLoadError"),
+    }
+    assert!(matches!(
+        MockMap::new_from_csv("test_data/missing.csv"),
+        Err(OpenLrErr::MapLoadError(_))
+    ));
+}
+
+#[test]
+fn test_decode_outside_map() {
+    // test1 and test4 cover different areas, so none of test1's LRPs lie on test4's map
+    let mock = MockMap::new_from_csv("test_data/test4.csv").unwrap();
+    let decoded = block_on(openlr::decode_binary(
+        "C/+zGCZJgyuvBAAh/x8rHw==",
+        1,
+        &mock,
+        &DecodingParameters::default(),
+        LogLevel::Debug,
+    ));
+    assert!(matches!(decoded.result, Err(OpenLrErr::LrpOutsideMap(0, _, _))));
+
+    let info = block_on(mock.get_info()).unwrap();
+    assert!(info.bounding_box.is_some());
+    assert_eq!(info.vendor, "Mock map");
+}
@@ -0,0 +1,323 @@
+use std::fs;
+use std::path::PathBuf;
+
+use flatbuffers::{FlatBufferBuilder, WIPOffset};
+use futures::executor::block_on;
+use mock_map_agent::mock_map::MockMap;
+use openlr::decoding_parameters::DecodingParameters;
+use openlr::edge::Edge;
+use openlr::errors::OpenLrErr;
+use openlr::fow::FOW;
+use openlr::frc::FRC;
+use openlr::in_memory_map::InMemoryMap;
+use openlr::log::LogLevel;
+use openlr::map_loader::{self, AttributeMapping};
+use rusqlite::{params, Connection};
+use serde_json::json;
+
+const CODES: [(&str, &str); 4] = [
+    ("C/+zGCZJgyuvBAAh/x8rHw==", "test1"),
+    ("C/5kUCVBsjPVAv8f/+QzBw==", "test2"),
+    ("C/4bnSaa4yu5Af91ACAruQT+r/+9Kwc=", "test3"),
+    ("C/7VOCaEbSu/BP+5AMUrbJEQ", "test4"),
+];
+
+// A row of the test data: id:"meta":fow:frc:flowdir:from_int:to_int:len:"wkt"
+struct Road {
+    id: i64,
+    meta: String,
+    fow: i64,
+    frc: i64,
+    flowdir: i64,
+    from_int: i64,
+    to_int: i64,
+    len: i64,
+    coords: Vec<(f64, f64)>,
+}
+
+fn read_roads(name: &str) -> Vec<Road> {
+    fs::read_to_string(format!("test_data/{}.csv", name))
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let v = line.split(':').collect::<Vec<&str>>();
+            let int = |i: usize| v[i].parse::<i64>().unwrap();
+            let edge = Edge::new_from_wkt(
+                0,
+                String::new(),
+                FOW::from_u8(0),
+                FRC::from_u8(0),
+                0,
+                v[8].trim_matches('"'),
+            )
+            .unwrap();
+            Road {
+                id: int(0),
+                meta: v[1].trim_matches('"').to_owned(),
+                fow: int(2),
+                frc: int(3),
+                flowdir: int(4),
+                from_int: int(5),
+                to_int: int(6),
+                len: int(7),
+                coords: edge.geom.coords().map(|c| (c.x, c.y)).collect(),
+            }
+        })
+        .collect()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("openlr_map_loader_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+fn write_geojson(roads: &[Road], path: &PathBuf) {
+    let features = roads
+        .iter()
+        .map(|r| {
+            json!({
+                "type": "Feature",
+                "geometry": {"type": "LineString", "coordinates": r.coords},
+                "properties": {
+                    "id": r.id, "meta": r.meta, "fow": r.fow, "frc": r.frc, "flowdir": r.flowdir,
+                    "from_int": r.from_int, "to_int": r.to_int, "len": r.len,
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    let collection = json!({"type": "FeatureCollection", "features": features});
+    fs::write(path, collection.to_string()).unwrap();
+}
+
+fn write_geopackage(roads: &[Road], path: &PathBuf) {
+    let _ = fs::remove_file(path);
+    let conn = Connection::open(path).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE gpkg_spatial_ref_sys (srs_name TEXT, srs_id INTEGER PRIMARY KEY,
+             organization TEXT, organization_coordsys_id INTEGER, definition TEXT);
+         INSERT INTO gpkg_spatial_ref_sys VALUES ('WGS 84', 4326, 'EPSG', 4326, '');
+         CREATE TABLE gpkg_contents (table_name TEXT PRIMARY KEY, data_type TEXT);
+         INSERT INTO gpkg_contents VALUES ('roads', 'features');
+         CREATE TABLE gpkg_geometry_columns (table_name TEXT, column_name TEXT,
+             geometry_type_name TEXT, srs_id INTEGER, z INTEGER, m INTEGER);
+         INSERT INTO gpkg_geometry_columns VALUES ('roads', 'geom', 'LINESTRING', 4326, 0, 0);
+         CREATE TABLE roads (fid INTEGER PRIMARY KEY, geom BLOB, id INTEGER, meta TEXT,
+             fow INTEGER, frc INTEGER, flowdir INTEGER, from_int INTEGER, to_int INTEGER,
+             len REAL);",
+    )
+    .unwrap();
+    for r in roads {
+        // little endian header without an envelope, followed by WKB
+        let mut blob = vec![b'G', b'P', 0, 0x01];
+        blob.extend(4326i32.to_le_bytes());
+        blob.push(1);
+        blob.extend(2u32.to_le_bytes());
+        blob.extend((r.coords.len() as u32).to_le_bytes());
+        for (x, y) in &r.coords {
+            blob.extend(x.to_le_bytes());
+            blob.extend(y.to_le_bytes());
+        }
+        conn.execute(
+            "INSERT INTO roads (geom, id, meta, fow, frc, flowdir, from_int, to_int, len)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                blob,
+                r.id,
+                r.meta,
+                r.fow,
+                r.frc,
+                r.flowdir,
+                r.from_int,
+                r.to_int,
+                r.len as f64
+            ],
+        )
+        .unwrap();
+    }
+}
+
+fn write_flatgeobuf(roads: &[Road], path: &PathBuf) {
+    // columns: id (Long), meta (String), fow, frc, flowdir (UByte), from_int, to_int
+    // (Long) and len (Int)
+    let columns = [
+        ("id", 7u8),
+        ("meta", 11),
+        ("fow", 1),
+        ("frc", 1),
+        ("flowdir", 1),
+        ("from_int", 7),
+        ("to_int", 7),
+        ("len", 5),
+    ];
+    let mut data = b"fgb\x03fgb\x00".to_vec();
+
+    let mut fbb = FlatBufferBuilder::new();
+    let column_offsets = columns
+        .iter()
+        .map(|(name, column_type)| {
+            let name = fbb.create_string(name);
+            let start = fbb.start_table();
+            fbb.push_slot_always(4, name);
+            fbb.push_slot::<u8>(6, *column_type, 0);
+            fbb.end_table(start)
+        })
+        .collect::<Vec<_>>();
+    let column_vector = fbb.create_vector(&column_offsets);
+    let start = fbb.start_table();
+    fbb.push_slot::<u8>(8, 2, 0);
+    fbb.push_slot_always(18, column_vector);
+    fbb.push_slot::<u64>(20, roads.len() as u64, 0);
+    fbb.push_slot::<u16>(22, 16, 16);
+    let header = fbb.end_table(start);
+    fbb.finish_size_prefixed(header, None);
+    data.extend(fbb.finished_data());
+
+    // a packed R-tree of node size 16, which the loader skips
+    let mut n = roads.len();
+    let mut nodes = n;
+    loop {
+        n = n.div_ceil(16);
+        nodes += n;
+        if n == 1 {
+            break;
+        }
+    }
+    data.extend(vec![0u8; nodes * 40]);
+
+    for r in roads {
+        let mut properties = vec![];
+        for (i, value) in [r.id, r.fow, r.frc, r.flowdir, r.from_int, r.to_int, r.len]
+            .iter()
+            .enumerate()
+        {
+            let column = if i == 0 { 0 } else { i + 1 };
+            properties.extend((column as u16).to_le_bytes());
+            match columns[column].1 {
+                1 => properties.push(*value as u8),
+                5 => properties.extend((*value as i32).to_le_bytes()),
+                _ => properties.extend(value.to_le_bytes()),
+            }
+        }
+        properties.extend(1u16.to_le_bytes());
+        properties.extend((r.meta.len() as u32).to_le_bytes());
+        properties.extend(r.meta.as_bytes());
+
+        let mut fbb = FlatBufferBuilder::new();
+        let xy = fbb.create_vector(
+            &r.coords
+                .iter()
+                .flat_map(|(x, y)| [*x, *y])
+                .collect::<Vec<f64>>(),
+        );
+        let start = fbb.start_table();
+        fbb.push_slot_always(6, xy);
+        let geometry: WIPOffset<_> = fbb.end_table(start);
+        let properties = fbb.create_vector(&properties);
+        let start = fbb.start_table();
+        fbb.push_slot_always(4, geometry);
+        fbb.push_slot_always(6, properties);
+        let feature = fbb.end_table(start);
+        fbb.finish_size_prefixed(feature, None);
+        data.extend(fbb.finished_data());
+    }
+    fs::write(path, data).unwrap();
+}
+
+#[test]
+fn test_load_same_as_mock_map() {
+    let params = DecodingParameters {
+        bearing_weight: 0.35,
+        fow_weight: 0.2,
+        frc_weight: 0.1,
+        distance_weight: 0.35,
+        ..Default::default()
+    };
+    for (code, name) in CODES {
+        let roads = read_roads(name);
+        let mock = MockMap::new_from_csv(&format!("test_data/{}.csv", name)).unwrap();
+        let expected = block_on(openlr::decode_binary(
+            code,
+            1,
+            &mock,
+            &params,
+            LogLevel::Debug,
+        ));
+        assert!(expected.result.is_ok());
+
+        let geojson = temp_path(&format!("{}.geojson", name));
+        write_geojson(&roads, &geojson);
+        let geopackage = temp_path(&format!("{}.gpkg", name));
+        write_geopackage(&roads, &geopackage);
+        let flatgeobuf = temp_path(&format!("{}.fgb", name));
+        write_flatgeobuf(&roads, &flatgeobuf);
+
+        for path in [geojson, geopackage, flatgeobuf] {
+            let map =
+                InMemoryMap::new_from_file(path.to_str().unwrap(), &AttributeMapping::default())
+                    .unwrap();
+            assert_eq!(map.len(), roads.len());
+            let decoded = block_on(openlr::decode_binary(
+                code,
+                1,
+                &map,
+                &params,
+                LogLevel::Debug,
+            ));
+            assert_eq!(
+                format!("{:?}", decoded.result),
+                format!("{:?}", expected.result),
+                "decoding {} against {:?}",
+                code,
+                path
+            );
+        }
+    }
+}
+
+#[test]
+fn test_load_errors() {
+    let mut roads = read_roads("test1");
+    roads[2].fow = 9;
+    let path = temp_path("invalid.geojson");
+    write_geojson(&roads, &path);
+    match map_loader::load(path.to_str().unwrap(), &AttributeMapping::default()) {
+        Err(OpenLrErr::InvalidMapFeature(_, 3, reason)) => assert!(reason.contains("fow")),
+        r => panic!("Unexpected result: {:?}", r.map(|edges| edges.len())),
+    }
+
+    let path = temp_path("invalid.gpkg");
+    write_geopackage(&roads, &path);
+    assert!(matches!(
+        map_loader::load(path.to_str().unwrap(), &AttributeMapping::default()),
+        Err(OpenLrErr::InvalidMapFeature(_, 3, _))
+    ));
+
+    let path = temp_path("invalid.fgb");
+    write_flatgeobuf(&roads, &path);
+    assert!(matches!(
+        map_loader::load(path.to_str().unwrap(), &AttributeMapping::default()),
+        Err(OpenLrErr::InvalidMapFeature(_, 3, _))
+    ));
+
+    // a truncated file
+    roads[2].fow = 3;
+    write_flatgeobuf(&roads, &path);
+    let data = fs::read(&path).unwrap();
+    fs::write(&path, &data[..data.len() - 10]).unwrap();
+    assert!(matches!(
+        map_loader::load(path.to_str().unwrap(), &AttributeMapping::default()),
+        Err(OpenLrErr::MapLoadError(_))
+    ));
+
+    let path = temp_path("not_json.geojson");
+    fs::write(&path, "{\"type\": \n\"FeatureCollection\",,").unwrap();
+    match map_loader::load(path.to_str().unwrap(), &AttributeMapping::default()) {
+        Err(OpenLrErr::MapLoadError(reason)) => assert!(reason.contains("line 2")),
+        r => panic!("Unexpected result: {:?}", r.map(|edges| edges.len())),
+    }
+    assert!(matches!(
+        map_loader::load("test_data/test1.wkt", &AttributeMapping::default()),
+        Err(OpenLrErr::MapLoadError(_))
+    ));
+}
@@ -0,0 +1,171 @@
+//! Settings for the proxies of map agents, configurable for each agent URL.
+//!
+//! The configuration is a JSON object whose optional `default` member applies to
+//! every agent not named in its optional `agents` member, which maps agent URLs to
+//! their own settings, i.e.
+//!
+//! ```json
+//! {
+//!   "default": {"deadline_ms": 2000},
+//!   "agents": {"grpc://[::1]:8081": {"deadline_ms": 500, "max_retries": 4}}
+//! }
+//! ```
+//!
+//! Settings which are left out take their default values.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::Deserialize;
+use url::Url;
+
+use crate::errors::OpenLrServerErr;
+
+/// How a proxy calls its map agent
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// Time allowed to establish a connection to the agent
+    pub connect_timeout_ms: u64,
+    /// Time allowed for each call to the agent, after which it fails as timed out
+    pub deadline_ms: u64,
+    /// Number of times a call which failed with a transient error is repeated
+    pub max_retries: u32,
+    /// Delay before the first retry, which doubles with each further retry
+    pub initial_backoff_ms: u64,
+    /// Maximum delay between retries
+    pub max_backoff_ms: u64,
+    /// Number of consecutive failed attempts, retries included, after which the agent
+    /// is considered down, and further calls fail fast
+    pub failure_threshold: u32,
+    /// Time for which calls fail fast once the agent is considered down, before a
+    /// single call is let through to find out whether it has recovered
+    pub open_ms: u64,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        AgentConfig {
+            connect_timeout_ms: 5_000,
+            deadline_ms: 10_000,
+            max_retries: 2,
+            initial_backoff_ms: 50,
+            max_backoff_ms: 1_000,
+            failure_threshold: 5,
+            open_ms: 30_000,
+        }
+    }
+}
+
+impl AgentConfig {
+    pub fn connect_timeout(&self) -> Duration {
+        Duration::from_millis(self.connect_timeout_ms)
+    }
+
+    pub fn deadline(&self) -> Duration {
+        Duration::from_millis(self.deadline_ms)
+    }
+
+    pub fn open_duration(&self) -> Duration {
+        Duration::from_millis(self.open_ms)
+    }
+
+    /// Returns the delay before the given retry (counting from 0)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let backoff = self
+            .initial_backoff_ms
+            .saturating_mul(2u64.saturating_pow(retry));
+        Duration::from_millis(backoff.min(self.max_backoff_ms))
+    }
+}
+
+/// The settings of every map agent
+#[derive(Debug, Clone, Default)]
+pub struct AgentConfigs {
+    pub default: AgentConfig,
+    pub agents: HashMap<Url, AgentConfig>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AgentConfigsFile {
+    #[serde(default)]
+    default: AgentConfig,
+    #[serde(default)]
+    agents: HashMap<String, AgentConfig>,
+}
+
+impl AgentConfigs {
+    /// Returns the settings of the agent at the given URL
+    pub fn get(&self, url: &Url) -> &AgentConfig {
+        self.agents.get(url).unwrap_or(&self.default)
+    }
+
+    /// Parses the settings from JSON, as described in the module documentation
+    pub fn from_json(json: &str) -> Result<Self, OpenLrServerErr> {
+        let file: AgentConfigsFile = serde_json::from_str(json).map_err(|e| {
+            OpenLrServerErr::ConfigurationError(format!("Invalid agent configuration: {}", e))
+        })?;
+        let agents = file
+            .agents
+            .into_iter()
+            .map(|(url, config)| {
+                Url::parse(&url).map(|u| (u, config)).map_err(|e| {
+                    OpenLrServerErr::ConfigurationError(format!(
+                        "Invalid map agent URL '{}': {}",
+                        url, e
+                    ))
+                })
+            })
+            .collect::<Result<HashMap<Url, AgentConfig>, OpenLrServerErr>>()?;
+        Ok(AgentConfigs {
+            default: file.default,
+            agents,
+        })
+    }
+
+    /// Reads the settings from a JSON file
+    pub fn from_file(path: &str) -> Result<Self, OpenLrServerErr> {
+        let json = std::fs::read_to_string(path).map_err(|e| {
+            OpenLrServerErr::ConfigurationError(format!("Unable to read {}: {}", path, e))
+        })?;
+        AgentConfigs::from_json(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_json() {
+        let configs = AgentConfigs::from_json(
+            r#"{
+                "default": {"deadline_ms": 2000},
+                "agents": {"grpc://[::1]:8081": {"deadline_ms": 500, "max_retries": 4}}
+            }"#,
+        )
+        .unwrap();
+        let agent = configs.get(&Url::parse("grpc://[::1]:8081").unwrap());
+        assert_eq!(agent.deadline(), Duration::from_millis(500));
+        assert_eq!(agent.max_retries, 4);
+        assert_eq!(agent.failure_threshold, AgentConfig::default().failure_threshold);
+        let other = configs.get(&Url::parse("grpc://[::1]:8082").unwrap());
+        assert_eq!(other.deadline(), Duration::from_millis(2000));
+
+        assert!(AgentConfigs::from_json(r#"{"agents": {"not a url": {}}}"#).is_err());
+        assert!(AgentConfigs::from_json(r#"{"default": {"deadline": 1}}"#).is_err());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let config = AgentConfig {
+            initial_backoff_ms: 100,
+            max_backoff_ms: 350,
+            ..Default::default()
+        };
+        let backoffs = (0..4).map(|r| config.backoff(r).as_millis()).collect::<Vec<u128>>();
+        assert_eq!(backoffs, vec![100, 200, 350, 350]);
+        assert_eq!(config.backoff(u32::MAX), Duration::from_millis(350));
+    }
+}
@@ -1,19 +1,26 @@
 use clap::Parser;
-use openlr_server::common::proto_edge_from_edge;
+use openlr_server::agent_config::AgentConfigs;
+use openlr_server::common::{
+    proto_edge_from_edge, proto_orientation_from_orientation,
+    proto_side_of_road_from_side_of_road,
+};
+use openlr::caching_map::CachingMap;
+use openlr::map::Map;
+use openlr::errors::OpenLrErr;
 use openlr::location::Location;
+use openlr::point_along_line::PointAlongLineLocation;
 use openlr::log::{LogEntry, LogLevel};
 use openlr::request_result::RequestResult;
 use openlr_server::grpc_map_proxy::GRPCMapProxy;
 use openlr_server::grpc_server_context::GrpcServerContext;
 use openlr_server::openlr_services::decoder_server::Decoder;
-use openlr_server::openlr_services::{BinaryDecodeRequest, BinaryDecodeResponse, binary_decode_response, DecodeError};
-use openlr_server::openlr_services::{LogMessage, OffsetRange};
+use openlr_server::openlr_services::{BinaryDecodeRequest, BinaryDecodeResponse, binary_decode_response, DecodeError, DecodeErrorCode};
+use openlr_server::openlr_services::{Coordinate, LogMessage, OffsetRange};
 use reqwest::Url;
-use std::collections::hash_map::Entry;
 use std::collections::VecDeque;
 use std::sync::Arc;
 use std::{error::Error, io::ErrorKind, pin::Pin};
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Semaphore};
 use tokio_stream::{wrappers::ReceiverStream, StreamExt};
 use tonic::codegen::futures_core::Stream;
 use tonic::Streaming;
@@ -28,6 +35,14 @@ pub struct Arguments {
     #[clap(default_value_t=String::from("[::1:8080]"),short, long)]
     /// Address to listen on (i.e. "127.0.0.1:9090")
     address: String,
+
+    #[clap(default_value_t = 64, short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    /// Maximum number of requests decoded concurrently on each stream
+    max_in_flight: u32,
+
+    #[clap(long)]
+    /// JSON file with the deadlines, retries and circuit breaker settings of map agents
+    agent_config: Option<String>,
 }
 
 fn match_for_io_error(err_status: &Status) -> Option<&std::io::Error> {
@@ -58,7 +73,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     simple_logger::init_with_env().unwrap();
     let args = Arguments::parse();
     let address = args.address.parse().unwrap();
-    let decode_service = DecoderService::new();
+    let agents = match &args.agent_config {
+        Some(path) => AgentConfigs::from_file(path)?,
+        None => AgentConfigs::default(),
+    };
+    let decode_service = DecoderService::new(args.max_in_flight as usize, agents);
 
     log::info!("Decoder server initializing...",);
     log::info!("Decoder server listening on port: {}...", args.address);
@@ -74,7 +93,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 }
 
 pub struct DecoderService {
-    context: GrpcServerContext,
+    context: Arc<GrpcServerContext>,
+    max_in_flight: usize,
 }
 
 fn log_message_to_proto(le: LogEntry) -> LogMessage {
@@ -91,6 +111,42 @@ fn log_message_to_proto(le: LogEntry) -> LogMessage {
     }
 }
 
+fn point_along_line_to_proto(pal: &PointAlongLineLocation) -> openlr_server::openlr_services::PointAlongLineLocation {
+    openlr_server::openlr_services::PointAlongLineLocation {
+        edge: pal.edges.iter().map(proto_edge_from_edge).collect::<Vec<openlr_server::openlr_services::Edge>>(),
+        pos_off: match pal.p_off {
+            Some((lb, ub)) => Some(OffsetRange { lb, ub }),
+            _ => None,
+        },
+        side_of_road: proto_side_of_road_from_side_of_road(&pal.side_of_road),
+        orientation: proto_orientation_from_orientation(&pal.orientation),
+    }
+}
+
+fn area_edges_to_proto(edges: &Option<Vec<openlr::edge::Edge>>) -> Vec<openlr_server::openlr_services::Edge> {
+    edges
+        .iter()
+        .flatten()
+        .map(proto_edge_from_edge)
+        .collect::<Vec<openlr_server::openlr_services::Edge>>()
+}
+
+// Build the response to a request which failed before decoding could begin
+fn decode_error_response(id: u64, code: DecodeErrorCode, reason: String) -> BinaryDecodeResponse {
+    BinaryDecodeResponse {
+        id,
+        elapsed_secs: 0,
+        elapsed_nanosecs: 0,
+        log: vec![],
+        decoding_result: Some(binary_decode_response::DecodingResult::DecodeError(
+            DecodeError {
+                reason,
+                code: code as i32,
+            },
+        )),
+    }
+}
+
 fn build_decode_response(res: &RequestResult<Location>) -> BinaryDecodeResponse {
     // FIXME: avoid the clone()
     let logmsgs = VecDeque::from(res.log.clone())
@@ -108,6 +164,11 @@ fn build_decode_response(res: &RequestResult<Location>) -> BinaryDecodeResponse
                 binary_decode_response::DecodingResult::DecodeError(
                     DecodeError {
                         reason: e.to_string(),
+                        code: match e {
+                            OpenLrErr::LrpOutsideMap(..) => DecodeErrorCode::LocationOutsideMap,
+                            OpenLrErr::AgentCircuitOpen(_) => DecodeErrorCode::MapAgentUnavailable,
+                            _ => DecodeErrorCode::DecodingFailed,
+                        } as i32,
                     },
                 ),
             ),
@@ -133,24 +194,184 @@ fn build_decode_response(res: &RequestResult<Location>) -> BinaryDecodeResponse
                 ),
             ),
         },
-        // TODO: Add PointALongLine
-        Ok(_) => todo!()
+        Ok(Location::ClosedLine(cl)) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::ClosedLineLocation(
+                    openlr_server::openlr_services::ClosedLineLocation {
+                        edge: cl.edges.iter().map(proto_edge_from_edge).collect::<Vec<openlr_server::openlr_services::Edge>>(),
+                    },
+                ),
+            ),
+        },
+        Ok(Location::PointAlongLine(pal)) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::PointAlongLineLocation(
+                    point_along_line_to_proto(pal),
+                ),
+            ),
+        },
+        Ok(Location::PoiWithAccessPoint(poi)) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::PoiWithAccessPointLocation(
+                    openlr_server::openlr_services::PoiWithAccessPointLocation {
+                        access_point: Some(point_along_line_to_proto(&poi.access_point)),
+                        poi: Some(Coordinate {
+                            longitude: poi.poi_longitude,
+                            latitude: poi.poi_latitude,
+                        }),
+                    },
+                ),
+            ),
+        },
+        Ok(Location::GeoCoordinate(geo)) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::GeoCoordinateLocation(
+                    openlr_server::openlr_services::GeoCoordinateLocation {
+                        coordinate: Some(Coordinate {
+                            longitude: geo.longitude,
+                            latitude: geo.latitude,
+                        }),
+                        edge: geo.edge.as_ref().map(proto_edge_from_edge),
+                        offset: geo.offset.unwrap_or(0),
+                    },
+                ),
+            ),
+        },
+        Ok(Location::Circle(circle)) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::CircleLocation(
+                    openlr_server::openlr_services::CircleLocation {
+                        center: Some(Coordinate {
+                            longitude: circle.longitude,
+                            latitude: circle.latitude,
+                        }),
+                        radius: circle.radius,
+                        edge: area_edges_to_proto(&circle.edges),
+                    },
+                ),
+            ),
+        },
+        Ok(Location::Rectangle(rect)) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::RectangleLocation(
+                    openlr_server::openlr_services::RectangleLocation {
+                        lower_left: Some(Coordinate {
+                            longitude: rect.lower_left_longitude,
+                            latitude: rect.lower_left_latitude,
+                        }),
+                        upper_right: Some(Coordinate {
+                            longitude: rect.upper_right_longitude,
+                            latitude: rect.upper_right_latitude,
+                        }),
+                        edge: area_edges_to_proto(&rect.edges),
+                    },
+                ),
+            ),
+        },
+        Ok(Location::Grid(grid)) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::GridLocation(
+                    openlr_server::openlr_services::GridLocation {
+                        lower_left: Some(Coordinate {
+                            longitude: grid.lower_left_longitude,
+                            latitude: grid.lower_left_latitude,
+                        }),
+                        upper_right: Some(Coordinate {
+                            longitude: grid.upper_right_longitude,
+                            latitude: grid.upper_right_latitude,
+                        }),
+                        columns: grid.columns as u32,
+                        rows: grid.rows as u32,
+                        edge: area_edges_to_proto(&grid.edges),
+                    },
+                ),
+            ),
+        },
+        Ok(Location::Polygon(poly)) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::PolygonLocation(
+                    openlr_server::openlr_services::PolygonLocation {
+                        corner: poly
+                            .corners
+                            .iter()
+                            .map(|(longitude, latitude)| Coordinate {
+                                longitude: *longitude,
+                                latitude: *latitude,
+                            })
+                            .collect::<Vec<Coordinate>>(),
+                        edge: area_edges_to_proto(&poly.edges),
+                    },
+                ),
+            ),
+        },
+        Ok(Location::Unknown) => BinaryDecodeResponse {
+            id: res.id,
+            elapsed_secs: res.elapsed.as_secs(),
+            elapsed_nanosecs: res.elapsed.subsec_nanos(),
+            log: logmsgs,
+            decoding_result: Some(
+                binary_decode_response::DecodingResult::DecodeError(
+                    DecodeError {
+                        reason: OpenLrErr::UnknownLocationTypeError.to_string(),
+                        code: DecodeErrorCode::DecodingFailed as i32,
+                    },
+                ),
+            ),
+        },
     }
 }
 
 impl DecoderService {
-    pub fn new() -> Self {
+    pub fn new(max_in_flight: usize, agents: AgentConfigs) -> Self {
+        let mut context = GrpcServerContext::new();
+        context.agents = agents;
         DecoderService {
-            context: GrpcServerContext::new(),
+            context: Arc::new(context),
+            max_in_flight,
         }
     }
-    async fn do_decode(&self, req: &BinaryDecodeRequest) -> BinaryDecodeResponse {
-        // Retreive a cached Map for the chosen URL, or else create a new one
-
+    async fn do_decode(context: &GrpcServerContext, req: &BinaryDecodeRequest) -> BinaryDecodeResponse {
         let url = match Url::parse(&req.agent_name) {
             Ok(u) => u,
-            // TODO: Deal with Url parse failure
-            _ => todo!(),
+            Err(e) => {
+                return decode_error_response(
+                    req.id,
+                    DecodeErrorCode::InvalidAgentUrl,
+                    format!("Invalid map agent URL '{}': {}", req.agent_name, e),
+                )
+            }
         };
 
         let log_level = match req.logging_level {
@@ -162,27 +383,38 @@ impl DecoderService {
             _ => LogLevel::Fatal,
         };
 
-        let mut mdbs = self.context.mdbs.lock().await;
-        let mdb = match mdbs.entry(url) {
-            Entry::Occupied(e) => (*e.into_mut()).clone(),
-            Entry::Vacant(e) => {
-                let dup = e.key().clone();
-                (*e.insert(Arc::new(GRPCMapProxy::new(dup).await))).clone()
+        // Retreive the request parameter set from the server context
+        let parameter_set = match context.get_param_set(&req.decoding_parameter_set).await {
+            Some(x) => x.clone(),
+            _ => {
+                return decode_error_response(
+                    req.id,
+                    DecodeErrorCode::UnknownParameterSet,
+                    format!("Unknown decoding parameter set '{}'", req.decoding_parameter_set),
+                )
             }
         };
 
-        // Drop the lock on the map hash early so that any future panics do not poison the mutex
-        drop(mdbs);
-
-        // Retreive the request parameter set from the server context
-        let parameter_set = match self
-            .context
-            .get_param_set(&req.decoding_parameter_set)
+        // Retreive a cached Map for the chosen URL, or else create a new one.  A failed
+        // connection is retried by the next request, and the results of a proxy are cached
+        // in turn for all the requests decoded against that agent.
+        let config = context.agents.get(&url).clone();
+        let mdb = match context
+            .mdbs
+            .get_or_connect(&url, || async {
+                let proxy = GRPCMapProxy::new_with_config(url.clone(), config.clone()).await?;
+                Ok(Arc::new(CachingMap::new(proxy, context.map_cache)) as Arc<dyn Map>)
+            })
             .await
         {
-            Some(x) => x.clone(),
-            // TODO: Deal with unknown parameter set
-            _ => todo!(),
+            Ok(mdb) => mdb,
+            Err(err) => {
+                return decode_error_response(
+                    req.id,
+                    DecodeErrorCode::MapAgentUnavailable,
+                    err.to_string(),
+                )
+            }
         };
 
         let res =
@@ -204,30 +436,53 @@ impl Decoder for DecoderService {
         let mut in_stream = req.into_inner();
         let (tx, rx) = mpsc::channel(128);
         let out_stream = ReceiverStream::new(rx);
+        let context = self.context.clone();
+        let in_flight = Arc::new(Semaphore::new(self.max_in_flight));
 
-        while let Some(result) = in_stream.next().await {
-            match result {
-                Ok(bin_dec_req) => tx
-                    .send(Ok(self.do_decode(&bin_dec_req).await))
-                    .await
-                    .expect("working rx"),
-                Err(err) => {
-                    if let Some(io_err) = match_for_io_error(&err) {
-                        if io_err.kind() == ErrorKind::BrokenPipe {
-                            // here you can handle special case when client
-                            // disconnected in unexpected way
-                            eprintln!("\tclient disconnected: broken pipe");
-                            break;
-                        }
+        // Read the requests in the background so that the response stream can be returned
+        // straight away.  Each request is decoded in a task of its own, and its response is
+        // sent as soon as it is ready: responses may therefore arrive out of order, and
+        // clients match them to their requests by id.
+        tokio::spawn(async move {
+            while let Some(result) = in_stream.next().await {
+                match result {
+                    Ok(bin_dec_req) => {
+                        // Stop reading requests while the in-flight limit is reached
+                        let permit = in_flight
+                            .clone()
+                            .acquire_owned()
+                            .await
+                            .expect("semaphore is never closed");
+                        let context = context.clone();
+                        let tx = tx.clone();
+                        tokio::spawn(async move {
+                            let response = DecoderService::do_decode(&context, &bin_dec_req).await;
+
+                            // the client may have gone away, in which case the response is
+                            // dropped.  The permit is held until the response is queued, so
+                            // that a client which reads slowly also slows down the decoding.
+                            let _ = tx.send(Ok(response)).await;
+                            drop(permit);
+                        });
                     }
+                    Err(err) => {
+                        if let Some(io_err) = match_for_io_error(&err) {
+                            if io_err.kind() == ErrorKind::BrokenPipe {
+                                // here you can handle special case when client
+                                // disconnected in unexpected way
+                                eprintln!("\tclient disconnected: broken pipe");
+                                break;
+                            }
+                        }
 
-                    match tx.send(Err(err)).await {
-                        Ok(_) => (),
-                        Err(_err) => break, // response was droped
+                        match tx.send(Err(err)).await {
+                            Ok(_) => (),
+                            Err(_err) => break, // response was droped
+                        }
                     }
                 }
             }
-        }
+        });
 
         Ok(Response::new(
             Box::pin(out_stream) as Self::DecodeBinaryStream
@@ -1,9 +1,15 @@
 use clap::Parser;
-use std::collections::hash_map::Entry;
+use std::io::ErrorKind;
 use std::sync::Arc;
+use std::time::Duration;
 
-use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
+use actix_web::http::header;
+use actix_web::{get, post, web, App, HttpRequest, HttpResponse, HttpServer, Responder};
+use openlr::caching_map::CachingMap;
 use openlr::log::LogLevel;
+use openlr::map::Map;
+use openlr::request_result::RequestResult;
+use openlr_server::agent_config::AgentConfigs;
 use openlr_server::decode_request::DecodeRequest;
 use openlr_server::grpc_map_proxy::GRPCMapProxy;
 use openlr_server::http_map_proxy::HttpMapProxy;
@@ -17,6 +23,9 @@ pub struct Arguments {
     #[clap(default_value_t=String::from("[::1]:8080"),short, long)]
     /// Interface address to bind to (i.e. 127.0.0.1:8080)
     address: String,
+    #[clap(long)]
+    /// JSON file with the deadlines, retries and circuit breaker settings of map agents
+    agent_config: Option<String>,
 }
 
 #[get("/")]
@@ -29,7 +38,16 @@ async fn echo(req_body: String) -> impl Responder {
     HttpResponse::Ok().body(req_body)
 }
 
+// Whether the client asked for the result as GeoJSON rather than the default JSON
+fn accepts_geo_json(req: &HttpRequest) -> bool {
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/geo+json"))
+}
+
 async fn decode(
+    req: HttpRequest,
     params: web::Json<DecodeRequest>,
     context: web::Data<ServerContext>,
 ) -> impl Responder {
@@ -55,25 +73,34 @@ async fn decode(
         _ => return HttpResponse::BadRequest().body("Unable to parse aid as u64"),
     };
 
-    // Retreive a cached Map for the chosen URL, or else create a new one
-    let mut mdbs = context.mdbs.lock().await;
-
-    let mdb = match mdbs.entry(url) {
-        Entry::Occupied(e) => (*e.into_mut()).clone(),
-        Entry::Vacant(e) => {
-            let dup = e.key().clone();
-            match dup.scheme() {
-                "http" | "https" => (*e.insert(Arc::new(HttpMapProxy::new(dup)))).clone(),
-                "grpc" | "grpcs" => {
-                    (*e.insert(Arc::new(GRPCMapProxy::new(dup).await))).clone()
-                }
-                _ => return HttpResponse::BadRequest().body("Unknown URL scheme"),
+    // Retreive a cached Map for the chosen URL, or else create a new one.  A failed
+    // connection is retried by the next request.  The results of a proxy are cached in
+    // turn, and shared by all the requests decoded against that map agent.
+    if !matches!(url.scheme(), "http" | "https" | "grpc" | "grpcs") {
+        return HttpResponse::BadRequest().body("Unknown URL scheme");
+    }
+    let config = context.agents.get(&url).clone();
+    let connect = || async {
+        let map: Arc<dyn Map> = match url.scheme() {
+            "http" | "https" => {
+                let proxy = HttpMapProxy::new_with_timeouts(
+                    url.clone(),
+                    config.connect_timeout(),
+                    config.deadline(),
+                )?;
+                Arc::new(CachingMap::new(proxy, context.map_cache))
             }
-        }
+            _ => {
+                let proxy = GRPCMapProxy::new_with_config(url.clone(), config.clone()).await?;
+                Arc::new(CachingMap::new(proxy, context.map_cache))
+            }
+        };
+        Ok(map)
+    };
+    let mdb = match context.mdbs.get_or_connect(&url, connect).await {
+        Ok(mdb) => mdb,
+        Err(err) => return HttpResponse::BadGateway().body(err.to_string()),
     };
-
-    // Drop the lock on the map hash early so that later panics do not poison the mutex
-    drop(mdbs);
 
     // Retreive the request paramter set from the server context
     let parameter_set = match context.get_param_set(&params.params_key).await {
@@ -81,14 +108,38 @@ async fn decode(
         _ => return HttpResponse::BadRequest().body("Unknown parameter set"),
     };
 
-    let res = openlr::decode_binary(
-        &params.openlr_code,
-        id,
-        mdb.as_ref(),
-        &parameter_set,
-        log_level,
-    )
-    .await;
+    // The location reference is kept to be drawn as GeoJSON
+    let (loc_ref, res) = match openlr::deserialize_binary(&params.openlr_code) {
+        Ok(loc_ref) => {
+            let res = openlr::decode(id, &loc_ref, mdb.as_ref(), &parameter_set, log_level).await;
+            (Some(loc_ref), res)
+        }
+        Err(e) => (
+            None,
+            RequestResult::new(id, Err(e), Duration::new(0, 0), vec![]),
+        ),
+    };
+
+    if accepts_geo_json(&req) {
+        // Draw the LRPs of the location reference along with the decoded location, and
+        // report any decoding error as a foreign member of the FeatureCollection
+        let mut features = match &res.result {
+            Ok(loc) => openlr::geojson::location_features(loc),
+            Err(_) => vec![],
+        };
+        if let Some(loc_ref) = &loc_ref {
+            features.extend(openlr::geojson::location_reference_features(loc_ref));
+        }
+        let mut collection = openlr::geojson::feature_collection(features);
+        collection["id"] = res.id.into();
+        if let Err(e) = &res.result {
+            collection["error"] = e.to_string().into();
+        }
+        return HttpResponse::Ok()
+            .content_type("application/geo+json")
+            .body(collection.to_string());
+    }
+
     HttpResponse::Ok().body(serde_json::to_string(&res).unwrap())
 }
 
@@ -99,7 +150,12 @@ async fn main() -> std::io::Result<()> {
     log::info!("OpenLR server initializing...");
     log::info!("Binding to {}", args.address);
 
-    let context = web::Data::new(ServerContext::new());
+    let mut context = ServerContext::new();
+    if let Some(path) = &args.agent_config {
+        context.agents = AgentConfigs::from_file(path)
+            .map_err(|e| std::io::Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+    }
+    let context = web::Data::new(context);
     HttpServer::new(move || {
         App::new()
             .app_data(context.clone())
@@ -115,7 +171,77 @@ async fn main() -> std::io::Result<()> {
 #[cfg(test)]
 mod test {
 
+    use super::*;
     use crate::main;
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use openlr::in_memory_map::InMemoryMap;
+    use serde_json::Value;
+
+    #[actix_web::test]
+    async fn test_decode_geo_json() {
+        // the map is registered up front, so the agent at the URL is never connected to
+        let url = "http://[::1]:9000/test4";
+        let mut context = ServerContext::new();
+        let map = InMemoryMap::new_from_csv("../openlr/test_data/test4.csv").unwrap();
+        context
+            .add_map_database(Url::parse(url).unwrap(), Arc::new(map))
+            .await;
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(context))
+                .route("/decode", web::post().to(decode)),
+        )
+        .await;
+        let request = |code: &str| {
+            TestRequest::post()
+                .uri("/decode")
+                .insert_header((header::ACCEPT, "application/geo+json"))
+                .set_json(DecodeRequest {
+                    id: "7".to_owned(),
+                    openlr_code: code.to_owned(),
+                    params_key: "default".to_owned(),
+                    url: url.to_owned(),
+                    credentials: String::new(),
+                    log_level: "info".to_owned(),
+                })
+                .to_request()
+        };
+
+        // the decoded line is drawn along with the LRPs of its location reference
+        let response = call_service(&app, request("C/7VOCaEbSu/BP+5AMUrbJEQ")).await;
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/geo+json"
+        );
+        let collection: Value = read_body_json(response).await;
+        assert_eq!(collection["type"], "FeatureCollection");
+        assert_eq!(collection["id"], 7);
+        assert!(collection.get("error").is_none());
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(features.len(), 3);
+        assert_eq!(features[0]["properties"]["locationType"], "Line");
+        assert_eq!(features[1]["properties"]["index"], 0);
+        assert_eq!(features[2]["properties"]["isLast"], true);
+
+        // a location reference which cannot be decoded on the map is still drawn, along
+        // with the error
+        let response = call_service(&app, request("C/+zGCZJgyuvBAAh/x8rHw==")).await;
+        let collection: Value = read_body_json(response).await;
+        assert_eq!(collection["id"], 7);
+        assert!(collection["error"]
+            .as_str()
+            .unwrap()
+            .contains("lies outside the bounding box of the map"));
+        let features = collection["features"].as_array().unwrap();
+        assert_eq!(features.len(), 2);
+        assert!(features.iter().all(|f| f["geometry"]["type"] == "Point"));
+
+        // and one which cannot be read leaves nothing to draw
+        let response = call_service(&app, request("not base64")).await;
+        let collection: Value = read_body_json(response).await;
+        assert!(collection["error"].is_string());
+        assert!(collection["features"].as_array().unwrap().is_empty());
+    }
     #[test]
     fn test_main() {
         main().unwrap()
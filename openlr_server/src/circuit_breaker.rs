@@ -0,0 +1,100 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    // calls go through, and the consecutive failures are counted
+    Closed(u32),
+    // calls fail fast until the given time
+    Open(Instant),
+    // a single trial call, let through at the given time, decides whether to close again
+    HalfOpen(Instant),
+}
+
+/// Tracks the failures of the calls to a map agent, so that once the agent is
+/// considered down calls fail fast instead of each waiting for it, until a trial call
+/// finds that it has recovered
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    state: Mutex<State>,
+    failure_threshold: u32,
+    open_duration: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        CircuitBreaker {
+            state: Mutex::new(State::Closed(0)),
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+        }
+    }
+
+    /// Returns whether a call may go through to the agent.  Once the agent has been down
+    /// for long enough, a single trial call goes through (or another one, should the
+    /// trial call never complete).
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            State::Closed(_) => true,
+            State::Open(until) if Instant::now() < until => false,
+            State::HalfOpen(since) if since.elapsed() < self.open_duration => false,
+            State::Open(_) | State::HalfOpen(_) => {
+                *state = State::HalfOpen(Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Records that the agent answered a call
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = State::Closed(0);
+    }
+
+    /// Records that a call to the agent failed, or timed out
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        *state = match *state {
+            State::Closed(failures) if failures + 1 < self.failure_threshold => {
+                State::Closed(failures + 1)
+            }
+            _ => State::Open(Instant::now() + self.open_duration),
+        };
+    }
+
+    /// Returns whether calls currently fail fast
+    pub fn is_open(&self) -> bool {
+        !matches!(*self.state.lock().unwrap(), State::Closed(_))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_and_recover() {
+        let breaker = CircuitBreaker::new(3, Duration::from_millis(20));
+        breaker.record_failure();
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        breaker.record_failure();
+        assert!(breaker.allow() && !breaker.is_open());
+        breaker.record_failure();
+        assert!(breaker.is_open() && !breaker.allow());
+
+        // after a while a single trial call goes through, and fails
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+        breaker.record_failure();
+        assert!(!breaker.allow());
+
+        // the next trial call succeeds
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(breaker.allow());
+        breaker.record_success();
+        assert!(!breaker.is_open() && breaker.allow());
+    }
+}
@@ -1,5 +1,5 @@
 use geo::{LineString, Coord};
-use openlr::{fow::FOW, frc::FRC, edge::Edge};
+use openlr::{fow::FOW, frc::FRC, edge::Edge, orientation::Orientation, side_of_road::SideOfRoad};
 use crate::openlr_services::Coordinate;
 
 
@@ -29,6 +29,24 @@ pub fn proto_frc_from_frc(frc: &FRC) -> i32 {
     }
 }
 
+pub fn proto_side_of_road_from_side_of_road(side: &SideOfRoad) -> i32 {
+    match side {
+        SideOfRoad::OnRoadOrUnknown => 0,
+        SideOfRoad::Right => 1,
+        SideOfRoad::Left => 2,
+        SideOfRoad::Both => 3,
+    }
+}
+
+pub fn proto_orientation_from_orientation(orientation: &Orientation) -> i32 {
+    match orientation {
+        Orientation::NoOrientationOrUnknown => 0,
+        Orientation::FirstToSecond => 1,
+        Orientation::SecondToFirst => 2,
+        Orientation::BothDirections => 3,
+    }
+}
+
 pub fn proto_edge_from_edge(e: &Edge) -> crate::openlr_services::Edge {
     crate::openlr_services::Edge {
         id: e.id,
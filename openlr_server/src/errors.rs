@@ -8,6 +8,8 @@ pub enum OpenLrServerErr {
     RekwestError(#[from] reqwest::Error),
     #[error("MapDatabase creation error: {0}")]
     MapDatabaseCreationError(String),
+    #[error("Configuration error: {0}")]
+    ConfigurationError(String),
     #[error("Implementation error: {0}")]
     ImplementationError(String),
     #[error("Unknown error encountered during OpenLR processing")]
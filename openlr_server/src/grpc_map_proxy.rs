@@ -1,28 +1,177 @@
 use async_trait::async_trait;
+use crate::agent_config::AgentConfig;
+use crate::circuit_breaker::CircuitBreaker;
 use crate::common::edge_from_proto_edge;
-use geo::Coord;
+use crate::errors::OpenLrServerErr;
+use geo::{Coord, CoordsIter, Polygon, Rect};
+use openlr::in_memory_map::MapEdge;
+use openlr::map_info::{AttributeSemantics, Capabilities, MapInfo};
+use openlr::subgraph::Subgraph;
+use openlr::turn_restriction::TurnRestriction;
 use openlr::{edge::Edge, errors::OpenLrErr, map::Map};
 use reqwest::Url;
-use tonic::transport::Channel;
+use std::fmt;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tonic::transport::{Channel, Endpoint};
+use tonic::{Code, Status};
 
 use crate::openlr_services::{
-    map_agent_client::MapAgentClient, Coordinate, NextEdgesRequest,
-    NearbyEdgesRequest,
+    map_agent_client::MapAgentClient, AreaEdgesRequest, Coordinate, MapInfoRequest,
+    NextEdgesBatchRequest, NextEdgesRequest, NearbyEdgesRequest, PreviousEdgesRequest,
 };
 
+// Why a call to the agent failed
+enum CallErr {
+    // the agent is considered down, so the call was not made
+    CircuitOpen,
+    Status(Status),
+}
+
+impl fmt::Display for CallErr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallErr::CircuitOpen => write!(f, "calls fail fast while the map agent is down"),
+            CallErr::Status(s) => write!(f, "{}", s),
+        }
+    }
+}
+
+// Statuses which a later attempt of the same call may not run into
+fn is_transient(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::Unavailable | Code::DeadlineExceeded | Code::ResourceExhausted | Code::Aborted
+    )
+}
+
 pub struct GRPCMapProxy<Channel> {
+    url: Url,
     client: MapAgentClient<Channel>,
+    config: AgentConfig,
+    breaker: CircuitBreaker,
+    // cleared once the agent turns out not to implement GetNextEdgesBatch or GetSubgraph
+    batches_supported: AtomicBool,
+    subgraphs_supported: AtomicBool,
+    // fetched once on connection, or the default if the agent does not implement GetMapInfo
+    info: MapInfo,
 }
 
 impl GRPCMapProxy<Channel> {
-    pub async fn new(url: Url) -> Self {
-        let client = MapAgentClient::connect(url.to_string()).await.unwrap();
-        GRPCMapProxy { client }
+    pub async fn new(url: Url) -> Result<Self, OpenLrServerErr> {
+        GRPCMapProxy::new_with_config(url, AgentConfig::default()).await
+    }
+
+    /// Constructor that sets the time allowed to connect to the agent and for each call,
+    /// how calls which fail with transient errors are retried, and when the agent is
+    /// considered down
+    pub async fn new_with_config(url: Url, config: AgentConfig) -> Result<Self, OpenLrServerErr> {
+        let connection_err = |e: &dyn std::fmt::Display| {
+            OpenLrServerErr::MapDatabaseCreationError(format!(
+                "Unable to connect to map agent at {}: {}",
+                url, e
+            ))
+        };
+        let channel = Endpoint::from_shared(url.to_string())
+            .map_err(|e| connection_err(&e))?
+            .connect_timeout(config.connect_timeout())
+            .connect()
+            .await
+            .map_err(|e| connection_err(&e))?;
+        let proxy = GRPCMapProxy {
+            url: url.clone(),
+            client: MapAgentClient::new(channel),
+            breaker: CircuitBreaker::new(config.failure_threshold, config.open_duration()),
+            config,
+            batches_supported: AtomicBool::new(true),
+            subgraphs_supported: AtomicBool::new(true),
+            info: MapInfo::default(),
+        };
+        let info = match proxy
+            .call(MapInfoRequest {}, |mut c, r| async move { c.get_map_info(r).await })
+            .await
+        {
+            Ok(info) => Some(map_info_from_proto(info)),
+            Err(CallErr::Status(s)) if s.code() == Code::Unimplemented => None,
+            Err(e) => {
+                return Err(OpenLrServerErr::MapDatabaseCreationError(format!(
+                    "Unable to retrieve the map info of map agent at {}: {}",
+                    url, e
+                )))
+            }
+        };
+        // Agents which describe their map tell us up front which optional requests they
+        // serve; the others are probed with them
+        Ok(GRPCMapProxy {
+            batches_supported: AtomicBool::new(
+                info.as_ref().is_none_or(|i| i.capabilities.next_edges_batch),
+            ),
+            subgraphs_supported: AtomicBool::new(
+                info.as_ref().is_none_or(|i| i.capabilities.subgraph_prefetch),
+            ),
+            info: info.unwrap_or_default(),
+            ..proxy
+        })
+    }
+
+    // Makes a call to the agent within the deadline, repeating it after a backoff should it
+    // fail with a transient error, unless the agent is considered down
+    async fn call<M, T, F, Fut>(&self, message: M, call: F) -> Result<T, CallErr>
+    where
+        M: Clone,
+        F: Fn(MapAgentClient<Channel>, tonic::Request<M>) -> Fut,
+        Fut: Future<Output = Result<tonic::Response<T>, Status>>,
+    {
+        if !self.breaker.allow() {
+            return Err(CallErr::CircuitOpen);
+        }
+        let mut retry = 0;
+        loop {
+            let attempt = tokio::time::timeout(
+                self.config.deadline(),
+                call(self.client.clone(), tonic::Request::new(message.clone())),
+            );
+            let status = match attempt.await {
+                Ok(Ok(response)) => {
+                    self.breaker.record_success();
+                    return Ok(response.into_inner());
+                }
+                Ok(Err(status)) => status,
+                Err(_) => Status::deadline_exceeded(format!(
+                    "No response from the map agent within {}ms",
+                    self.config.deadline_ms
+                )),
+            };
+            // any answer other than a transient error shows that the agent is up
+            if !is_transient(&status) {
+                self.breaker.record_success();
+                return Err(CallErr::Status(status));
+            }
+            self.breaker.record_failure();
+            if retry >= self.config.max_retries || self.breaker.is_open() {
+                return Err(CallErr::Status(status));
+            }
+            tokio::time::sleep(self.config.backoff(retry)).await;
+            retry += 1;
+        }
+    }
+
+    // Reports a failed call as the given kind of error, or as the agent being down
+    fn call_err(&self, e: CallErr, err: fn(String) -> OpenLrErr) -> OpenLrErr {
+        match e {
+            CallErr::CircuitOpen => OpenLrErr::AgentCircuitOpen(self.url.to_string()),
+            CallErr::Status(s) => err(s.to_string()),
+        }
     }
 }
 
 #[async_trait]
 impl Map for GRPCMapProxy<Channel> {
+    /// Returns the map info retrieved from the agent on connection
+    async fn get_info(&self) -> Result<MapInfo, OpenLrErr> {
+        Ok(self.info.clone())
+    }
+
     async fn get_nearby_edges(
         &self,
         points: Vec<Coord>,
@@ -38,14 +187,11 @@ impl Map for GRPCMapProxy<Channel> {
                 .collect::<Vec<Coordinate>>(),
                 radius
         };
-        let request = tonic::Request::new(rsr);
-        let mut c = self.client.clone();
-        let response = c
-            .get_nearby_edges(request)
+        let response = self
+            .call(rsr, |mut c, r| async move { c.get_nearby_edges(r).await })
             .await
-            .map_err(|s| OpenLrErr::NearbyEdgesError(s.to_string()))?;
+            .map_err(|e| self.call_err(e, OpenLrErr::NearbyEdgesError))?;
         Ok(response
-            .into_inner()
             .edge_sets
             .iter()
             .map(|es| {
@@ -61,17 +207,304 @@ impl Map for GRPCMapProxy<Channel> {
     /// is equal to the set of outgoing lines of the end node of this line.
     async fn get_next_edges(&self, id: i64, meta: String) -> Result<Vec<Edge>, OpenLrErr> {
         let ner = NextEdgesRequest { id, meta };
-        let request = tonic::Request::new(ner);
-        let mut c = self.client.clone();
-        let response = c
-            .get_next_edges(request)
+        let response = self
+            .call(ner, |mut c, r| async move { c.get_next_edges(r).await })
             .await
-            .map_err(|s| OpenLrErr::NextEdgeError(s.to_string()))?;
+            .map_err(|e| self.call_err(e, OpenLrErr::NextEdgeError))?;
         Ok(response
-            .into_inner()
             .edges
             .iter()
             .map(|e| edge_from_proto_edge(e))
             .collect::<Vec<Edge>>())
     }
+
+    /// Returns a set of lines which precede this line in the same direction. The set of lines
+    /// is equal to the set of incoming lines of the start node of this line.
+    async fn get_previous_edges(&self, id: i64, meta: String) -> Result<Vec<Edge>, OpenLrErr> {
+        let per = PreviousEdgesRequest { id, meta };
+        let response = self
+            .call(per, |mut c, r| async move { c.get_previous_edges(r).await })
+            .await
+            .map_err(|e| self.call_err(e, OpenLrErr::PreviousEdgeError))?;
+        Ok(response
+            .edges
+            .iter()
+            .map(edge_from_proto_edge)
+            .collect::<Vec<Edge>>())
+    }
+
+    /// Returns the sets of lines which follow each of the given lines with a single
+    /// GetNextEdgesBatch request, or with a GetNextEdges request per line if the agent
+    /// does not implement batches.
+    async fn get_next_edges_many(
+        &self,
+        edges: &[(i64, String)],
+    ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
+        if self.batches_supported.load(Ordering::Relaxed) {
+            let nebr = NextEdgesBatchRequest {
+                edges: edges
+                    .iter()
+                    .map(|(id, meta)| NextEdgesRequest {
+                        id: *id,
+                        meta: meta.clone(),
+                    })
+                    .collect::<Vec<NextEdgesRequest>>(),
+            };
+            let response = self
+                .call(nebr, |mut c, r| async move { c.get_next_edges_batch(r).await })
+                .await;
+            match response {
+                Ok(response) => {
+                    let edge_sets = response.edge_sets;
+                    if edge_sets.len() != edges.len() {
+                        return Err(OpenLrErr::NextEdgeError(format!(
+                            "The map agent returned {} edge sets for {} edges",
+                            edge_sets.len(),
+                            edges.len()
+                        )));
+                    }
+                    return Ok(edge_sets
+                        .iter()
+                        .map(|es| {
+                            es.edges
+                                .iter()
+                                .map(edge_from_proto_edge)
+                                .collect::<Vec<Edge>>()
+                        })
+                        .collect::<Vec<Vec<Edge>>>());
+                }
+                Err(CallErr::Status(s)) if s.code() == Code::Unimplemented => {
+                    self.batches_supported.store(false, Ordering::Relaxed);
+                }
+                Err(e) => return Err(self.call_err(e, OpenLrErr::NextEdgeError)),
+            }
+        }
+
+        let mut next_edges = Vec::with_capacity(edges.len());
+        for (id, meta) in edges {
+            next_edges.push(self.get_next_edges(*id, meta.clone()).await?);
+        }
+        Ok(next_edges)
+    }
+
+    /// Returns all lines whose geometry intersects the given area
+    async fn get_edges_in_area(&self, area: Polygon) -> Result<Vec<Edge>, OpenLrErr> {
+        let response = self
+            .call(area_edges_request(&area), |mut c, r| async move {
+                c.get_edges_in_area(r).await
+            })
+            .await
+            .map_err(|e| self.call_err(e, OpenLrErr::AreaSearchError))?;
+        Ok(response
+            .edges
+            .iter()
+            .map(edge_from_proto_edge)
+            .collect::<Vec<Edge>>())
+    }
+
+    /// Returns the subgraph of all lines whose geometry intersects the given area, or None
+    /// if the agent does not implement GetSubgraph
+    async fn get_subgraph(&self, area: Polygon) -> Result<Option<Subgraph>, OpenLrErr> {
+        if !self.subgraphs_supported.load(Ordering::Relaxed) {
+            return Ok(None);
+        }
+        let response = self
+            .call(area_edges_request(&area), |mut c, r| async move {
+                c.get_subgraph(r).await
+            })
+            .await;
+        let subgraph = match response {
+            Ok(subgraph) => subgraph,
+            Err(CallErr::Status(s)) if s.code() == Code::Unimplemented => {
+                self.subgraphs_supported.store(false, Ordering::Relaxed);
+                return Ok(None);
+            }
+            Err(e) => return Err(self.call_err(e, OpenLrErr::SubgraphError)),
+        };
+        let edges = subgraph
+            .edges
+            .iter()
+            .map(|se| match &se.edge {
+                Some(e) => Ok(MapEdge::new(edge_from_proto_edge(e), se.start_node, se.end_node)),
+                None => Err(OpenLrErr::SubgraphError(
+                    "The map agent returned a subgraph edge without an edge".to_owned(),
+                )),
+            })
+            .collect::<Result<Vec<MapEdge>, OpenLrErr>>()?;
+        Ok(Some(Subgraph::new(
+            edges,
+            subgraph
+                .turn_restrictions
+                .into_iter()
+                .map(|r| TurnRestriction::new(r.edges))
+                .collect::<Vec<TurnRestriction>>(),
+        )))
+    }
+}
+
+fn area_edges_request(area: &Polygon) -> AreaEdgesRequest {
+    AreaEdgesRequest {
+        boundary: area
+            .exterior()
+            .coords_iter()
+            .map(|c| Coordinate {
+                longitude: c.x,
+                latitude: c.y,
+            })
+            .collect::<Vec<Coordinate>>(),
+    }
+}
+
+fn map_info_from_proto(info: crate::openlr_services::MapInfo) -> MapInfo {
+    let semantics = |s: String| {
+        if s.is_empty() {
+            AttributeSemantics::OpenLr
+        } else {
+            AttributeSemantics::Derived(s)
+        }
+    };
+    let capabilities = info.capabilities.unwrap_or_default();
+    MapInfo {
+        bounding_box: info.bounding_box.and_then(|bb| match (bb.lower_left, bb.upper_right) {
+            (Some(ll), Some(ur)) => Some(Rect::new(
+                Coord {
+                    x: ll.longitude,
+                    y: ll.latitude,
+                },
+                Coord {
+                    x: ur.longitude,
+                    y: ur.latitude,
+                },
+            )),
+            _ => None,
+        }),
+        vendor: info.vendor,
+        version: info.version,
+        capabilities: Capabilities {
+            next_edges_batch: capabilities.next_edges_batch,
+            subgraph_prefetch: capabilities.subgraph_prefetch,
+            turn_restrictions: capabilities.turn_restrictions,
+            area_search: capabilities.area_search,
+        },
+        fow_semantics: semantics(info.fow_semantics),
+        frc_semantics: semantics(info.frc_semantics),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openlr_services::map_agent_server::{MapAgent, MapAgentServer};
+    use crate::openlr_services::{
+        EdgeSet, NearbyEdgesResponse, NextEdgesBatchResponse, Subgraph as ProtoSubgraph,
+    };
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use tonic::{Request, Response};
+
+    // An agent which hangs on the next edges of edge 1, and is unavailable otherwise
+    struct FailingAgent {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[tonic::async_trait]
+    impl MapAgent for FailingAgent {
+        async fn get_nearby_edges(
+            &self,
+            _request: Request<NearbyEdgesRequest>,
+        ) -> Result<Response<NearbyEdgesResponse>, Status> {
+            Err(Status::unimplemented("not served"))
+        }
+
+        async fn get_next_edges(
+            &self,
+            request: Request<NextEdgesRequest>,
+        ) -> Result<Response<EdgeSet>, Status> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            if request.get_ref().id == 1 {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            Err(Status::unavailable("down"))
+        }
+
+        async fn get_previous_edges(
+            &self,
+            _request: Request<PreviousEdgesRequest>,
+        ) -> Result<Response<EdgeSet>, Status> {
+            Err(Status::unimplemented("not served"))
+        }
+
+        async fn get_next_edges_batch(
+            &self,
+            _request: Request<NextEdgesBatchRequest>,
+        ) -> Result<Response<NextEdgesBatchResponse>, Status> {
+            Err(Status::unimplemented("not served"))
+        }
+
+        async fn get_edges_in_area(
+            &self,
+            _request: Request<AreaEdgesRequest>,
+        ) -> Result<Response<EdgeSet>, Status> {
+            Err(Status::unimplemented("not served"))
+        }
+
+        async fn get_subgraph(
+            &self,
+            _request: Request<AreaEdgesRequest>,
+        ) -> Result<Response<ProtoSubgraph>, Status> {
+            Err(Status::unimplemented("not served"))
+        }
+
+        async fn get_map_info(
+            &self,
+            _request: Request<MapInfoRequest>,
+        ) -> Result<Response<crate::openlr_services::MapInfo>, Status> {
+            Err(Status::unimplemented("not served"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deadline_retries_and_circuit_breaker() {
+        let address = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let agent = FailingAgent {
+            calls: calls.clone(),
+        };
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(MapAgentServer::new(agent))
+                .serve(address),
+        );
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let config = AgentConfig {
+            deadline_ms: 50,
+            max_retries: 2,
+            initial_backoff_ms: 1,
+            failure_threshold: 6,
+            open_ms: 60_000,
+            ..Default::default()
+        };
+        let url = Url::parse(&format!("http://{}", address)).unwrap();
+        let proxy = GRPCMapProxy::new_with_config(url, config).await.unwrap();
+
+        // a hung call times out, and is retried twice
+        let start = Instant::now();
+        let result = proxy.get_next_edges(1, String::new()).await;
+        assert!(matches!(result, Err(OpenLrErr::NextEdgeError(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        // after six failed attempts in a row, calls fail fast without reaching the agent
+        let result = proxy.get_next_edges(2, String::new()).await;
+        assert!(matches!(result, Err(OpenLrErr::NextEdgeError(_))));
+        assert_eq!(calls.load(Ordering::Relaxed), 6);
+        let result = proxy.get_next_edges(2, String::new()).await;
+        assert!(matches!(result, Err(OpenLrErr::AgentCircuitOpen(_))));
+        assert_eq!(calls.load(Ordering::Relaxed), 6);
+    }
 }
@@ -1,26 +1,34 @@
-use openlr::{map::Map, decoding_parameters::DecodingParameters};
+use crate::agent_config::AgentConfigs;
+use crate::mdb_registry::MdbRegistry;
+use openlr::{caching_map::CacheConfig, map::Map, decoding_parameters::DecodingParameters};
 use std::{collections::HashMap, sync::Arc};
 use tokio::sync::Mutex;
 use url::Url;
 
 pub struct GrpcServerContext {
-    pub mdbs: Arc<Mutex<HashMap<Url,Arc<dyn Map>>>>,
+    pub mdbs: MdbRegistry,
     pub params: Mutex<HashMap<String, Arc<DecodingParameters>>>,
+    // bounds of the cache wrapped around each map agent proxy in mdbs
+    pub map_cache: CacheConfig,
+    // how the proxy of each map agent calls it
+    pub agents: AgentConfigs,
 }
 
 impl GrpcServerContext {
     pub fn new() -> Self {
         GrpcServerContext {
-            mdbs: Arc::new(Mutex::new(HashMap::new())),
+            mdbs: MdbRegistry::new(),
             params: Mutex::new(HashMap::from([(
                 "default".to_owned(),
                 Arc::new(DecodingParameters::default()),
             )])),
+            map_cache: CacheConfig::default(),
+            agents: AgentConfigs::default(),
         }
     }
 
     pub async fn add_map_database(&mut self, key:Url, value: Arc<dyn Map>) {
-        self.mdbs.lock().await.insert(key,value);
+        self.mdbs.insert(key, value);
     }
 
     pub async fn add_param_set(&mut self, key:String, value: DecodingParameters) {
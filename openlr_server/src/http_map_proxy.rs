@@ -1,47 +1,87 @@
-use geo::Coord;
-use openlr::{map::Map, edge::Edge, errors::OpenLrErr};
+//! Proxy for map agents which speak JSON over HTTP(S).
+//!
+//! A REST map agent serves these endpoints below its base URL, all of which
+//! accept and return `application/json`:
+//!
+//! - `POST <base>/nearby_edges` with a [`RadiusSearchRequest`] body, i.e.
+//!   `{"points": [{"lon": 4.9, "lat": 52.3, "radius": 100}, ...]}`.  The response
+//!   is a [`RadiusSearchResponse`]: an array holding, for each point in the
+//!   request and in the same order, an array of the edges within `radius`
+//!   meters of that point.
+//! - `POST <base>/next_edges` with a [`NextEdgeRequest`] body, i.e.
+//!   `{"id": 42, "meta": "..."}`.  The response is a [`NextEdgeResponse`]: an
+//!   array of the edges leaving the end node of the given edge, excluding the
+//!   edge leading straight back (U-turn).
+//! - `POST <base>/previous_edges` with the same body as `next_edges`, returning
+//!   the edges arriving at the start node of the given edge, excluding U-turns.
+//!   Only the encoder uses it, to move LRPs to valid nodes, so agents may leave it out.
+//!
+//! Edges are objects of the form
+//! `{"id": 42, "meta": "...", "len": 120, "fow": 3, "frc": 5, "geom": [[lon, lat], ...]}`,
+//! where `len` is the length in meters, `fow` and `frc` are the numeric OpenLR
+//! values (0-7), and `geom` holds at least two points in the direction of travel.
+//! The optional `meta` string is passed back to the agent unchanged in
+//! `next_edges` requests.
+//!
+//! Any status other than 2xx is treated as a failed request, and the response
+//! body is reported as the reason.
+
+use std::time::Duration;
+
 use async_trait::async_trait;
-use reqwest::Url;
-use serde::Serialize;
+use geo::Coord;
+use openlr::{edge::Edge, errors::OpenLrErr, map::Map};
+use reqwest::{Client, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::errors::OpenLrServerErr;
 
-#[derive(Debug, Serialize)]
+/// Time allowed to establish a connection to the map agent
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time allowed for a complete request to the map agent
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RadiusSearchRequestPoint {
     pub lon: f64,
     pub lat: f64,
     pub radius: u32,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RadiusSearchRequest {
     pub points: Vec<RadiusSearchRequestPoint>
 }
 
 impl RadiusSearchRequest {
     pub fn new(points: &Vec<Coord>, radius: u32) -> Self {
-        RadiusSearchRequest { 
-            points:points.iter().map(|c| 
-                RadiusSearchRequestPoint{ 
-                    lon: c.x, 
-                    lat: c.y, 
-                    radius 
+        RadiusSearchRequest {
+            points:points.iter().map(|c|
+                RadiusSearchRequestPoint{
+                    lon: c.x,
+                    lat: c.y,
+                    radius
                 }
             )
-            .collect::<Vec<RadiusSearchRequestPoint>>() 
+            .collect::<Vec<RadiusSearchRequestPoint>>()
         }
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct RadiusSearchResponse (Vec<Edge>);
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RadiusSearchResponse (pub Vec<Vec<Edge>>);
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NextEdgeRequest {
-    pub id: u64,
+    pub id: i64,
+    #[serde(default)]
     pub meta: String
 }
 
 impl NextEdgeRequest {
-    pub fn new(id: u64, meta: String) -> Self {
+    pub fn new(id: i64, meta: String) -> Self {
         NextEdgeRequest {
             id,
             meta
@@ -49,16 +89,76 @@ impl NextEdgeRequest {
     }
 }
 
-#[derive(Debug, Serialize)]
-pub struct NextEdgeResponse (Vec<Edge>);
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NextEdgeResponse (pub Vec<Edge>);
 
 pub struct HttpMapProxy {
     url: Url,
+    client: Client,
 }
 
 impl HttpMapProxy {
-    pub fn new(url: Url) -> Self {
-        HttpMapProxy {url}
+    pub fn new(url: Url) -> Result<Self, OpenLrServerErr> {
+        HttpMapProxy::new_with_timeouts(url, DEFAULT_CONNECT_TIMEOUT, DEFAULT_REQUEST_TIMEOUT)
+    }
+
+    /// Constructor that sets the time allowed to connect to the agent, and to
+    /// complete each request
+    pub fn new_with_timeouts(
+        url: Url,
+        connect_timeout: Duration,
+        request_timeout: Duration,
+    ) -> Result<Self, OpenLrServerErr> {
+        if url.cannot_be_a_base() {
+            return Err(OpenLrServerErr::MapDatabaseCreationError(format!(
+                "Invalid map agent URL: {}",
+                url
+            )));
+        }
+
+        // The client keeps a pool of connections to the agent, which are reused
+        // across requests
+        let client = Client::builder()
+            .connect_timeout(connect_timeout)
+            .timeout(request_timeout)
+            .build()?;
+        Ok(HttpMapProxy { url, client })
+    }
+
+    // Return the URL of an endpoint below the agent's base URL
+    fn endpoint(&self, name: &str) -> Url {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .expect("base URLs have path segments")
+            .pop_if_empty()
+            .push(name);
+        url
+    }
+
+    // POST a JSON request to an endpoint of the agent, and parse the JSON response
+    async fn post<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        name: &str,
+        request: &Req,
+    ) -> Result<Resp, String> {
+        let url = self.endpoint(name);
+        let response = self
+            .client
+            .post(url.clone())
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| format!("Request to {} failed: {}", url, e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(format!("{} responded with {}: {}", url, status, body));
+        }
+        response
+            .json::<Resp>()
+            .await
+            .map_err(|e| format!("Invalid response from {}: {}", url, e))
     }
 }
 
@@ -69,7 +169,21 @@ impl Map for HttpMapProxy {
         points: Vec<Coord>,
         radius: u32,
     ) -> Result<Vec<Vec<Edge>>, OpenLrErr> {
-        todo!()
+        let response: RadiusSearchResponse = self
+            .post("nearby_edges", &RadiusSearchRequest::new(&points, radius))
+            .await
+            .map_err(OpenLrErr::NearbyEdgesError)?;
+
+        // The decoder relies on one set of edges per point
+        if response.0.len() != points.len() {
+            return Err(OpenLrErr::NearbyEdgesError(format!(
+                "Expected {} sets of edges from {}, but received {}",
+                points.len(),
+                self.url,
+                response.0.len()
+            )));
+        }
+        Ok(response.0)
     }
 
     /// Returns a set of lines which follows this line in the same direction. The set of lines
@@ -79,6 +193,57 @@ impl Map for HttpMapProxy {
         id: i64,
         meta: String,
     ) -> Result<Vec<Edge>, OpenLrErr> {
-        todo!()
+        let response: NextEdgeResponse = self
+            .post("next_edges", &NextEdgeRequest::new(id, meta))
+            .await
+            .map_err(OpenLrErr::NextEdgeError)?;
+        Ok(response.0)
+    }
+
+    /// Returns a set of lines which precede this line in the same direction. The set of lines
+    /// is equal to the set of incoming lines of the start node of this line.
+    async fn get_previous_edges(
+        &self,
+        id: i64,
+        meta: String,
+    ) -> Result<Vec<Edge>, OpenLrErr> {
+        let response: NextEdgeResponse = self
+            .post("previous_edges", &NextEdgeRequest::new(id, meta))
+            .await
+            .map_err(OpenLrErr::PreviousEdgeError)?;
+        Ok(response.0)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint() {
+        for base in ["http://localhost:8080/agent", "http://localhost:8080/agent/"] {
+            let proxy = HttpMapProxy::new(Url::parse(base).unwrap()).unwrap();
+            assert_eq!(
+                proxy.endpoint("next_edges").as_str(),
+                "http://localhost:8080/agent/next_edges"
+            );
+        }
+        let proxy = HttpMapProxy::new(Url::parse("https://example.com").unwrap()).unwrap();
+        assert_eq!(
+            proxy.endpoint("nearby_edges").as_str(),
+            "https://example.com/nearby_edges"
+        );
+    }
+
+    #[test]
+    fn test_next_edge_response() {
+        let response: NextEdgeResponse = serde_json::from_str(
+            r#"[{"id": -42, "meta": "m", "len": 120, "fow": 3, "frc": 5,
+                 "geom": [[4.9, 52.3], [4.91, 52.31]]}]"#,
+        )
+        .unwrap();
+        assert_eq!(response.0.len(), 1);
+        assert_eq!(response.0[0].id, -42);
+        assert_eq!(response.0[0].len, 120);
+    }
+}
@@ -2,8 +2,12 @@ pub mod openlr_services {
     tonic::include_proto!("openlr_services");
 }
 
+pub mod agent_config;
+pub mod circuit_breaker;
 pub mod decode_request;
+pub mod errors;
 pub mod http_map_proxy;
+pub mod mdb_registry;
 pub mod grpc_map_proxy;
 pub mod server_context;
 pub mod grpc_server_context;
@@ -0,0 +1,156 @@
+//! The map agents requests are decoded against, each connected to once and then shared by
+//! all requests for it.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use openlr::map::Map;
+use tokio::sync::OnceCell;
+use url::Url;
+
+use crate::errors::OpenLrServerErr;
+
+// The map of an agent, once connected, and the last failed attempt to connect to it
+#[derive(Default)]
+struct AgentEntry {
+    map: OnceCell<Arc<dyn Map>>,
+    failure: Mutex<Option<(Instant, String)>>,
+}
+
+impl AgentEntry {
+    // The reason the last attempt to connect failed, should it have failed after the
+    // given time
+    fn failure_since(&self, since: Instant) -> Option<String> {
+        match &*self.failure.lock().unwrap() {
+            Some((at, reason)) if *at > since => Some(reason.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// The maps of the agents connected to so far, by URL
+#[derive(Default)]
+pub struct MdbRegistry {
+    agents: Mutex<HashMap<Url, Arc<AgentEntry>>>,
+}
+
+impl MdbRegistry {
+    pub fn new() -> Self {
+        MdbRegistry::default()
+    }
+
+    /// Registers the map of the agent at the given URL, replacing any connected before
+    pub fn insert(&self, url: Url, map: Arc<dyn Map>) {
+        let entry = AgentEntry {
+            map: OnceCell::new_with(Some(map)),
+            ..Default::default()
+        };
+        self.agents.lock().unwrap().insert(url, Arc::new(entry));
+    }
+
+    /// Returns the map of the agent at the given URL, connecting to it with `connect`
+    /// unless already connected.  Only the requests for the same agent wait for a
+    /// connection to be made.  Should it fail, those requests fail with it, and the next
+    /// request for the agent tries to connect again.
+    pub async fn get_or_connect<F, Fut>(
+        &self,
+        url: &Url,
+        connect: F,
+    ) -> Result<Arc<dyn Map>, OpenLrServerErr>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Arc<dyn Map>, OpenLrServerErr>>,
+    {
+        let arrived = Instant::now();
+        let entry = self
+            .agents
+            .lock()
+            .unwrap()
+            .entry(url.clone())
+            .or_default()
+            .clone();
+        let map = entry
+            .map
+            .get_or_try_init(|| async {
+                // requests which waited for a connection which failed do not try again
+                if let Some(reason) = entry.failure_since(arrived) {
+                    return Err(OpenLrServerErr::MapDatabaseCreationError(format!(
+                        "Unable to connect to {}: {}",
+                        url, reason
+                    )));
+                }
+                connect().await.inspect_err(|err| {
+                    *entry.failure.lock().unwrap() = Some((Instant::now(), err.to_string()));
+                })
+            })
+            .await?;
+        Ok(map.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openlr::in_memory_map::InMemoryMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_get_or_connect() {
+        let registry = Arc::new(MdbRegistry::new());
+        let url = Url::parse("grpc://[::1]:8081").unwrap();
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        // concurrent requests for an agent connect to it once
+        let requests = (0..4).map(|_| {
+            let (registry, url, attempts) = (registry.clone(), url.clone(), attempts.clone());
+            tokio::spawn(async move {
+                registry
+                    .get_or_connect(&url, || async {
+                        attempts.fetch_add(1, Ordering::Relaxed);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Ok(Arc::new(InMemoryMap::new(vec![]).unwrap()) as Arc<dyn Map>)
+                    })
+                    .await
+            })
+        });
+        for request in requests.collect::<Vec<_>>() {
+            assert!(request.await.unwrap().is_ok());
+        }
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+
+        // requests which wait for a connection which fails fail with it, while the next
+        // request tries again
+        let down = Url::parse("grpc://[::1]:8082").unwrap();
+        attempts.store(0, Ordering::Relaxed);
+        let requests = (0..4).map(|_| {
+            let (registry, down, attempts) = (registry.clone(), down.clone(), attempts.clone());
+            tokio::spawn(async move {
+                registry
+                    .get_or_connect(&down, || async {
+                        attempts.fetch_add(1, Ordering::Relaxed);
+                        tokio::time::sleep(Duration::from_millis(20)).await;
+                        Err(OpenLrServerErr::MapDatabaseCreationError(
+                            "refused".to_owned(),
+                        ))
+                    })
+                    .await
+            })
+        });
+        for request in requests.collect::<Vec<_>>() {
+            let result = request.await.unwrap();
+            assert!(result.err().unwrap().to_string().contains("refused"));
+        }
+        assert_eq!(attempts.load(Ordering::Relaxed), 1);
+        let result = registry
+            .get_or_connect(&down, || async {
+                attempts.fetch_add(1, Ordering::Relaxed);
+                Ok(Arc::new(InMemoryMap::new(vec![]).unwrap()) as Arc<dyn Map>)
+            })
+            .await;
+        assert!(result.is_ok());
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+    }
+}
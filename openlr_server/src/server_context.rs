@@ -1,11 +1,17 @@
-use openlr::{map::Map, decoding_parameters::DecodingParameters};
+use crate::agent_config::AgentConfigs;
+use crate::mdb_registry::MdbRegistry;
+use openlr::{caching_map::CacheConfig, map::Map, decoding_parameters::DecodingParameters};
 use tokio::sync::Mutex;
 use std::{collections::HashMap, sync::Arc};
 use url::Url;
 
 pub struct ServerContext {
-    pub mdbs: Mutex<HashMap<Url, Arc<dyn Map>>>,
+    pub mdbs: MdbRegistry,
     pub params: Mutex<HashMap<String, Arc<DecodingParameters>>>,
+    // bounds of the cache wrapped around each map agent proxy in mdbs
+    pub map_cache: CacheConfig,
+    // how the proxy of each map agent calls it
+    pub agents: AgentConfigs,
 }
 
 unsafe impl Sync for ServerContext {}
@@ -13,16 +19,18 @@ unsafe impl Sync for ServerContext {}
 impl ServerContext {
     pub fn new() -> Self {
         ServerContext {
-            mdbs: Mutex::new(HashMap::new()),
+            mdbs: MdbRegistry::new(),
             params: Mutex::new(HashMap::from([(
                 "default".to_owned(),
                 Arc::new(DecodingParameters::default()),
             )])),
+            map_cache: CacheConfig::default(),
+            agents: AgentConfigs::default(),
         }
     }
 
     pub async fn add_map_database(&mut self, key:Url, value: Arc<dyn Map>) {
-        self.mdbs.lock().await.insert(key,value);
+        self.mdbs.insert(key, value);
     }
 
     pub async fn add_param_set(&mut self, key:String, value: DecodingParameters) {